@@ -0,0 +1,20 @@
+select
+    a.id,
+    a.title,
+    a.message,
+    a.mod_de,
+    a.mod_timp,
+    b.read_timp
+from portal.tbl_int_announcements as a
+
+left join portal.tbl_int_announcement_reads as b
+on b.announcement_id = a.id and b.user_id = $1
+
+where exists (
+        select 1
+        from portal.tbl_int_user_roles as r
+        where r.user_id = $1 and r.group_id = any(a.groups)
+    )
+    and (not $2 or b.read_timp is null)
+order by a.mod_timp desc
+limit 1000
\ No newline at end of file
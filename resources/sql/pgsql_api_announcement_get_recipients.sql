@@ -0,0 +1,10 @@
+select distinct
+    u.user_id,
+    u.language
+from portal.tbl_int_user_roles as r
+
+inner join portal.tbl_int_users as u
+on r.user_id = u.user_id
+
+where r.group_id = any($1)
+order by u.user_id
\ No newline at end of file
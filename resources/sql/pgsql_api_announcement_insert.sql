@@ -0,0 +1,24 @@
+with announcement as (
+    insert into portal.tbl_int_announcements (title, message, groups, mod_de)
+    select $1, $2, $3, $4
+    where (
+        select count(*)
+        from portal.tbl_int_user_groups as a
+        where a.group_id = any($3)
+    ) = cardinality(array(select distinct unnest($3::text[])))
+    returning *
+), mails as (
+    insert into portal.tbl_int_mail_outbox (kind, user_id, to_addrs, subject, html, text_body)
+    select 'announcement', u.user_id, array[u.email], m.subject, m.html, m.text_body
+    from announcement as a
+
+    cross join unnest($5::text[], $6::int8[]) as r (user_id, mail_idx)
+
+    inner join unnest($7::text[], $8::text[], $9::text[]) with ordinality as m (subject, html, text_body, mail_idx)
+    on m.mail_idx = r.mail_idx
+
+    inner join portal.tbl_int_users as u
+    on u.user_id = r.user_id
+)
+select a.*
+from announcement as a
\ No newline at end of file
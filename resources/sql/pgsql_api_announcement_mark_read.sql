@@ -0,0 +1,11 @@
+insert into portal.tbl_int_announcement_reads (announcement_id, user_id)
+select a.id, $2
+from portal.tbl_int_announcements as a
+where a.id = $1
+    and exists (
+        select 1
+        from portal.tbl_int_user_roles as r
+        where r.user_id = $2 and r.group_id = any(a.groups)
+    )
+on conflict (announcement_id, user_id) do update set
+    read_timp = portal.tbl_int_announcement_reads.read_timp
\ No newline at end of file
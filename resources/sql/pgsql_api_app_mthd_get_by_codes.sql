@@ -0,0 +1,4 @@
+select a.*
+from portal.tbl_int_app_transactions as a
+    inner join unnest($1::text[], $2::text[]) as b (app_code, method_code)
+        on a.app_code = b.app_code and a.method_code = b.method_code
\ No newline at end of file
@@ -1,4 +1,4 @@
-select
-    *
-from portal.tbl_int_app_transactions as a
+select
+    *
+from portal.tbl_int_app_transactions as a
 where a.app_code = $1;
\ No newline at end of file
@@ -0,0 +1,5 @@
+select a.*
+from portal.tbl_int_app_transactions_history as a
+where a.app_method_id = $1
+order by a.mod_timp desc, a.id
+limit 1000
\ No newline at end of file
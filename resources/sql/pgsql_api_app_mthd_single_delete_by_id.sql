@@ -1,2 +1,4 @@
-delete from portal.tbl_int_app_transactions
-where id = $1;
\ No newline at end of file
+delete from portal.tbl_int_app_transactions
+where id = $1
+    and ($3::text[] is null or '*' = any($3) or to_char(mod_timp, 'YYYYMMDDHH24MISSUS') = any($3))
+    and set_config('portal.mod_de', $2, true) is not null;
\ No newline at end of file
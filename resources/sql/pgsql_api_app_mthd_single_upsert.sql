@@ -1,7 +1,12 @@
-insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
-values ($1, $2, $3, $4)
-on conflict (app_code, method_code) do update set
-    descr = excluded.descr,
-    mod_de = excluded.mod_de,
-    mod_timp = current_timestamp
+insert into portal.tbl_int_app_transactions as a (app_code, method_code, descr, mod_de)
+select $1, $2, $3, $4
+where $5::text[] is null
+    or exists (select 1 from portal.tbl_int_app_transactions as b where b.app_code = $1 and b.method_code = $2)
+on conflict (app_code, method_code) do update set
+    descr = excluded.descr,
+    mod_de = excluded.mod_de,
+    mod_timp = current_timestamp
+where $5::text[] is null
+    or '*' = any($5)
+    or to_char(a.mod_timp, 'YYYYMMDDHH24MISSUS') = any($5)
 returning *;
\ No newline at end of file
@@ -0,0 +1,13 @@
+select
+    b.id,
+    b.app_code,
+    b.method_code,
+    (select count(*) from portal.tbl_int_user_authorization as c where c.app_method_id = b.id) as grants
+from portal.tbl_int_app_transactions as b
+where b.app_code = $3
+    and not exists (
+        select 1
+        from unnest($1::text[], $2::text[]) as a (app_code, method_code)
+        where a.app_code = b.app_code and a.method_code = b.method_code
+    )
+order by b.method_code;
\ No newline at end of file
@@ -0,0 +1,40 @@
+with src as (
+    select a.app_code, a.method_code, a.descr
+    from unnest($1::text[], $2::text[], $3::text[]) as a (app_code, method_code, descr)
+),
+missing as (
+    select
+        b.id,
+        b.app_code,
+        b.method_code,
+        (select count(*) from portal.tbl_int_user_authorization as c where c.app_method_id = b.id) as grants
+    from portal.tbl_int_app_transactions as b
+    where b.app_code = $4
+        and not exists (
+            select 1
+            from src
+            where src.app_code = b.app_code and src.method_code = b.method_code
+        )
+),
+del as (
+    delete from portal.tbl_int_app_transactions as d
+    using missing as m
+    where d.id = m.id
+        and m.grants = 0
+        and set_config('portal.mod_de', $5, true) is not null
+    returning d.id
+),
+ups as (
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    select src.app_code, src.method_code, src.descr, $5
+    from src
+    on conflict (app_code, method_code) do update set
+        descr = excluded.descr,
+        mod_de = excluded.mod_de,
+        mod_timp = current_timestamp
+    where tbl_int_app_transactions.descr is distinct from excluded.descr
+    returning id
+)
+select m.id, m.app_code, m.method_code, m.grants
+from missing as m
+order by m.method_code;
\ No newline at end of file
@@ -0,0 +1,8 @@
+insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+select a.app_code, a.method_code, a.descr, $4
+from unnest($1::text[], $2::text[], $3::text[]) as a (app_code, method_code, descr)
+on conflict (app_code, method_code) do update set
+    descr = excluded.descr,
+    mod_de = excluded.mod_de,
+    mod_timp = current_timestamp
+where tbl_int_app_transactions.descr is distinct from excluded.descr
\ No newline at end of file
@@ -0,0 +1,4 @@
+select
+    *
+from portal.tbl_int_app_owners as a
+order by a.app_code, a.owner_type, a.owner_id;
\ No newline at end of file
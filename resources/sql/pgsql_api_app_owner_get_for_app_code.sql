@@ -0,0 +1,5 @@
+select
+    *
+from portal.tbl_int_app_owners as a
+where a.app_code = $1
+order by a.owner_type, a.owner_id;
\ No newline at end of file
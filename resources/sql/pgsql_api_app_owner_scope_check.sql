@@ -0,0 +1,27 @@
+select (
+        exists (
+            select
+                *
+            from portal.tbl_int_user_roles as a
+
+            inner join portal.tbl_int_user_authorization as b
+            on a.group_id = b.group_id
+
+            inner join portal.tbl_int_app_transactions as c
+            on b.app_method_id = c.id
+
+            where a.user_id = $1 and c.app_code = 'portal' and c.method_code = 'app_admin_all'
+        )
+        or exists (
+            select
+                *
+            from portal.tbl_int_app_owners as d
+            where d.app_code = $2 and d.app_code <> 'portal'
+                and (
+                    (d.owner_type = 'user' and d.owner_id = $1)
+                    or (d.owner_type = 'group' and d.owner_id in (
+                        select e.group_id from portal.tbl_int_user_roles as e where e.user_id = $1
+                    ))
+                )
+        )
+    ) as rezult;
\ No newline at end of file
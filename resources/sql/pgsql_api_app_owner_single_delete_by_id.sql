@@ -0,0 +1,2 @@
+delete from portal.tbl_int_app_owners
+where id = $1;
\ No newline at end of file
@@ -0,0 +1,8 @@
+insert into portal.tbl_int_app_owners (app_code, owner_type, owner_id, mod_de)
+select $1, $2, $3, $4
+where ($2 = 'user' and exists (select * from portal.tbl_int_users as a where a.user_id = $3))
+    or ($2 = 'group' and exists (select * from portal.tbl_int_user_groups as b where b.group_id = $3))
+on conflict (app_code, owner_type, owner_id) do update set
+    mod_de = excluded.mod_de,
+    mod_timp = current_timestamp
+returning *;
\ No newline at end of file
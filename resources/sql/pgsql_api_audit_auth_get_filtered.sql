@@ -0,0 +1,9 @@
+select a.id, a.user_id, a.event, a.outcome, a.ip, a.user_agent, a.details, a.mod_timp
+from portal.tbl_int_auth_audit as a
+where ($1::text is null or a.user_id = $1)
+    and ($2::date is null or a.mod_timp >= $2)
+    and ($3::date is null or a.mod_timp < $3 + 1)
+    and ($4::text is null or a.outcome = $4)
+    and ($5::text is null or a.event = $5)
+order by a.mod_timp desc
+limit 10000
\ No newline at end of file
@@ -0,0 +1,2 @@
+insert into portal.tbl_int_auth_audit (user_id, event, outcome, ip, user_agent, details)
+values ($1, $2, $3, $4, $5, $6)
\ No newline at end of file
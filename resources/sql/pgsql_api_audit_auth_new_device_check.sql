@@ -0,0 +1,10 @@
+select exists (
+        select 1 from portal.tbl_int_auth_audit as a
+        where a.user_id = $1 and a.event = 'authenticate' and a.outcome = 'success'
+    )
+    and not exists (
+        select 1 from portal.tbl_int_auth_audit as a
+        where a.user_id = $1 and a.event = 'authenticate' and a.outcome = 'success'
+            and a.ip is not distinct from $2
+            and portal.fn_device_of(a.user_agent) is not distinct from portal.fn_device_of($3)
+    ) as is_new
\ No newline at end of file
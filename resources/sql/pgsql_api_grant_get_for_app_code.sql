@@ -0,0 +1,15 @@
+select
+    b.id,
+    b.group_id,
+    b.app_method_id,
+    c.app_code,
+    c.method_code,
+    b.mod_de,
+    b.mod_timp
+from portal.tbl_int_user_authorization as b
+
+inner join portal.tbl_int_app_transactions as c
+on b.app_method_id = c.id
+
+where c.app_code = $1
+order by c.method_code, b.group_id;
\ No newline at end of file
@@ -0,0 +1,14 @@
+select
+    b.id,
+    b.group_id,
+    b.app_method_id,
+    c.app_code,
+    c.method_code,
+    b.mod_de,
+    b.mod_timp
+from portal.tbl_int_user_authorization as b
+
+inner join portal.tbl_int_app_transactions as c
+on b.app_method_id = c.id
+
+where b.id = $1;
\ No newline at end of file
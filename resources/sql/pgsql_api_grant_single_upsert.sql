@@ -0,0 +1,22 @@
+with ins as (
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select $1, c.id, $4
+    from portal.tbl_int_app_transactions as c
+    where c.app_code = $2 and c.method_code = $3
+    on conflict (group_id, app_method_id) do update set
+        mod_de = excluded.mod_de,
+        mod_timp = current_timestamp
+    returning *
+)
+select
+    b.id,
+    b.group_id,
+    b.app_method_id,
+    c.app_code,
+    c.method_code,
+    b.mod_de,
+    b.mod_timp
+from ins as b
+
+inner join portal.tbl_int_app_transactions as c
+on b.app_method_id = c.id;
\ No newline at end of file
@@ -0,0 +1,9 @@
+update portal.tbl_int_jobs set
+    cancel_requested = true,
+    status = case when status = 'queued' then 'cancelled' else status end,
+    finished_timp = case when status = 'queued' then current_timestamp else finished_timp end,
+    mod_timp = current_timestamp
+where id = $1
+    and mod_de = $2
+    and status in ('queued', 'running')
+returning *
\ No newline at end of file
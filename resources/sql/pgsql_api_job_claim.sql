@@ -0,0 +1,18 @@
+update portal.tbl_int_jobs set
+    status = 'running',
+    stage = null,
+    attempts = attempts + 1,
+    heartbeat_timp = current_timestamp,
+    started_timp = coalesce(started_timp, current_timestamp),
+    mod_timp = current_timestamp
+where id = (
+    select a.id
+    from portal.tbl_int_jobs as a
+    where (a.status = 'queued' or (a.status = 'running' and a.heartbeat_timp < current_timestamp - interval '1 second' * $1))
+        and not a.cancel_requested
+        and a.attempts < $2
+    order by a.created_timp
+    limit 1
+    for update skip locked
+)
+returning *
\ No newline at end of file
@@ -0,0 +1,4 @@
+delete from portal.tbl_int_jobs
+where status in ('done', 'failed', 'cancelled')
+    and coalesce(finished_timp, mod_timp) < current_timestamp - interval '1 day' * $1
+returning *
\ No newline at end of file
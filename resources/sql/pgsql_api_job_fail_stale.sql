@@ -0,0 +1,9 @@
+update portal.tbl_int_jobs set
+    status = case when cancel_requested then 'cancelled' else 'failed' end,
+    error = case when cancel_requested then error else 'job stopped before finishing too many times' end,
+    finished_timp = current_timestamp,
+    mod_timp = current_timestamp
+where status = 'running'
+    and heartbeat_timp < current_timestamp - interval '1 second' * $1
+    and (cancel_requested or attempts >= $2)
+returning *
\ No newline at end of file
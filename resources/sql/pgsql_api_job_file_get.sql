@@ -0,0 +1,4 @@
+select
+    *
+from portal.tbl_int_job_files as a
+where a.job_id = $1 and a.kind = $2
\ No newline at end of file
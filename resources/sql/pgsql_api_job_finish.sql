@@ -0,0 +1,24 @@
+with job as (
+    update portal.tbl_int_jobs set
+        status = $2,
+        progress = case when $2 = 'done' then 100 else progress end,
+        result = $3,
+        result_file = $4,
+        error = $5,
+        finished_timp = current_timestamp,
+        mod_timp = current_timestamp
+    where id = $1 and attempts = $7 and status = 'running'
+    returning *
+), result as (
+    insert into portal.tbl_int_job_files (job_id, kind, content)
+    select a.id, 'result', $6
+    from job as a
+    where $6 is not null
+    on conflict (job_id, kind) do update set content = excluded.content, created_timp = current_timestamp
+), input as (
+    delete from portal.tbl_int_job_files as f
+    using job as a
+    where f.job_id = a.id and f.kind = 'input'
+)
+select a.*
+from job as a
\ No newline at end of file
@@ -0,0 +1,6 @@
+select
+    *
+from portal.tbl_int_jobs as a
+where a.mod_de = $1
+order by a.created_timp desc
+limit 100;
\ No newline at end of file
@@ -0,0 +1,7 @@
+update portal.tbl_int_jobs set
+    stage = $3,
+    progress = $4,
+    heartbeat_timp = current_timestamp,
+    mod_timp = current_timestamp
+where id = $1 and attempts = $2 and status = 'running'
+returning *
\ No newline at end of file
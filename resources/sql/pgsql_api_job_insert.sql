@@ -0,0 +1,12 @@
+with job as (
+    insert into portal.tbl_int_jobs (id, kind, params, input_file, mod_de)
+    values ($1, $2, $3, $4, $5)
+    returning *
+), input as (
+    insert into portal.tbl_int_job_files (job_id, kind, content)
+    select a.id, 'input', $6
+    from job as a
+    where $6 is not null
+)
+select a.*
+from job as a
\ No newline at end of file
@@ -0,0 +1,4 @@
+select
+    *
+from portal.tbl_int_jobs as a
+where a.id = $1 and a.mod_de = $2;
\ No newline at end of file
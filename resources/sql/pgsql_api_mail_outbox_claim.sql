@@ -0,0 +1,13 @@
+update portal.tbl_int_mail_outbox set
+    status = 'sending',
+    mod_timp = current_timestamp
+where id in (
+    select a.id
+    from portal.tbl_int_mail_outbox as a
+    where (a.status = 'pending' and a.next_attempt <= current_timestamp)
+        or (a.status = 'sending' and a.mod_timp < current_timestamp - interval '10 minutes')
+    order by a.next_attempt
+    limit $1
+    for update skip locked
+)
+returning *
\ No newline at end of file
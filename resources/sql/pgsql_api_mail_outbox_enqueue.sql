@@ -0,0 +1,3 @@
+insert into portal.tbl_int_mail_outbox (kind, user_id, to_addrs, subject, html, text_body, link_token_id, link_exp)
+values ($1, $2, $3, $4, $5, $6, $7, $8)
+returning *
\ No newline at end of file
@@ -0,0 +1,6 @@
+select a.id, a.kind, a.user_id, a.to_addrs, a.subject, a.status, a.attempts, a.next_attempt,
+    a.last_error, a.sent_timp, a.created_timp, a.mod_timp
+from portal.tbl_int_mail_outbox as a
+where ($1::text is null or a.status = $1)
+order by a.created_timp desc
+limit 1000
\ No newline at end of file
@@ -0,0 +1,8 @@
+update portal.tbl_int_mail_outbox set
+    status = case when attempts + 1 >= $3 then 'dead' else 'pending' end,
+    attempts = attempts + 1,
+    last_error = $2,
+    next_attempt = current_timestamp + least(interval '1 second' * $4 * power(2, attempts), interval '6 hours'),
+    mod_timp = current_timestamp
+where id = $1
+returning *
\ No newline at end of file
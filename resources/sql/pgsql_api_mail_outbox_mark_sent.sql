@@ -0,0 +1,7 @@
+update portal.tbl_int_mail_outbox set
+    status = 'sent',
+    attempts = attempts + 1,
+    last_error = null,
+    sent_timp = current_timestamp,
+    mod_timp = current_timestamp
+where id = $1
\ No newline at end of file
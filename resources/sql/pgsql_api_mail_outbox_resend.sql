@@ -0,0 +1,8 @@
+update portal.tbl_int_mail_outbox set
+    status = 'pending',
+    attempts = 0,
+    last_error = null,
+    next_attempt = current_timestamp,
+    mod_timp = current_timestamp
+where id = $1 and status = 'dead' and kind not in ('login', 'step_up')
+returning *
\ No newline at end of file
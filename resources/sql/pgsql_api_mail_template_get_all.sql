@@ -0,0 +1,4 @@
+select a.*
+from portal.tbl_int_mail_templates as a
+where ($1::text is null or a.template_code = $1)
+order by a.template_code, a.lang
\ No newline at end of file
@@ -0,0 +1,3 @@
+select a.*
+from portal.tbl_int_mail_templates as a
+where a.template_code = $1 and a.lang = $2
\ No newline at end of file
@@ -0,0 +1,9 @@
+insert into portal.tbl_int_mail_templates (template_code, lang, subject, html, text_body, mod_de)
+values ($1, $2, $3, $4, $5, $6)
+on conflict (template_code, lang) do update set
+    subject = excluded.subject,
+    html = excluded.html,
+    text_body = excluded.text_body,
+    mod_de = excluded.mod_de,
+    mod_timp = current_timestamp
+returning *
\ No newline at end of file
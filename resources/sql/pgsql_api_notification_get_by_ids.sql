@@ -0,0 +1,4 @@
+select
+    *
+from portal.tbl_int_notifications as a
+where a.id = any($1)
\ No newline at end of file
@@ -0,0 +1,6 @@
+select a.*
+from portal.tbl_int_notifications as a
+where a.user_id = $1
+    and (not $2 or a.read_timp is null)
+order by a.mod_timp desc
+limit 1000
\ No newline at end of file
@@ -0,0 +1,10 @@
+insert into portal.tbl_int_notifications (user_id, kind, title, message)
+select u.user_id, $3, $4, $5
+from portal.tbl_int_users as u
+where u.user_id = any($1)
+    or exists (
+        select 1
+        from portal.tbl_int_user_roles as r
+        where r.user_id = u.user_id and r.group_id = any($2)
+    )
+returning *
\ No newline at end of file
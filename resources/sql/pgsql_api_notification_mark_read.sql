@@ -0,0 +1,3 @@
+update portal.tbl_int_notifications set
+    read_timp = coalesce(read_timp, current_timestamp)
+where id = $1 and user_id = $2
\ No newline at end of file
@@ -0,0 +1,6 @@
+update portal.tbl_int_user_authentication set
+    token_id = $3,
+    mod_timp = $4
+where user_id = $1
+    and token_id = $2
+returning *;
\ No newline at end of file
@@ -0,0 +1,12 @@
+select distinct
+    c.method_code,
+    c.sensitive
+from portal.tbl_int_user_roles as a
+
+inner join portal.tbl_int_user_authorization as b
+on a.group_id = b.group_id
+
+inner join portal.tbl_int_app_transactions as c
+on b.app_method_id = c.id
+
+where a.user_id = $1 and c.app_code = $2 and c.method_code = any($3);
\ No newline at end of file
@@ -0,0 +1,3 @@
+insert into portal.tbl_int_user_revoke_links (user_id)
+values ($1)
+returning id, user_id
\ No newline at end of file
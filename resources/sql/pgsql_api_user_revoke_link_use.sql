@@ -0,0 +1,4 @@
+update portal.tbl_int_user_revoke_links set
+    used_timp = current_timestamp
+where id = $1 and used_timp is null and mod_timp > current_timestamp - interval '30 days'
+returning id, user_id
\ No newline at end of file
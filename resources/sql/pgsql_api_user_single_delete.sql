@@ -1,2 +1,2 @@
-delete from portal.tbl_int_user_roles where user_id = $1;
-delete from portla.tbl_int_users where user_id = $1;
+delete from portal.tbl_int_user_roles where user_id = $1;
+delete from portla.tbl_int_users where user_id = $1;
@@ -0,0 +1,20 @@
+update portal.tbl_int_webhook_deliveries as a set
+    status = 'sending',
+    mod_timp = current_timestamp
+from portal.tbl_int_webhooks as b
+where a.webhook_id = b.id
+    and b.active
+    and a.id in (
+        select c.id
+        from portal.tbl_int_webhook_deliveries as c
+
+        inner join portal.tbl_int_webhooks as d
+        on c.webhook_id = d.id and d.active
+
+        where (c.status = 'pending' and c.next_attempt <= current_timestamp)
+            or (c.status = 'sending' and c.mod_timp < current_timestamp - interval '10 minutes')
+        order by c.next_attempt
+        limit $1
+        for update of c skip locked
+    )
+returning a.*, b.url, b.secret
\ No newline at end of file
@@ -0,0 +1,4 @@
+insert into portal.tbl_int_webhook_deliveries (webhook_id, event_type, payload)
+select a.id, $1, $2
+from portal.tbl_int_webhooks as a
+where a.active and $1 = any(a.event_types)
\ No newline at end of file
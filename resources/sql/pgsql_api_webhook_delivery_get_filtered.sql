@@ -0,0 +1,6 @@
+select a.*
+from portal.tbl_int_webhook_deliveries as a
+where ($1::uuid is null or a.webhook_id = $1)
+    and ($2::text is null or a.status = $2)
+order by a.created_timp desc
+limit 1000
\ No newline at end of file
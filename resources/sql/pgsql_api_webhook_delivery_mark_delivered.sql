@@ -0,0 +1,8 @@
+update portal.tbl_int_webhook_deliveries set
+    status = 'delivered',
+    attempts = attempts + 1,
+    response_status = $2,
+    last_error = null,
+    delivered_timp = current_timestamp,
+    mod_timp = current_timestamp
+where id = $1
\ No newline at end of file
@@ -0,0 +1,9 @@
+update portal.tbl_int_webhook_deliveries set
+    status = case when attempts + 1 >= $4 then 'dead' else 'pending' end,
+    attempts = attempts + 1,
+    response_status = $2,
+    last_error = $3,
+    next_attempt = current_timestamp + least(interval '1 second' * $5 * power(2, attempts), interval '6 hours'),
+    mod_timp = current_timestamp
+where id = $1
+returning *
\ No newline at end of file
@@ -0,0 +1,3 @@
+select a.*
+from portal.tbl_int_webhooks as a
+order by a.url, a.id
\ No newline at end of file
@@ -0,0 +1 @@
+delete from portal.tbl_int_webhooks where id = $1
\ No newline at end of file
@@ -0,0 +1,14 @@
+insert into portal.tbl_int_webhooks as a (id, url, event_types, secret, active, mod_de)
+values (
+    coalesce($1, uuid_generate_v4()), $2, $3,
+    coalesce($4, (select b.secret from portal.tbl_int_webhooks as b where b.id = $1)),
+    $5, $6
+)
+on conflict (id) do update set
+    url = excluded.url,
+    event_types = excluded.event_types,
+    secret = excluded.secret,
+    active = excluded.active,
+    mod_de = excluded.mod_de,
+    mod_timp = current_timestamp
+returning *
\ No newline at end of file
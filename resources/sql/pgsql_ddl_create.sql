@@ -1,113 +1,502 @@
-do $$
-begin
-    raise notice 'CREATING EXTENSION "uuid-ossp"';
-    create extension if not exists "uuid-ossp";
-
-    raise notice 'CREATING SCHEMA "portal"';
-    create schema if not exists portal;
-
-    /* 0001.001 */
-    raise notice 'CREATING TABLE "tbl_int_users"';
-    create table if not exists portal.tbl_int_users (
-        user_id text not null,
-        first_name text not null,
-        last_name text not null,
-        email text not null,
-        mod_de text not null,
-        mod_timp timestamp not null default current_timestamp,
-        constraint tbl_int_users_pk primary key (user_id),
-        constraint tbl_int_users_ck1 check (email ~* '^[a-zA-Z0-9_+&-]+(?:.[a-zA-Z0-9_+&-]+)*@(?:[a-zA-Z0-9-]+.)+[a-zA-Z]{2,7}$')
-    );
-
-    /* 0001.002 */
-    raise notice 'CREATING TABLE "tbl_int_user_groups"';
-    create table if not exists portal.tbl_int_user_groups (
-        group_id text not null,
-        group_name text not null,
-        mod_de text not null,
-        mod_timp timestamp not null default current_timestamp,
-        constraint tbl_int_user_groups_pk primary key (group_id)
-    );
-
-    /* 0001.003 */
-    raise notice 'CREATING TABLE "tbl_int_user_roles"';
-    create table if not exists portal.tbl_int_user_roles (
-        id uuid not null default uuid_generate_v4(),
-        user_id text not null,
-        group_id text not null,
-        mod_de text not null,
-        mod_timp timestamp not null default current_timestamp,
-        constraint tbl_int_user_roles_pk primary key (id),
-        constraint tbl_int_user_roles_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id),
-        constraint tbl_int_user_roles_fk_group_id foreign key (group_id) references portal.tbl_int_user_groups (group_id),
-        constraint tbl_int_user_roles_unique_1 unique (user_id, group_id)
-    );
-
-    /* 0001.004 */
-    raise notice 'CREATING TABLE "tbl_int_app_transactions"';
-    create table if not exists portal.tbl_int_app_transactions (
-        id uuid not null default uuid_generate_v4(),
-        app_code text not null,
-        method_code text not null,
-        descr text not null,
-        mod_de text not null,
-        mod_timp timestamp not null default current_timestamp,
-        constraint tbl_int_app_transactions_pk primary key (id),
-        constraint tbl_int_app_transactions_unique_1 unique (app_code, method_code)
-    );
-
-    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
-    values ('portal', 'user_single_get', 'Get data for one app user by user id', 'catalin'),
-        ('portal', 'user_single_persist', 'Add/ update data for one app user', 'catalin'),
-        ('portal', 'user_single_delete', 'Delete data for one app user', 'catalin'),
-        ('portal', 'user_all_list', 'Get data for all app users', 'catalin'),
-        ('portal', 'app_method_app_codes', 'TBD', 'catalin'),
-        ('portal', 'app_method_list_all', 'TBD', 'catalin'),
-        ('portal', 'app_method_get_single_by_id', 'TBD', 'catalin'),
-        ('portal', 'app_method_del_single_by_id', 'TBD', 'catalin'),
-        ('portal', 'app_method_upsert_single', 'TBD', 'catalin'),
-        ('portal', 'app_method_upsert_all', 'TBD', 'catalin')
-    on conflict (app_code, method_code) do nothing;
-
-    /* 0001.005 */
-    raise notice 'CREATING TABLE "tbl_int_user_authorization"';
-    create table if not exists portal.tbl_int_user_authorization (
-        id uuid not null default uuid_generate_v4(),
-        group_id text not null,
-        app_method_id uuid not null,
-        mod_de text not null,
-        mod_timp timestamp not null default current_timestamp,
-        constraint tbl_int_user_authorization_pk primary key (id),
-        constraint tbl_int_user_authorization_fk_group_id foreign key (group_id) references portal.tbl_int_user_groups (group_id),
-        constraint tbl_int_user_authorization_fk_app_method_id foreign key (app_method_id) references portal.tbl_int_app_transactions (id),
-        constraint tbl_int_user_authorization_unique_1 unique (group_id, app_method_id)
-    );
-
-    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
-    values ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_get'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_persist'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_delete'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_all_list'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_app_codes'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_list_all'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_get_single_by_id'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_del_single_by_id'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_upsert_single'), 'catalin'),
-        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_upsert_all'), 'catalin'),
-        ('cdg_controller', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_get'), 'catalin'),
-        ('cdg_controller', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_all_list'), 'catalin')
-    on conflict (group_id, app_method_id) do nothing;
-
-    /* 0001.006 */
-    raise notice 'CREATING TABLE "tbl_int_user_authentication"';
-    create table if not exists portal.tbl_int_user_authentication (
-        id uuid not null default uuid_generate_v4(),
-        user_id text not null,
-        token_id uuid not null,
-        mod_timp timestamp not null,
-        constraint tbl_int_user_authentication_pk primary key (id),
-        constraint tbl_int_user_authentication_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id),
-        constraint tbl_int_user_authentication_uq_user_id unique (user_id)
-    );
-end;
+do $$
+begin
+    raise notice 'CREATING EXTENSION "uuid-ossp"';
+    create extension if not exists "uuid-ossp";
+
+    raise notice 'CREATING SCHEMA "portal"';
+    create schema if not exists portal;
+
+    /* 0001.001 */
+    raise notice 'CREATING TABLE "tbl_int_users"';
+    create table if not exists portal.tbl_int_users (
+        user_id text not null,
+        first_name text not null,
+        last_name text not null,
+        email text not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_users_pk primary key (user_id),
+        constraint tbl_int_users_ck1 check (email ~* '^[a-zA-Z0-9_+&-]+(?:.[a-zA-Z0-9_+&-]+)*@(?:[a-zA-Z0-9-]+.)+[a-zA-Z]{2,7}$')
+    );
+
+    /* 0001.002 */
+    raise notice 'CREATING TABLE "tbl_int_user_groups"';
+    create table if not exists portal.tbl_int_user_groups (
+        group_id text not null,
+        group_name text not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_user_groups_pk primary key (group_id)
+    );
+
+    /* 0001.003 */
+    raise notice 'CREATING TABLE "tbl_int_user_roles"';
+    create table if not exists portal.tbl_int_user_roles (
+        id uuid not null default uuid_generate_v4(),
+        user_id text not null,
+        group_id text not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_user_roles_pk primary key (id),
+        constraint tbl_int_user_roles_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id),
+        constraint tbl_int_user_roles_fk_group_id foreign key (group_id) references portal.tbl_int_user_groups (group_id),
+        constraint tbl_int_user_roles_unique_1 unique (user_id, group_id)
+    );
+
+    /* 0001.004 */
+    raise notice 'CREATING TABLE "tbl_int_app_transactions"';
+    create table if not exists portal.tbl_int_app_transactions (
+        id uuid not null default uuid_generate_v4(),
+        app_code text not null,
+        method_code text not null,
+        descr text not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_app_transactions_pk primary key (id),
+        constraint tbl_int_app_transactions_unique_1 unique (app_code, method_code)
+    );
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'user_single_get', 'Get data for one app user by user id', 'catalin'),
+        ('portal', 'user_single_persist', 'Add/ update data for one app user', 'catalin'),
+        ('portal', 'user_single_delete', 'Delete data for one app user', 'catalin'),
+        ('portal', 'user_all_list', 'Get data for all app users', 'catalin'),
+        ('portal', 'app_method_app_codes', 'TBD', 'catalin'),
+        ('portal', 'app_method_list_all', 'TBD', 'catalin'),
+        ('portal', 'app_method_get_single_by_id', 'TBD', 'catalin'),
+        ('portal', 'app_method_del_single_by_id', 'TBD', 'catalin'),
+        ('portal', 'app_method_upsert_single', 'TBD', 'catalin'),
+        ('portal', 'app_method_upsert_all', 'TBD', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    /* 0001.005 */
+    raise notice 'CREATING TABLE "tbl_int_user_authorization"';
+    create table if not exists portal.tbl_int_user_authorization (
+        id uuid not null default uuid_generate_v4(),
+        group_id text not null,
+        app_method_id uuid not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_user_authorization_pk primary key (id),
+        constraint tbl_int_user_authorization_fk_group_id foreign key (group_id) references portal.tbl_int_user_groups (group_id),
+        constraint tbl_int_user_authorization_fk_app_method_id foreign key (app_method_id) references portal.tbl_int_app_transactions (id),
+        constraint tbl_int_user_authorization_unique_1 unique (group_id, app_method_id)
+    );
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    values ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_get'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_persist'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_delete'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_all_list'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_app_codes'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_list_all'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_get_single_by_id'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_del_single_by_id'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_upsert_single'), 'catalin'),
+        ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_upsert_all'), 'catalin'),
+        ('cdg_controller', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_single_get'), 'catalin'),
+        ('cdg_controller', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'user_all_list'), 'catalin')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.006 */
+    raise notice 'CREATING TABLE "tbl_int_user_authentication"';
+    create table if not exists portal.tbl_int_user_authentication (
+        id uuid not null default uuid_generate_v4(),
+        user_id text not null,
+        token_id uuid not null,
+        mod_timp timestamp not null,
+        constraint tbl_int_user_authentication_pk primary key (id),
+        constraint tbl_int_user_authentication_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id),
+        constraint tbl_int_user_authentication_uq_user_id unique (user_id)
+    );
+
+    /* 0001.007 */
+    raise notice 'CREATING TABLE "tbl_int_app_owners"';
+    create table if not exists portal.tbl_int_app_owners (
+        id uuid not null default uuid_generate_v4(),
+        app_code text not null,
+        owner_type text not null,
+        owner_id text not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_app_owners_pk primary key (id),
+        constraint tbl_int_app_owners_ck1 check (owner_type in ('user', 'group')),
+        constraint tbl_int_app_owners_unique_1 unique (app_code, owner_type, owner_id)
+    );
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'app_admin_all', 'Administer methods and grants for every app code', 'catalin'),
+        ('portal', 'app_owner_list', 'Get app owners list', 'catalin'),
+        ('portal', 'app_owner_upsert', 'Add/ update app owner', 'catalin'),
+        ('portal', 'app_owner_delete', 'Delete app owner', 'catalin'),
+        ('portal', 'grant_list', 'Get method grants for app code', 'catalin'),
+        ('portal', 'grant_upsert', 'Grant app method to user group', 'catalin'),
+        ('portal', 'grant_delete', 'Revoke app method from user group', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select 'cdg_admin', a.id, 'catalin'
+    from portal.tbl_int_app_transactions as a
+    where a.app_code = 'portal' and a.method_code in ('app_admin_all', 'app_owner_list', 'app_owner_upsert', 'app_owner_delete', 'grant_list', 'grant_upsert', 'grant_delete')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.008 */
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'app_method_export', 'Export app methods to xlsx/ csv files', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    values ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'app_method_export'), 'catalin')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.009 */
+    raise notice 'CREATING TABLE "tbl_int_auth_audit"';
+    create table if not exists portal.tbl_int_auth_audit (
+        id uuid not null default uuid_generate_v4(),
+        user_id text,
+        event text not null,
+        outcome text not null,
+        ip text,
+        user_agent text,
+        details text,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_auth_audit_pk primary key (id),
+        constraint tbl_int_auth_audit_ck1 check (event in ('link_request', 'link_sent', 'link_failed', 'authenticate', 'logout', 'token_rejected')),
+        constraint tbl_int_auth_audit_ck2 check (outcome in ('success', 'failure'))
+    );
+    create index if not exists tbl_int_auth_audit_idx_user_id on portal.tbl_int_auth_audit (user_id, mod_timp);
+    create index if not exists tbl_int_auth_audit_idx_mod_timp on portal.tbl_int_auth_audit (mod_timp);
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'audit_auth_list', 'Get login/ authentication audit trail', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    values ('cdg_admin', (select id from portal.tbl_int_app_transactions where app_code = 'portal' and method_code = 'audit_auth_list'), 'catalin')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.010 */
+    raise notice 'CREATING TABLE "tbl_int_user_revoke_links"';
+    create table if not exists portal.tbl_int_user_revoke_links (
+        id uuid not null default uuid_generate_v4(),
+        user_id text not null,
+        used_timp timestamp,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_user_revoke_links_pk primary key (id),
+        constraint tbl_int_user_revoke_links_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id)
+    );
+
+    alter table portal.tbl_int_auth_audit drop constraint if exists tbl_int_auth_audit_ck1;
+    alter table portal.tbl_int_auth_audit add constraint tbl_int_auth_audit_ck1
+        check (event in ('link_request', 'link_sent', 'link_failed', 'authenticate', 'logout', 'token_rejected', 'new_device_mail', 'revoke_all'));
+
+    /* 0001.011 */
+    raise notice 'ADDING COLUMN "sensitive" TO "tbl_int_app_transactions"';
+    alter table portal.tbl_int_app_transactions add column if not exists sensitive boolean not null default false;
+
+    update portal.tbl_int_app_transactions set sensitive = true
+    where app_code = 'portal' and method_code in ('app_method_del_single_by_id', 'app_method_upsert_all');
+
+    /* 0001.012 */
+    raise notice 'ADDING COLUMN "language" TO "tbl_int_users"';
+    alter table portal.tbl_int_users add column if not exists language text;
+
+    /* 0001.013 */
+    raise notice 'CREATING TABLE "tbl_int_mail_outbox"';
+    create table if not exists portal.tbl_int_mail_outbox (
+        id uuid not null default uuid_generate_v4(),
+        kind text not null,
+        user_id text,
+        to_addrs text[] not null,
+        subject text not null,
+        html text not null,
+        status text not null default 'pending',
+        attempts integer not null default 0,
+        next_attempt timestamp not null default current_timestamp,
+        last_error text,
+        sent_timp timestamp,
+        created_timp timestamp not null default current_timestamp,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_mail_outbox_pk primary key (id),
+        constraint tbl_int_mail_outbox_ck1 check (status in ('pending', 'sending', 'sent', 'dead'))
+    );
+    create index if not exists tbl_int_mail_outbox_idx_status on portal.tbl_int_mail_outbox (status, next_attempt);
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'mail_outbox_list', 'Get outbound mail queue', 'catalin'),
+        ('portal', 'mail_outbox_resend', 'Resend mail from the outbound queue', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select 'cdg_admin', a.id, 'catalin'
+    from portal.tbl_int_app_transactions as a
+    where a.app_code = 'portal' and a.method_code in ('mail_outbox_list', 'mail_outbox_resend')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.014 */
+    raise notice 'CREATING TABLE "tbl_int_mail_templates"';
+    create table if not exists portal.tbl_int_mail_templates (
+        id uuid not null default uuid_generate_v4(),
+        template_code text not null,
+        lang text not null,
+        subject text not null,
+        html text not null,
+        text_body text,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_mail_templates_pk primary key (id),
+        constraint tbl_int_mail_templates_uq1 unique (template_code, lang)
+    );
+
+    alter table portal.tbl_int_mail_outbox add column if not exists text_body text;
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'mail_template_list', 'Get mail templates', 'catalin'),
+        ('portal', 'mail_template_upsert', 'Add/ update/ preview mail template', 'catalin'),
+        ('portal', 'mail_template_delete', 'Delete mail template', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select 'cdg_admin', a.id, 'catalin'
+    from portal.tbl_int_app_transactions as a
+    where a.app_code = 'portal' and a.method_code in ('mail_template_list', 'mail_template_upsert', 'mail_template_delete')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.015 */
+    raise notice 'CREATING TABLE "tbl_int_announcements"';
+    create table if not exists portal.tbl_int_announcements (
+        id uuid not null default uuid_generate_v4(),
+        title text not null,
+        message text not null,
+        groups text[] not null,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_announcements_pk primary key (id),
+        constraint tbl_int_announcements_ck1 check (cardinality(groups) > 0)
+    );
+
+    raise notice 'CREATING TABLE "tbl_int_announcement_reads"';
+    create table if not exists portal.tbl_int_announcement_reads (
+        announcement_id uuid not null,
+        user_id text not null,
+        read_timp timestamp not null default current_timestamp,
+        constraint tbl_int_announcement_reads_pk primary key (announcement_id, user_id),
+        constraint tbl_int_announcement_reads_fk_announcement_id foreign key (announcement_id) references portal.tbl_int_announcements (id) on delete cascade,
+        constraint tbl_int_announcement_reads_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id)
+    );
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'announcement_create', 'Send announcement to user groups', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select 'cdg_admin', a.id, 'catalin'
+    from portal.tbl_int_app_transactions as a
+    where a.app_code = 'portal' and a.method_code = 'announcement_create'
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.016 */
+    raise notice 'CREATING TABLE "tbl_int_notifications"';
+    create table if not exists portal.tbl_int_notifications (
+        id uuid not null default uuid_generate_v4(),
+        user_id text not null,
+        kind text not null,
+        title text not null,
+        message text not null,
+        read_timp timestamp,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_notifications_pk primary key (id),
+        constraint tbl_int_notifications_fk_user_id foreign key (user_id) references portal.tbl_int_users (user_id),
+        constraint tbl_int_notifications_ck1 check (kind in ('upload_finished', 'access_granted', 'announcement'))
+    );
+    create index if not exists tbl_int_notifications_idx_user on portal.tbl_int_notifications (user_id, mod_timp);
+
+    /* 0001.017 */
+    raise notice 'CREATING TABLE "tbl_int_webhooks"';
+    create table if not exists portal.tbl_int_webhooks (
+        id uuid not null default uuid_generate_v4(),
+        url text not null,
+        event_types text[] not null,
+        secret text not null,
+        active boolean not null default true,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_webhooks_pk primary key (id),
+        constraint tbl_int_webhooks_ck1 check (url ~ '^https?://'),
+        constraint tbl_int_webhooks_ck2 check (cardinality(event_types) > 0)
+    );
+
+    raise notice 'CREATING TABLE "tbl_int_webhook_deliveries"';
+    create table if not exists portal.tbl_int_webhook_deliveries (
+        id uuid not null default uuid_generate_v4(),
+        webhook_id uuid not null,
+        event_type text not null,
+        payload jsonb not null,
+        status text not null default 'pending',
+        attempts integer not null default 0,
+        next_attempt timestamp not null default current_timestamp,
+        response_status integer,
+        last_error text,
+        delivered_timp timestamp,
+        created_timp timestamp not null default current_timestamp,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_webhook_deliveries_pk primary key (id),
+        constraint tbl_int_webhook_deliveries_fk_webhook_id foreign key (webhook_id) references portal.tbl_int_webhooks (id) on delete cascade,
+        constraint tbl_int_webhook_deliveries_ck1 check (status in ('pending', 'sending', 'delivered', 'dead'))
+    );
+    create index if not exists tbl_int_webhook_deliveries_idx_due on portal.tbl_int_webhook_deliveries (status, next_attempt);
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values
+        ('portal', 'webhook_list', 'List webhook subscriptions and deliveries', 'catalin'),
+        ('portal', 'webhook_upsert', 'Add or edit webhook subscription', 'catalin'),
+        ('portal', 'webhook_delete', 'Delete webhook subscription', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select 'cdg_admin', a.id, 'catalin'
+    from portal.tbl_int_app_transactions as a
+    where a.app_code = 'portal' and a.method_code in ('webhook_list', 'webhook_upsert', 'webhook_delete')
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.018 */
+    raise notice 'CREATING TABLE "tbl_int_app_transactions_history"';
+    create table if not exists portal.tbl_int_app_transactions_history (
+        id uuid not null default uuid_generate_v4(),
+        app_method_id uuid not null,
+        operation text not null,
+        before_data jsonb,
+        after_data jsonb,
+        mod_de text not null,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_app_transactions_history_pk primary key (id),
+        constraint tbl_int_app_transactions_history_ck1 check (operation in ('insert', 'update', 'delete'))
+    );
+    create index if not exists tbl_int_app_transactions_history_idx_method on portal.tbl_int_app_transactions_history (app_method_id, mod_timp);
+
+    /* row level, so single changes and each row of the xlsx/ csv uploads are all recorded;
+    deletes take the actor from the "portal.mod_de" transaction setting */
+    raise notice 'CREATING FUNCTION "fn_app_transactions_history"';
+    create or replace function portal.fn_app_transactions_history() returns trigger as $fn$
+    begin
+        if tg_op = 'DELETE' then
+            insert into portal.tbl_int_app_transactions_history (app_method_id, operation, before_data, after_data, mod_de)
+            values (old.id, 'delete', to_jsonb(old), null, coalesce(nullif(current_setting('portal.mod_de', true), ''), old.mod_de));
+            return old;
+        elsif tg_op = 'UPDATE' then
+            insert into portal.tbl_int_app_transactions_history (app_method_id, operation, before_data, after_data, mod_de)
+            values (new.id, 'update', to_jsonb(old), to_jsonb(new), new.mod_de);
+        else
+            insert into portal.tbl_int_app_transactions_history (app_method_id, operation, before_data, after_data, mod_de)
+            values (new.id, 'insert', null, to_jsonb(new), new.mod_de);
+        end if;
+        return new;
+    end;
+    $fn$ language plpgsql;
+
+    drop trigger if exists trg_app_transactions_history on portal.tbl_int_app_transactions;
+    create trigger trg_app_transactions_history
+    after insert or update or delete on portal.tbl_int_app_transactions
+    for each row execute function portal.fn_app_transactions_history();
+
+    insert into portal.tbl_int_app_transactions (app_code, method_code, descr, mod_de)
+    values ('portal', 'app_method_history', 'Get change history of one app method', 'catalin')
+    on conflict (app_code, method_code) do nothing;
+
+    insert into portal.tbl_int_user_authorization (group_id, app_method_id, mod_de)
+    select 'cdg_admin', a.id, 'catalin'
+    from portal.tbl_int_app_transactions as a
+    where a.app_code = 'portal' and a.method_code = 'app_method_history'
+    on conflict (group_id, app_method_id) do nothing;
+
+    /* 0001.019 */
+    /* background jobs; a "running" job whose heartbeat stopped (server restart) is claimed again */
+    raise notice 'CREATING TABLE "tbl_int_jobs"';
+    create table if not exists portal.tbl_int_jobs (
+        id uuid not null default uuid_generate_v4(),
+        kind text not null,
+        params jsonb not null default '{}'::jsonb,
+        input_file text,
+        status text not null default 'queued',
+        stage text,
+        progress integer not null default 0,
+        cancel_requested boolean not null default false,
+        attempts integer not null default 0,
+        result jsonb,
+        result_file text,
+        error text,
+        heartbeat_timp timestamp,
+        started_timp timestamp,
+        finished_timp timestamp,
+        mod_de text not null,
+        created_timp timestamp not null default current_timestamp,
+        mod_timp timestamp not null default current_timestamp,
+        constraint tbl_int_jobs_pk primary key (id),
+        constraint tbl_int_jobs_ck1 check (kind in ('app_method_import', 'app_method_export')),
+        constraint tbl_int_jobs_ck2 check (status in ('queued', 'running', 'done', 'failed', 'cancelled')),
+        constraint tbl_int_jobs_ck3 check (progress between 0 and 100)
+    );
+    create index if not exists tbl_int_jobs_idx_status on portal.tbl_int_jobs (status, created_timp);
+    create index if not exists tbl_int_jobs_idx_mod_de on portal.tbl_int_jobs (mod_de, created_timp);
+
+    /* 0001.020 */
+    /* browser family and os of a user agent, so a browser update is not a new device */
+    create or replace function portal.fn_device_of(user_agent text) returns text as $fn$
+        select case
+                when user_agent is null then null
+                else concat_ws(' on ',
+                    case
+                        when user_agent ~ 'Edg(e|A|iOS)?/' then 'edge'
+                        when user_agent ~ '(OPR|Opera)/' then 'opera'
+                        when user_agent ~ '(Firefox|FxiOS)/' then 'firefox'
+                        when user_agent ~ '(Chrome|CriOS|Chromium)/' then 'chrome'
+                        when user_agent ~ 'Safari/' then 'safari'
+                        else regexp_replace(user_agent, '[0-9][0-9._]*', '', 'g')
+                    end,
+                    case
+                        when user_agent ~ 'Windows' then 'windows'
+                        when user_agent ~ 'Android' then 'android'
+                        when user_agent ~ '(iPhone|iPad|iPod)' then 'ios'
+                        when user_agent ~ '(Macintosh|Mac OS X)' then 'macos'
+                        when user_agent ~ 'CrOS' then 'chromeos'
+                        when user_agent ~ 'Linux' then 'linux'
+                    end)
+            end;
+    $fn$ language sql immutable;
+
+    /* 0001.021 */
+    /* the sign-in link mails keep only the token id and expiry, the worker signs the token at send time */
+    alter table portal.tbl_int_mail_outbox add column if not exists link_token_id uuid;
+    alter table portal.tbl_int_mail_outbox add column if not exists link_exp timestamp;
+
+    /* 0001.022 */
+    /* job input/ result files are kept in the db, so any server can run a job or serve its result;
+       "input_file"/ "result_file" of the job keep only the file name */
+    raise notice 'CREATING TABLE "tbl_int_job_files"';
+    create table if not exists portal.tbl_int_job_files (
+        job_id uuid not null,
+        kind text not null,
+        content bytea not null,
+        created_timp timestamp not null default current_timestamp,
+        constraint tbl_int_job_files_pk primary key (job_id, kind),
+        constraint tbl_int_job_files_fk_job_id foreign key (job_id) references portal.tbl_int_jobs (id) on delete cascade,
+        constraint tbl_int_job_files_ck1 check (kind in ('input', 'result'))
+    );
+
+    /* 0001.023 */
+    /* each new notification is announced on the "portal_notification" channel, so every server
+       pushes it to its own SSE streams, whichever server stored it */
+    raise notice 'CREATING FUNCTION "fn_notifications_announce"';
+    create or replace function portal.fn_notifications_announce() returns trigger as $fn$
+    begin
+        perform pg_notify('portal_notification', new.id::text);
+        return new;
+    end;
+    $fn$ language plpgsql;
+
+    drop trigger if exists trg_notifications_announce on portal.tbl_int_notifications;
+    create trigger trg_notifications_announce
+    after insert on portal.tbl_int_notifications
+    for each row execute function portal.fn_notifications_announce();
+end;
 $$ language plpgsql;
\ No newline at end of file
@@ -0,0 +1,326 @@
+use actix_web::web::{self, Bytes};
+use futures_util::{Stream, TryStreamExt};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_postgres::types::Type;
+
+use crate::AppContext;
+
+/// the csv text is sent in chunks of about this size
+pub const CHUNK_BYTES: usize = 64 * 1024;
+/// chunks waiting for a slow client; the db reading pauses when they are all taken
+pub const CHANNEL_CHUNKS: usize = 8;
+/// written in place of the characters missing from the export encoding, after `cedilla_form`
+pub const UNMAPPABLE_REPLACEMENT: u8 = b'?';
+/// longest an export may keep its db connection
+pub const STREAM_TIMEOUT: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Crlf,
+    Lf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Crlf => "crlf",
+            Self::Lf => "lf",
+        }
+    }
+
+    pub fn parse(v: &str) -> Option<Self> {
+        [Self::Crlf, Self::Lf]
+            .into_iter()
+            .find(|k| k.as_str().eq_ignore_ascii_case(v))
+    }
+
+    fn terminator(&self) -> csv::Terminator {
+        match self {
+            Self::Crlf => csv::Terminator::CRLF,
+            Self::Lf => csv::Terminator::Any(b'\n'),
+        }
+    }
+}
+
+/// how the csv text of an export is written; the default is the plain comma separated UTF-8
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvDialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub line_ending: LineEnding,
+    /// column names as first line
+    pub header: bool,
+    /// UTF-8 BOM first, so Excel doesn't read the text as the Windows encoding of its locale
+    pub bom: bool,
+    /// characters missing from it are written as `UNMAPPABLE_REPLACEMENT`
+    pub encoding: &'static encoding_rs::Encoding,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            line_ending: LineEnding::Crlf,
+            header: true,
+            bom: false,
+            encoding: encoding_rs::UTF_8,
+        }
+    }
+}
+
+/// builds the csv text of an export, handing it over in chunks
+pub struct CsvChunks {
+    builder: csv::WriterBuilder,
+    buf: Vec<u8>,
+    encoding: &'static encoding_rs::Encoding,
+}
+
+impl CsvChunks {
+    pub fn new(dialect: &CsvDialect) -> Self {
+        let mut builder = csv::WriterBuilder::new();
+        builder
+            .delimiter(dialect.delimiter)
+            .quote(dialect.quote)
+            .terminator(dialect.line_ending.terminator());
+        let mut buf = Vec::with_capacity(CHUNK_BYTES);
+        if dialect.bom {
+            buf.extend_from_slice(b"\xEF\xBB\xBF");
+        }
+        Self {
+            builder,
+            buf,
+            encoding: dialect.encoding,
+        }
+    }
+
+    /// adds a line, giving back the text gathered so far once it reaches `CHUNK_BYTES`
+    pub fn push<I, T>(&mut self, record: I) -> Result<Option<Bytes>, csv::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut writer = self.builder.from_writer(&mut self.buf);
+        writer.write_record(record)?;
+        writer.flush()?;
+        drop(writer);
+        if self.buf.len() < CHUNK_BYTES {
+            return Ok(None);
+        }
+        Ok(Some(self.take()))
+    }
+
+    /// the text not handed over yet; the chunks end with whole lines, so each is encoded alone
+    pub fn take(&mut self) -> Bytes {
+        let buf = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_BYTES));
+        if self.encoding == encoding_rs::UTF_8 {
+            return Bytes::from(buf);
+        }
+        let text = String::from_utf8_lossy(&buf);
+        let mut encoder = self.encoding.new_encoder();
+        let mut src: &str = &text;
+        let mut res = Vec::with_capacity(
+            encoder
+                .max_buffer_length_from_utf8_without_replacement(src.len())
+                .unwrap_or(src.len()),
+        );
+        loop {
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(src, &mut res, true);
+            src = &src[read..];
+            match result {
+                encoding_rs::EncoderResult::InputEmpty => break,
+                encoding_rs::EncoderResult::OutputFull => res.reserve(src.len() + 16),
+                encoding_rs::EncoderResult::Unmappable(c) => {
+                    let mut utf8 = [0; 4];
+                    match cedilla_form(c).map(|v| self.encoding.encode(v.encode_utf8(&mut utf8))) {
+                        Some((bytes, _, false)) => res.extend_from_slice(&bytes),
+                        _ => res.push(UNMAPPABLE_REPLACEMENT),
+                    }
+                }
+            }
+        }
+        Bytes::from(res)
+    }
+}
+
+/// the Romanian comma below "ș"/ "ț" as the cedilla "ş"/ "ţ", the only ones the Windows
+/// encodings (Windows-1250 ...) have
+fn cedilla_form(c: char) -> Option<char> {
+    match c {
+        'ș' => Some('ş'),
+        'Ș' => Some('Ş'),
+        'ț' => Some('ţ'),
+        'Ț' => Some('Ţ'),
+        _ => None,
+    }
+}
+
+impl Default for CsvChunks {
+    fn default() -> Self {
+        Self::new(&CsvDialect::default())
+    }
+}
+
+/// text of a cell, empty for null; besides the types below only the text ones (text, varchar,
+/// char, name...) are read, the others (numeric, bytea, arrays...) are an error asking for
+/// a cast to text in the query
+fn cell_text(row: &tokio_postgres::Row, idx: usize) -> Result<String, String> {
+    fn text<T: ToString>(v: Option<T>) -> String {
+        v.map(|v| v.to_string()).unwrap_or_default()
+    }
+    let column = &row.columns()[idx];
+    let res = match *column.type_() {
+        Type::BOOL => row.try_get::<_, Option<bool>>(idx).map(text),
+        Type::INT2 => row.try_get::<_, Option<i16>>(idx).map(text),
+        Type::INT4 => row.try_get::<_, Option<i32>>(idx).map(text),
+        Type::INT8 => row.try_get::<_, Option<i64>>(idx).map(text),
+        Type::FLOAT4 => row.try_get::<_, Option<f32>>(idx).map(text),
+        Type::FLOAT8 => row.try_get::<_, Option<f64>>(idx).map(text),
+        Type::UUID => row.try_get::<_, Option<uuid::Uuid>>(idx).map(text),
+        Type::DATE => row.try_get::<_, Option<chrono::NaiveDate>>(idx).map(text),
+        Type::TIMESTAMP => row
+            .try_get::<_, Option<chrono::NaiveDateTime>>(idx)
+            .map(text),
+        Type::TIMESTAMPTZ => row
+            .try_get::<_, Option<chrono::DateTime<chrono::Utc>>>(idx)
+            .map(text),
+        Type::JSON | Type::JSONB => row.try_get::<_, Option<serde_json::Value>>(idx).map(text),
+        ref v if <String as tokio_postgres::types::FromSql>::accepts(v) => {
+            row.try_get::<_, Option<String>>(idx).map(text)
+        }
+        ref v => {
+            return Err(format!(
+                "column '{}' of type {} can't be exported, cast it to text in the query",
+                column.name(),
+                v
+            ))
+        }
+    };
+    res.map_err(|e| e.to_string())
+}
+
+/// runs `sql` and streams the result as `dialect` csv, without a temp file;
+/// the rows are read while the client takes the chunks, so the memory use stays bounded.
+/// An error after the first chunk can only cut the download short, it is logged
+pub fn stream_csv(
+    ctx: web::Data<AppContext>,
+    sql: String,
+    params: Vec<String>,
+    dialect: CsvDialect,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let (tx, rx) = mpsc::channel::<Result<Bytes, actix_web::Error>>(CHANNEL_CHUNKS);
+    actix_web::rt::spawn(async move {
+        let (sql_ref, params_ref, dialect_ref, tx_ref) = (&sql, &params, &dialect, &tx);
+        let res = ctx
+            .pgsql_pool
+            .conn_run(
+                |conn| async move {
+                    Ok(send_rows(&conn, sql_ref, params_ref, dialect_ref, tx_ref).await)
+                },
+                STREAM_TIMEOUT,
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|v| v);
+        if let Err(err) = res {
+            log::error!("CSV export -> {}", err);
+            let _ = tx
+                .send(Err(actix_web::error::ErrorExpectationFailed(err)))
+                .await;
+        }
+    });
+    futures_util::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|v| (v, rx)) })
+}
+
+async fn send_rows(
+    client: &tokio_postgres::Client,
+    sql: &str,
+    params: &[String],
+    dialect: &CsvDialect,
+    tx: &mpsc::Sender<Result<Bytes, actix_web::Error>>,
+) -> Result<(), String> {
+    let statement = client.prepare(sql).await.map_err(|e| e.to_string())?;
+    let mut chunks = CsvChunks::new(dialect);
+    if dialect.header {
+        chunks
+            .push(statement.columns().iter().map(|v| v.name()))
+            .map_err(|e| e.to_string())?;
+    }
+
+    let rows = client
+        .query_raw(&statement, params.iter())
+        .await
+        .map_err(|e| e.to_string())?;
+    futures_util::pin_mut!(rows);
+    while let Some(row) = rows.try_next().await.map_err(|e| e.to_string())? {
+        let cells = (0..row.len())
+            .map(|i| cell_text(&row, i))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(chunk) = chunks.push(cells).map_err(|e| e.to_string())? {
+            if tx.send(Ok(chunk)).await.is_err() {
+                // client went away
+                return Ok(());
+            }
+        }
+    }
+    let _ = tx.send(Ok(chunks.take())).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn csv_chunks_split_and_keep_every_line() {
+        let mut chunks = super::CsvChunks::default();
+        let mut res = Vec::new();
+        assert_eq!(None, chunks.push(["app_code", "descr"]).unwrap());
+        let descr = "x".repeat(1000);
+        for i in 0..200 {
+            if let Some(chunk) = chunks.push([format!("app_{}", i), descr.clone()]).unwrap() {
+                assert!(chunk.len() >= super::CHUNK_BYTES);
+                res.extend_from_slice(&chunk);
+            }
+        }
+        res.extend_from_slice(&chunks.take());
+
+        let text = String::from_utf8(res).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(201, lines.len());
+        assert_eq!("app_code,descr", lines[0]);
+        assert_eq!(format!("app_199,{}", descr), lines[200]);
+    }
+
+    #[test]
+    fn csv_chunks_follow_dialect() {
+        let dialect = super::CsvDialect {
+            delimiter: b';',
+            quote: b'\'',
+            line_ending: super::LineEnding::Lf,
+            bom: true,
+            ..Default::default()
+        };
+        let mut chunks = super::CsvChunks::new(&dialect);
+        chunks
+            .push(["portal", "descriere; cu diacritice ăşţ"])
+            .unwrap();
+        assert_eq!(
+            "\u{feff}portal;'descriere; cu diacritice ăşţ'\n".as_bytes(),
+            &chunks.take()[..]
+        );
+
+        let dialect = super::CsvDialect {
+            encoding: encoding_rs::WINDOWS_1250,
+            ..Default::default()
+        };
+        let mut chunks = super::CsvChunks::new(&dialect);
+        chunks.push(["portal", "ăşţ ✓"]).unwrap();
+        assert_eq!(b"portal,\xE3\xBA\xFE ?\r\n", &chunks.take()[..]);
+
+        // the comma below forms are written as the cedilla ones of Windows-1250
+        chunks.push(["portal", "șțȘȚ"]).unwrap();
+        assert_eq!(b"portal,\xBA\xFE\xAA\xDE\r\n", &chunks.take()[..]);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::AppContext;
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::{collections::BTreeMap, time::Duration};
+
+/// announcements for the authenticated user; optional query parameter "unread=true"
+pub async fn announcement_list(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let query = crate::helper::get_req_query_params(&req)?;
+    let unread_only = query.get("unread").map(|v| v == "true").unwrap_or(false);
+    let res = crate::model::announcement::db_announcement_get_for_user(
+        &user_id,
+        unread_only,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// saves the announcement and queues one mail for each user of its groups,
+/// in the user's language; the mail outbox worker sends them in batches
+pub async fn announcement_create(
+    ctx: web::Data<AppContext>,
+    announcement: web::Json<crate::model::announcement::Announcement>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    if announcement.title.trim().is_empty() || announcement.message.trim().is_empty() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "title and message are mandatory",
+        ));
+    }
+    if announcement.groups.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest("no target groups"));
+    }
+
+    //render once per language
+    let recipients = crate::model::announcement::db_announcement_get_recipients(
+        &announcement.groups,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    let mut by_language: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for recipient in recipients {
+        let lang = ctx
+            .mail_templates
+            .pick_language(recipient.language.as_deref(), None);
+        by_language.entry(lang).or_default().push(recipient.user_id);
+    }
+    let mut mails = Vec::with_capacity(by_language.len());
+    for (lang, user_ids) in by_language {
+        let mail = crate::mail::render_mail(
+            "announcement",
+            &lang,
+            &[
+                ("title", announcement.title.as_str()),
+                ("message", announcement.message.as_str()),
+            ],
+            &ctx,
+        )
+        .await?;
+        mails.push(crate::model::announcement::AnnouncementMail { user_ids, mail });
+    }
+
+    //the announcement and its mails are saved together
+    let Some(res) = crate::model::announcement::db_announcement_insert(&announcement, &mod_de.sub, &mails, &ctx, Duration::from_secs(10)).await? else {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "unknown group in {:?}",
+            announcement.groups
+        )));
+    };
+    let Some(id) = res.id else {
+        return Err(actix_web::error::ErrorExpectationFailed(
+            "announcement without id",
+        ));
+    };
+    for v in mails.iter() {
+        log::info!("Announcement {} -> {} mails queued", id, v.user_ids.len());
+    }
+    crate::notify::notify(
+        crate::model::notification::NotificationKind::Announcement,
+        &[],
+        &res.groups,
+        &res.title,
+        &res.message,
+        &ctx,
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn announcement_mark_read(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let res = crate::model::announcement::db_announcement_mark_read(
+        &id,
+        &user_id,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body("element not found")
+    })
+}
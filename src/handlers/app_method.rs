@@ -1,349 +1,681 @@
-use crate::{extractors::multipart::MultipartFormData, AppContext};
-use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
-use std::time::Duration;
-
-pub async fn app_method_get_app_code_list(
-    ctx: web::Data<AppContext>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let res = crate::model::app_method::db_get_app_code_list(&ctx, Duration::from_secs(10)).await?;
-    Ok(HttpResponse::Ok().json(res))
-}
-
-/// optional query parameter for app_code is "q"
-pub async fn app_method_list(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
-    let Some(ctx) = req.app_data() else {
-        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
-    };
-    let query = crate::helper::get_req_query_params(&req)?;
-    let res = match query.get("q").map(|v| crate::model::app_method::AppCode {
-        app_code: v.to_owned(),
-    }) {
-        Some(app_zone) => {
-            crate::model::app_method::db_get_methods_by_app_code(
-                &app_zone,
-                &ctx,
-                Duration::from_secs(10),
-            )
-            .await?
-        }
-        None => crate::model::app_method::db_get_methods_all(&ctx, Duration::from_secs(10)).await?,
-    };
-    Ok(HttpResponse::Ok().json(res))
-}
-
-pub async fn app_method_get_single_by_id(
-    ctx: web::Data<AppContext>,
-    id: web::Path<uuid::Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let res =
-        crate::model::app_method::db_method_get_by_id(&id, &ctx, Duration::from_secs(10)).await?;
-    Ok(HttpResponse::Ok().json(res))
-}
-
-pub async fn app_method_delete_by_id(
-    ctx: web::Data<AppContext>,
-    id: web::Path<uuid::Uuid>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let res = crate::model::app_method::db_method_delete_by_id(&id, &ctx, Duration::from_secs(10))
-        .await?;
-    Ok(if res > 0 {
-        HttpResponse::Ok().finish()
-    } else {
-        HttpResponse::NoContent().body(format!("element not found"))
-    })
-}
-
-pub async fn app_method_single_upsert(
-    ctx: web::Data<AppContext>,
-    method: web::Json<crate::model::app_method::AppMethod>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-) -> Result<HttpResponse, actix_web::Error> {
-    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
-    let res = crate::model::app_method::db_method_single_upsert(
-        &method,
-        &mod_de.sub,
-        &ctx,
-        Duration::from_secs(10),
-    )
-    .await?;
-    Ok(HttpResponse::Ok().json(res))
-}
-
-/// optional query parameter for app_code is "q"
-pub async fn app_method_down_xlsx(
-    req: HttpRequest,
-) -> Result<actix_files::NamedFile, actix_web::Error> {
-    let Some(ctx) = req.app_data() else {
-        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
-    };
-    let mod_de = crate::extractors::auth::AuthenticateData::from_request(
-        &req,
-        &mut actix_web::dev::Payload::None,
-    )
-    .await?
-    .1;
-    let query = crate::helper::get_req_query_params(&req)?;
-    let (tmp_file, content_disposition) =
-        match query.get("q").map(|v| crate::model::app_method::AppCode {
-            app_code: v.to_owned(),
-        }) {
-            Some(app_zone) => (
-                crate::helper::TempFile {
-                    path: crate::model::app_method::db_methods_by_app_code_down_xlsx(
-                        &app_zone,
-                        &mod_de.sub,
-                        &ctx,
-                        Duration::from_secs(30),
-                    )
-                    .await?,
-                },
-                actix_web::http::header::ContentDisposition {
-                    disposition: actix_web::http::header::DispositionType::Attachment,
-                    parameters: vec![actix_web::http::header::DispositionParam::Filename(
-                        format!("app_transactions_for_{}.xlsx", app_zone.app_code),
-                    )],
-                },
-            ),
-            None => (
-                crate::helper::TempFile {
-                    path: crate::model::app_method::db_methods_all_down_xlsx(
-                        &mod_de.sub,
-                        &ctx,
-                        Duration::from_secs(30),
-                    )
-                    .await?,
-                },
-                actix_web::http::header::ContentDisposition {
-                    disposition: actix_web::http::header::DispositionType::Attachment,
-                    parameters: vec![actix_web::http::header::DispositionParam::Filename(
-                        "app_transactions_all.xlsx".into(),
-                    )],
-                },
-            ),
-        };
-
-    actix_files::NamedFile::open_async(tmp_file.path.as_path())
-        .await
-        .map(|f| f.set_content_disposition(content_disposition))
-        .map_err(|err| actix_web::Error::from(err))
-}
-
-/// optional fields:
-/// - "app_code", type String
-/// - "sheet_name", type String
-pub async fn app_method_up_xlsx(
-    ctx: web::Data<AppContext>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-    payload: actix_multipart::Multipart,
-) -> Result<HttpResponse, actix_web::Error> {
-    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
-    let file_prefix = format!("u-{}", mod_de.sub);
-    let temp_dir = ctx.general.temp_dir.as_path();
-
-    let form_data = MultipartFormData::from_multipart(
-        temp_dir,
-        &file_prefix,
-        payload,
-        Some(1),
-        Some(&["xlsx"]),
-    )
-    .await
-    .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-
-    if form_data.file_paths.len() != 1 {
-        return Err(actix_web::error::ErrorBadRequest(
-            "no file with 'xlsx' extension loaded",
-        ));
-    }
-
-    let app_code = form_data.fields.get("app_code");
-    let sheet_name = form_data.fields.get("sheet_name");
-    let file_path = form_data.file_paths.first().unwrap();
-
-    let res = if let Some(v) = app_code {
-        let app_zone = crate::model::app_method::AppCode {
-            app_code: v.to_owned(),
-        };
-        crate::model::app_method::db_methods_by_app_code_up_xlsx(
-            &app_zone,
-            &mod_de.sub,
-            &file_path.path,
-            sheet_name.map(String::as_str),
-            &ctx,
-            Duration::from_secs(60),
-        )
-        .await?
-    } else {
-        crate::model::app_method::db_methods_all_up_xlsx(
-            &mod_de.sub,
-            &file_path.path,
-            sheet_name.map(String::as_str),
-            &ctx,
-            Duration::from_secs(60),
-        )
-        .await?
-    };
-
-    Ok(HttpResponse::Ok().body(res.to_string()))
-}
-
-/// optional query parameter for app_code is "q"
-pub async fn app_method_down_csv(
-    req: HttpRequest,
-) -> Result<actix_files::NamedFile, actix_web::Error> {
-    let Some(ctx) = req.app_data() else {
-        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
-    };
-    let mod_de = crate::extractors::auth::AuthenticateData::from_request(
-        &req,
-        &mut actix_web::dev::Payload::None,
-    )
-    .await?
-    .1;
-    let query = crate::helper::get_req_query_params(&req)?;
-    let (tmp_file, content_disposition) =
-        match query.get("q").map(|v| crate::model::app_method::AppCode {
-            app_code: v.to_owned(),
-        }) {
-            Some(app_zone) => (
-                crate::helper::TempFile {
-                    path: crate::model::app_method::db_methods_by_app_code_down_csv(
-                        &app_zone,
-                        &mod_de.sub,
-                        &ctx,
-                        Duration::from_secs(30),
-                    )
-                    .await?,
-                },
-                actix_web::http::header::ContentDisposition {
-                    disposition: actix_web::http::header::DispositionType::Attachment,
-                    parameters: vec![actix_web::http::header::DispositionParam::Filename(
-                        format!("app_transactions_for_{}.csv", app_zone.app_code),
-                    )],
-                },
-            ),
-            None => (
-                crate::helper::TempFile {
-                    path: crate::model::app_method::db_methods_all_down_csv(
-                        &mod_de.sub,
-                        &ctx,
-                        Duration::from_secs(30),
-                    )
-                    .await?,
-                },
-                actix_web::http::header::ContentDisposition {
-                    disposition: actix_web::http::header::DispositionType::Attachment,
-                    parameters: vec![actix_web::http::header::DispositionParam::Filename(
-                        "app_transactions_all.csv".into(),
-                    )],
-                },
-            ),
-        };
-
-    actix_files::NamedFile::open_async(tmp_file.path.as_path())
-        .await
-        .map(|f| f.set_content_disposition(content_disposition))
-        .map_err(|err| actix_web::Error::from(err))
-}
-
-/// mandatory fields:
-/// - **column_delimiter**, validated by regex `^[,;\t|/]{1}$`
-///
-/// optional fields:
-/// - **column_quote**, validated by regex `^["'|\\/]{1}$`
-/// - **column_quote_escape**, validated by regex `^["'|\\/]{1}$`
-/// - **app_code**, type String
-pub async fn app_method_up_txt(
-    ctx: web::Data<AppContext>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-    payload: actix_multipart::Multipart,
-) -> Result<HttpResponse, actix_web::Error> {
-    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
-    let file_prefix = format!("u-{}", mod_de.sub);
-    let temp_dir = ctx.general.temp_dir.as_path();
-
-    let form_data = MultipartFormData::from_multipart(
-        temp_dir,
-        &file_prefix,
-        payload,
-        Some(1),
-        Some(&["xlsx", "txt"]),
-    )
-    .await
-    .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-
-    if form_data.file_paths.len() != 1 {
-        return Err(actix_web::error::ErrorBadRequest(
-            "no file with 'csv' or 'txt' extension loaded",
-        ));
-    }
-
-    let column_delimiter_regex = regex::Regex::new(crate::Consts::TXT_FILE_COLUMN_DELIM)
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    let column_delimiter = if let Some(v) = form_data.fields.get("column_delimiter") {
-        if !column_delimiter_regex.is_match(v) {
-            return Err(actix_web::error::ErrorBadRequest(
-                "value supplied for field 'column_delimiter' is not correct",
-            ));
-        }
-        v.as_bytes()[0]
-    } else {
-        return Err(actix_web::error::ErrorBadRequest(
-            "no value was supplied for mandatory field 'column_delimiter'",
-        ));
-    };
-    let column_quote_regex = regex::Regex::new(crate::Consts::TXT_FILE_COLUMN_QUOTE)
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    let column_quote = if let Some(v) = form_data.fields.get("column_quote") {
-        if !column_quote_regex.is_match(v) {
-            return Err(actix_web::error::ErrorBadRequest(
-                "value supplied for field 'column_quote' is not correct",
-            ));
-        }
-        Some(v.as_bytes()[0])
-    } else {
-        None
-    };
-    let column_quote_escape = if let Some(v) = form_data.fields.get("column_quote_escape") {
-        if !column_quote_regex.is_match(v) {
-            return Err(actix_web::error::ErrorBadRequest(
-                "value supplied for field 'column_quote_escape' is not correct",
-            ));
-        }
-        Some(v.as_bytes()[0])
-    } else {
-        None
-    };
-    let app_code = form_data.fields.get("app_code");
-    let file_path = form_data.file_paths.first().unwrap();
-
-    let res = if let Some(v) = app_code {
-        let app_zone = crate::model::app_method::AppCode {
-            app_code: v.to_owned(),
-        };
-        crate::model::app_method::db_methods_by_app_code_up_txt(
-            &app_zone,
-            &mod_de.sub,
-            &file_path.path,
-            column_delimiter,
-            column_quote,
-            column_quote_escape,
-            &ctx,
-            Duration::from_secs(60),
-        )
-        .await?
-    } else {
-        crate::model::app_method::db_methods_all_up_txt(
-            &mod_de.sub,
-            &file_path.path,
-            column_delimiter,
-            column_quote,
-            column_quote_escape,
-            &ctx,
-            Duration::from_secs(60),
-        )
-        .await?
-    };
-
-    Ok(HttpResponse::Ok().body(res.to_string()))
-}
+use crate::{
+    export::{CsvDialect, LineEnding},
+    extractors::multipart::MultipartFormData,
+    upload::{ErrorMode, ImportOptions, UploadFormat, UploadReport},
+    AppContext,
+};
+use actix_web::{http::header, web, FromRequest, HttpRequest, HttpResponse};
+use std::time::Duration;
+
+pub async fn app_method_get_app_code_list(
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let res = crate::model::app_method::db_get_app_code_list(&ctx, Duration::from_secs(10)).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// optional query parameter for app_code is "q"
+pub async fn app_method_list(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let Some(ctx) = req.app_data() else {
+        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
+    };
+    let query = crate::helper::get_req_query_params(&req)?;
+    let res = match query.get("q").map(|v| crate::model::app_method::AppCode {
+        app_code: v.to_owned(),
+    }) {
+        Some(app_zone) => {
+            crate::model::app_method::db_get_methods_by_app_code(
+                &app_zone,
+                &ctx,
+                Duration::from_secs(10),
+            )
+            .await?
+        }
+        None => crate::model::app_method::db_get_methods_all(&ctx, Duration::from_secs(10)).await?,
+    };
+    let res: Vec<crate::model::app_method::AppMethodTagged> =
+        res.into_iter().map(Into::into).collect();
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn app_method_get_single_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let res =
+        crate::model::app_method::db_method_get_by_id(&id, &ctx, Duration::from_secs(10)).await?;
+    let mut builder = HttpResponse::Ok();
+    if let Some(etag) = res.as_ref().and_then(|v| v.etag()) {
+        builder.insert_header(header::ETag(etag));
+    }
+    Ok(builder.json(res))
+}
+
+/// with an `If-Match` header the method is deleted only if its ETag still matches, else 412
+pub async fn app_method_delete_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    if_match: Option<web::Header<header::IfMatch>>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let if_match = crate::helper::if_match_tags(if_match.as_deref());
+    let Some(method) =
+        crate::model::app_method::db_method_get_by_id(&id, &ctx, Duration::from_secs(10)).await? else {
+        return Ok(HttpResponse::NoContent().body("element not found"));
+    };
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, Some(&method.app_code), &ctx).await?;
+
+    let res = crate::model::app_method::db_method_delete_by_id(
+        &id,
+        &mod_de.sub,
+        if_match.as_deref(),
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    // found above, so the ETag didn't match or the method changed meanwhile
+    if res == 0 && if_match.is_some() {
+        return Err(actix_web::error::ErrorPreconditionFailed(
+            "app method was changed by someone else",
+        ));
+    }
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body(format!("element not found"))
+    })
+}
+
+/// changes of the method, newest first; also answers for deleted methods
+pub async fn app_method_history(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let res =
+        crate::model::app_method::db_method_history_get_by_id(&id, &ctx, Duration::from_secs(10))
+            .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// with an `If-Match` header only an existing method with a matching ETag is updated, else 412
+pub async fn app_method_single_upsert(
+    ctx: web::Data<AppContext>,
+    method: web::Json<crate::model::app_method::AppMethod>,
+    if_match: Option<web::Header<header::IfMatch>>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let if_match = crate::helper::if_match_tags(if_match.as_deref());
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, Some(&method.app_code), &ctx).await?;
+
+    let Some(res) = crate::model::app_method::db_method_single_upsert(
+        &method,
+        &mod_de.sub,
+        if_match.as_deref(),
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await? else {
+        return Err(actix_web::error::ErrorPreconditionFailed(
+            "app method doesn't exist or was changed by someone else",
+        ));
+    };
+    let mut builder = HttpResponse::Ok();
+    if let Some(etag) = res.etag() {
+        builder.insert_header(header::ETag(etag));
+    }
+    Ok(builder.json(res))
+}
+
+/// optional query parameter for app_code is "q"
+pub async fn app_method_down_xlsx(
+    req: HttpRequest,
+) -> Result<actix_files::NamedFile, actix_web::Error> {
+    let Some(ctx) = req.app_data() else {
+        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
+    };
+    let mod_de = crate::extractors::auth::AuthenticateData::from_request(
+        &req,
+        &mut actix_web::dev::Payload::None,
+    )
+    .await?
+    .1;
+    let query = crate::helper::get_req_query_params(&req)?;
+    let (tmp_file, content_disposition) =
+        match query.get("q").map(|v| crate::model::app_method::AppCode {
+            app_code: v.to_owned(),
+        }) {
+            Some(app_zone) => (
+                crate::helper::TempFile {
+                    path: crate::model::app_method::db_methods_by_app_code_down_xlsx(
+                        &app_zone,
+                        &mod_de.sub,
+                        &ctx,
+                        Duration::from_secs(30),
+                    )
+                    .await?,
+                },
+                actix_web::http::header::ContentDisposition {
+                    disposition: actix_web::http::header::DispositionType::Attachment,
+                    parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                        format!("app_transactions_for_{}.xlsx", app_zone.app_code),
+                    )],
+                },
+            ),
+            None => (
+                crate::helper::TempFile {
+                    path: crate::model::app_method::db_methods_all_down_xlsx(
+                        &mod_de.sub,
+                        &ctx,
+                        Duration::from_secs(30),
+                    )
+                    .await?,
+                },
+                actix_web::http::header::ContentDisposition {
+                    disposition: actix_web::http::header::DispositionType::Attachment,
+                    parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                        "app_transactions_all.xlsx".into(),
+                    )],
+                },
+            ),
+        };
+
+    actix_files::NamedFile::open_async(tmp_file.path.as_path())
+        .await
+        .map(|f| f.set_content_disposition(content_disposition))
+        .map_err(|err| actix_web::Error::from(err))
+}
+
+/// empty workbook for the xlsx uploads: the expected columns, with notes, and the app codes in a dropdown
+pub async fn app_method_template_xlsx(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<actix_files::NamedFile, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let app_codes: Vec<String> =
+        crate::model::app_method::db_get_app_code_list(&ctx, Duration::from_secs(10))
+            .await?
+            .into_iter()
+            .map(|v| v.app_code)
+            .collect();
+    let table = crate::upload::APP_METHOD_TABLE;
+    let tmp_file = crate::helper::TempFile {
+        path: crate::upload::template::file_path(&ctx.general.temp_dir, &mod_de.sub),
+    };
+
+    let path = tmp_file.path.clone();
+    web::block(move || {
+        crate::upload::template::write_template(
+            &path,
+            &table,
+            &[(crate::upload::COLUMN_APP_CODE, app_codes)],
+        )
+    })
+    .await?
+    .map_err(actix_web::error::ErrorExpectationFailed)?;
+
+    actix_files::NamedFile::open_async(tmp_file.path.as_path())
+        .await
+        .map(|f| {
+            f.set_content_disposition(actix_web::http::header::ContentDisposition {
+                disposition: actix_web::http::header::DispositionType::Attachment,
+                parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                    crate::upload::template::download_name(&table),
+                )],
+            })
+        })
+        .map_err(actix_web::Error::from)
+}
+
+/// optional fields:
+/// - "app_code", type String; without it the upload may touch every app code
+///   and needs global app administration rights
+/// - "sheet_name", type String
+/// - "dry_run", "true" or "false"; when true the file is only checked and the answer
+///   is the rows that would be inserted, updated or left unchanged, plus the rejected ones
+/// - "error_mode", "all_or_nothing" (default) or "skip_bad_rows"
+/// - "sync", "true" or "false"; needs "app_code", whose methods missing from the file get deleted,
+///   except the ones still granted to some group (reported as `in_use`); bad rows stop a sync
+/// - "column_map", JSON object of file header -> column, e.g. `{"Text": "descr"}`, for headers that
+///   are neither column names nor their Romanian/ English aliases
+///
+/// answers with an `UploadReport`; 400 when bad rows stopped an "all_or_nothing" upload
+pub async fn app_method_up_xlsx(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+    payload: actix_multipart::Multipart,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let file_prefix = format!("u-{}", mod_de.sub);
+    let temp_dir = ctx.general.temp_dir.as_path();
+
+    let form_data = MultipartFormData::from_multipart(
+        temp_dir,
+        &file_prefix,
+        payload,
+        Some(1),
+        Some(&["xlsx"]),
+    )
+    .await
+    .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+
+    if form_data.file_paths.len() != 1 {
+        return Err(actix_web::error::ErrorBadRequest(
+            "no file with 'xlsx' extension loaded",
+        ));
+    }
+
+    let app_code = form_data.fields.get("app_code");
+    let sheet_name = form_data.fields.get("sheet_name");
+    let file_path = form_data.file_paths.first().unwrap();
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, app_code.map(String::as_str), &ctx)
+        .await?;
+    let format = UploadFormat::Xlsx {
+        sheet_name: sheet_name.cloned(),
+    };
+    upload_file(
+        &file_path.path,
+        format,
+        app_code,
+        &form_data.fields,
+        &mod_de.sub,
+        &ctx,
+    )
+    .await
+}
+
+/// the csv is streamed straight from the db; optional query parameters:
+/// - "q", the app_code
+/// - "delimiter", validated by regex `^[,;\t|/]{1}$`, default ","
+/// - "quote", validated by regex `^["'|\\/]{1}$`, default `"`
+/// - "line_ending", "crlf" (default) or "lf"
+/// - "header", "true" (default) or "false"
+/// - "bom", "true" or "false" (default); UTF-8 only
+/// - "encoding", a name like "utf-8" (default) or "windows-1250"; the comma below "ș"/ "ț"
+///   are written as the cedilla "ş"/ "ţ" when only those are in it, other missing characters
+///   as "?"
+///
+/// e.g. `?delimiter=;&bom=true` for Excel in Romanian locale
+pub async fn app_method_down_csv(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let Some(ctx) = req.app_data::<web::Data<AppContext>>() else {
+        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
+    };
+    let query = crate::helper::get_req_query_params(&req)?;
+    let dialect = csv_dialect(&query)?;
+    let (sql, params, file_name) = match query.get("q") {
+        Some(app_code) => (
+            ctx.general
+                .get_sql("pgsql_api_app_mthd_get_method_list_for_app_code.sql")?,
+            vec![app_code.to_owned()],
+            format!("app_transactions_for_{}.csv", app_code),
+        ),
+        None => (
+            ctx.general
+                .get_sql("pgsql_api_app_mthd_get_method_all.sql")?,
+            Vec::new(),
+            "app_transactions_all.csv".to_string(),
+        ),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(format!(
+            "text/csv; charset={}",
+            dialect.encoding.name().to_lowercase()
+        ))
+        .insert_header(actix_web::http::header::ContentDisposition {
+            disposition: actix_web::http::header::DispositionType::Attachment,
+            parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                file_name,
+            )],
+        })
+        .streaming(crate::export::stream_csv(ctx.clone(), sql, params, dialect)))
+}
+
+/// mandatory fields:
+/// - **column_delimiter**, validated by regex `^[,;\t|/]{1}$`
+///
+/// optional fields:
+/// - **column_quote**, validated by regex `^["'|\\/]{1}$`
+/// - **column_quote_escape**, validated by regex `^["'|\\/]{1}$`
+/// - **encoding**, "auto" (default) or a name like "utf-8" or "windows-1250"; auto reads the BOM
+///   when present, else UTF-8 when the file is valid UTF-8, else Windows-1250
+/// - **app_code**, type String; without it the upload may touch every app code
+///   and needs global app administration rights
+/// - **dry_run**, "true" or "false"; when true the file is only checked and the answer
+///   is the rows that would be inserted, updated or left unchanged, plus the rejected ones
+/// - **error_mode**, "all_or_nothing" (default) or "skip_bad_rows"
+/// - **sync**, "true" or "false"; needs **app_code**, whose methods missing from the file get
+///   deleted, except the ones still granted to some group (reported as `in_use`); bad rows stop a sync
+/// - **column_map**, JSON object of file header -> column, e.g. `{"Text": "descr"}`, for headers
+///   that are neither column names nor their Romanian/ English aliases
+///
+/// answers with an `UploadReport`; 400 when bad rows stopped an "all_or_nothing" upload
+pub async fn app_method_up_txt(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+    payload: actix_multipart::Multipart,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let file_prefix = format!("u-{}", mod_de.sub);
+    let temp_dir = ctx.general.temp_dir.as_path();
+
+    let form_data = MultipartFormData::from_multipart(
+        temp_dir,
+        &file_prefix,
+        payload,
+        Some(1),
+        Some(&["csv", "txt"]),
+    )
+    .await
+    .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+
+    if form_data.file_paths.len() != 1 {
+        return Err(actix_web::error::ErrorBadRequest(
+            "no file with 'csv' or 'txt' extension loaded",
+        ));
+    }
+
+    let format = text_format(&form_data.fields)?;
+    let app_code = form_data.fields.get("app_code");
+    let file_path = form_data.file_paths.first().unwrap();
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, app_code.map(String::as_str), &ctx)
+        .await?;
+    upload_file(
+        &file_path.path,
+        format,
+        app_code,
+        &form_data.fields,
+        &mod_de.sub,
+        &ctx,
+    )
+    .await
+}
+
+/// optional query parameters:
+/// - "q", the app_code
+/// - "ndjson", "true" or "false"; when true the answer is one JSON method per line
+pub async fn app_method_down_json(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let Some(ctx) = req.app_data::<web::Data<AppContext>>() else {
+        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
+    };
+    let query = crate::helper::get_req_query_params(&req)?;
+    let ndjson = form_flag(&query, "ndjson")?;
+    let res = match query.get("q").map(|v| crate::model::app_method::AppCode {
+        app_code: v.to_owned(),
+    }) {
+        Some(app_zone) => {
+            crate::model::app_method::db_get_methods_by_app_code(
+                &app_zone,
+                ctx,
+                Duration::from_secs(30),
+            )
+            .await?
+        }
+        None => crate::model::app_method::db_get_methods_all(ctx, Duration::from_secs(30)).await?,
+    };
+    if !ndjson {
+        return Ok(HttpResponse::Ok().json(res));
+    }
+    let mut body = String::new();
+    for v in res.iter() {
+        body.push_str(&serde_json::to_string(v)?);
+        body.push('\n');
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .body(body))
+}
+
+/// bulk upsert of a JSON array of methods, saved all or none; `id`, `mod_de` and `mod_timp`
+/// are filled by the db. Answers with an `UploadReport`, 400 when some items are not valid
+pub async fn app_method_up_json(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+    methods: web::Json<Vec<crate::model::app_method::AppMethod>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let parsed = crate::upload::from_methods(methods.into_inner())
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let methods: Vec<crate::model::app_method::AppMethod> =
+        parsed.rows.iter().map(|v| v.method.clone()).collect();
+    let app_codes: std::collections::BTreeSet<&str> =
+        methods.iter().map(|v| v.app_code.as_str()).collect();
+    for app_code in app_codes {
+        crate::handlers::app_owner::check_app_scope(&mod_de.sub, Some(app_code), &ctx).await?;
+    }
+
+    let existing =
+        crate::model::app_method::db_methods_get_by_codes(&methods, &ctx, Duration::from_secs(60))
+            .await?;
+    let report = UploadReport::from_existing(&parsed, &existing, false);
+    if !report.rejected.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(report));
+    }
+    let res = crate::model::app_method::db_methods_upsert_rows(
+        &methods,
+        None,
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(60),
+    )
+    .await?;
+    crate::upload::notify_upload_finished(&mod_de.sub, res, &ctx).await;
+    Ok(HttpResponse::Ok().json(UploadReport {
+        saved: true,
+        ..report
+    }))
+}
+
+/// the "column_*" and "encoding" form fields of a text upload
+pub fn text_format(
+    fields: &std::collections::HashMap<String, String>,
+) -> Result<UploadFormat, actix_web::Error> {
+    let column_delimiter_regex = regex::Regex::new(crate::Consts::TXT_FILE_COLUMN_DELIM)
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    let column_delimiter = if let Some(v) = fields.get("column_delimiter") {
+        if !column_delimiter_regex.is_match(v) {
+            return Err(actix_web::error::ErrorBadRequest(
+                "value supplied for field 'column_delimiter' is not correct",
+            ));
+        }
+        v.as_bytes()[0]
+    } else {
+        return Err(actix_web::error::ErrorBadRequest(
+            "no value was supplied for mandatory field 'column_delimiter'",
+        ));
+    };
+    let column_quote_regex = regex::Regex::new(crate::Consts::TXT_FILE_COLUMN_QUOTE)
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    let column_quote = if let Some(v) = fields.get("column_quote") {
+        if !column_quote_regex.is_match(v) {
+            return Err(actix_web::error::ErrorBadRequest(
+                "value supplied for field 'column_quote' is not correct",
+            ));
+        }
+        Some(v.as_bytes()[0])
+    } else {
+        None
+    };
+    let column_quote_escape = if let Some(v) = fields.get("column_quote_escape") {
+        if !column_quote_regex.is_match(v) {
+            return Err(actix_web::error::ErrorBadRequest(
+                "value supplied for field 'column_quote_escape' is not correct",
+            ));
+        }
+        Some(v.as_bytes()[0])
+    } else {
+        None
+    };
+    let encoding = crate::upload::text_encoding(fields.get("encoding").map(String::as_str))
+        .map_err(|_| {
+            actix_web::error::ErrorBadRequest(
+                "value supplied for field 'encoding' is not correct, expected 'auto' or a name like 'utf-8' or 'windows-1250'",
+            )
+        })?;
+    Ok(UploadFormat::Text {
+        delimiter: column_delimiter,
+        quote: column_quote,
+        quote_escape: column_quote_escape,
+        encoding: encoding.map(|v| v.name().to_string()),
+    })
+}
+
+/// the "delimiter", "quote", "line_ending", "header", "bom" and "encoding" query parameters
+/// of a csv download
+pub fn csv_dialect(
+    query: &std::collections::HashMap<String, String>,
+) -> Result<CsvDialect, actix_web::Error> {
+    let mut res = CsvDialect::default();
+    let delimiter_regex = regex::Regex::new(crate::Consts::TXT_FILE_COLUMN_DELIM)
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    if let Some(v) = query.get("delimiter") {
+        if !delimiter_regex.is_match(v) {
+            return Err(actix_web::error::ErrorBadRequest(
+                "value supplied for query parameter 'delimiter' is not correct",
+            ));
+        }
+        res.delimiter = v.as_bytes()[0];
+    }
+    let quote_regex = regex::Regex::new(crate::Consts::TXT_FILE_COLUMN_QUOTE)
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    if let Some(v) = query.get("quote") {
+        if !quote_regex.is_match(v) {
+            return Err(actix_web::error::ErrorBadRequest(
+                "value supplied for query parameter 'quote' is not correct",
+            ));
+        }
+        res.quote = v.as_bytes()[0];
+    }
+    if res.quote == res.delimiter {
+        return Err(actix_web::error::ErrorBadRequest(
+            "query parameters 'delimiter' and 'quote' must differ",
+        ));
+    }
+    if let Some(v) = query.get("line_ending") {
+        res.line_ending = LineEnding::parse(v.trim()).ok_or_else(|| {
+            actix_web::error::ErrorBadRequest(
+                "value supplied for query parameter 'line_ending' is not correct, expected 'crlf' or 'lf'",
+            )
+        })?;
+    }
+    res.header = !query.contains_key("header") || form_flag(query, "header")?;
+    res.bom = form_flag(query, "bom")?;
+    if let Some(v) = query.get("encoding") {
+        // the UTF-16 encoders of encoding_rs write UTF-8, so they are not offered
+        res.encoding = encoding_rs::Encoding::for_label(v.trim().as_bytes())
+            .filter(|e| e.output_encoding() == *e)
+            .ok_or_else(|| {
+                actix_web::error::ErrorBadRequest(
+                    "value supplied for query parameter 'encoding' is not correct, expected a name like 'utf-8' or 'windows-1250'",
+                )
+            })?;
+    }
+    if res.bom && res.encoding != encoding_rs::UTF_8 {
+        return Err(actix_web::error::ErrorBadRequest(
+            "query parameter 'bom' needs the 'utf-8' encoding",
+        ));
+    }
+    Ok(res)
+}
+
+/// "true"/ "false" form field or query parameter, false when missing
+fn form_flag(
+    fields: &std::collections::HashMap<String, String>,
+    name: &str,
+) -> Result<bool, actix_web::Error> {
+    match fields.get(name).map(|v| v.trim()) {
+        None | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(_) => Err(actix_web::error::ErrorBadRequest(format!(
+            "value supplied for field '{}' is not correct, expected 'true' or 'false'",
+            name
+        ))),
+    }
+}
+
+/// 400 when bad rows stopped the upload
+async fn upload_file(
+    file_path: &std::path::Path,
+    format: UploadFormat,
+    app_code: Option<&String>,
+    fields: &std::collections::HashMap<String, String>,
+    user_id: &str,
+    ctx: &web::Data<AppContext>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let options = import_options(fields, app_code)?;
+    let report = crate::upload::import_file(
+        file_path,
+        &format,
+        &options,
+        user_id,
+        ctx,
+        Duration::from_secs(60),
+        None,
+    )
+    .await?;
+    if !report.dry_run && !report.saved {
+        return Ok(HttpResponse::BadRequest().json(report));
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// the "dry_run", "error_mode", "sync" and "column_map" form fields of an upload
+pub fn import_options(
+    fields: &std::collections::HashMap<String, String>,
+    app_code: Option<&String>,
+) -> Result<ImportOptions, actix_web::Error> {
+    let error_mode = match fields.get("error_mode") {
+        None => ErrorMode::AllOrNothing,
+        Some(v) => ErrorMode::parse(v.trim()).ok_or_else(|| {
+            actix_web::error::ErrorBadRequest(
+                "value supplied for field 'error_mode' is not correct, expected 'all_or_nothing' or 'skip_bad_rows'",
+            )
+        })?,
+    };
+    let sync = form_flag(fields, "sync")?;
+    if sync && app_code.is_none() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "field 'sync' needs the field 'app_code'",
+        ));
+    }
+    let column_map = match fields.get("column_map") {
+        None => Default::default(),
+        Some(v) => serde_json::from_str(v).map_err(|e| {
+            actix_web::error::ErrorBadRequest(format!(
+                "value supplied for field 'column_map' is not correct, expected a JSON object of file header -> column: {}",
+                e
+            ))
+        })?,
+    };
+    Ok(ImportOptions {
+        app_code: app_code.cloned(),
+        dry_run: form_flag(fields, "dry_run")?,
+        error_mode,
+        sync,
+        column_map,
+    })
+}
+
+/// rejected rows of an upload of the current user, with the reason in the last column
+pub async fn app_method_upload_errors(
+    ctx: web::Data<AppContext>,
+    name: web::Path<String>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<actix_files::NamedFile, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let Some(path) = crate::upload::error_file_path(&ctx.general.temp_dir, &mod_de.sub, &name) else {
+        return Err(actix_web::error::ErrorNotFound("error file not found"));
+    };
+    actix_files::NamedFile::open_async(path)
+        .await
+        .map(|f| {
+            f.set_content_disposition(actix_web::http::header::ContentDisposition {
+                disposition: actix_web::http::header::DispositionType::Attachment,
+                parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                    name.into_inner(),
+                )],
+            })
+        })
+        .map_err(actix_web::Error::from)
+}
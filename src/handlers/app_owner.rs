@@ -0,0 +1,80 @@
+use crate::AppContext;
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::time::Duration;
+
+/// fails if the user may not administer `app_code`; with no `app_code` the global right is required
+/// and so it is for `portal`, whose owners could otherwise grant themselves the admin methods
+pub async fn check_app_scope(
+    user_id: &str,
+    app_code: Option<&str>,
+    ctx: &web::Data<AppContext>,
+) -> Result<(), actix_web::Error> {
+    let allowed = crate::model::app_owner::db_check_app_scope(
+        user_id,
+        app_code,
+        ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    if !allowed {
+        return Err(actix_web::error::ErrorForbidden(match app_code {
+            Some(v) => format!("not an owner of app code '{}'", v),
+            None => "operation allowed only for global app administrators".to_string(),
+        }));
+    }
+    Ok(())
+}
+
+/// optional query parameter for app_code is "q"
+pub async fn app_owner_list(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let Some(ctx) = req.app_data() else {
+        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
+    };
+    let query = crate::helper::get_req_query_params(&req)?;
+    let res = match query.get("q") {
+        Some(app_code) => {
+            crate::model::app_owner::db_get_owners_by_app_code(
+                app_code,
+                ctx,
+                Duration::from_secs(10),
+            )
+            .await?
+        }
+        None => crate::model::app_owner::db_get_owners_all(ctx, Duration::from_secs(10)).await?,
+    };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn app_owner_single_upsert(
+    ctx: web::Data<AppContext>,
+    owner: web::Json<crate::model::app_owner::AppOwner>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let Some(res) = crate::model::app_owner::db_owner_single_upsert(
+        &owner,
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await? else {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "{} '{}' not found",
+            owner.owner_type, owner.owner_id
+        )));
+    };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn app_owner_delete_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let res =
+        crate::model::app_owner::db_owner_delete_by_id(&id, &ctx, Duration::from_secs(10)).await?;
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body("element not found")
+    })
+}
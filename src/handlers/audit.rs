@@ -0,0 +1,14 @@
+use crate::AppContext;
+use actix_web::{web, HttpResponse};
+use std::time::Duration;
+
+/// login/ authentication audit trail, filtered by the query string params
+/// `user_id`, `from`, `to` (dates as yyyy-mm-dd), `outcome` and `event`
+pub async fn auth_audit_list(
+    ctx: web::Data<AppContext>,
+    filter: web::Query<crate::model::audit::AuthAuditFilter>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let vals =
+        crate::model::audit::db_audit_get_filtered(&filter, &ctx, Duration::from_secs(10)).await?;
+    Ok(HttpResponse::Ok().json(vals))
+}
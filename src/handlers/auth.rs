@@ -1,213 +1,543 @@
-use actix_web::{web, FromRequest, HttpResponse};
-
-use crate::{extractors::auth::AuthClaims, AppContext};
-
-pub async fn user_login(
-    ctx: web::Data<AppContext>,
-    data: web::Json<crate::model::users::LoginData>,
-) -> Result<HttpResponse, actix_web::Error> {
-    //get user account from request
-    let Some(user) = crate::model::users::db_get_single(data.user_id.as_str(), &ctx, std::time::Duration::from_secs(10))
-        .await
-        .map_err(|e| actix_web::error::ErrorExpectationFailed(e))? else {
-        return Err(actix_web::error::ErrorUnauthorized("user not found"))
-    };
-
-    //get last token data
-    let last_token = crate::model::users::db_get_last_token_id(
-        &user.user_id,
-        &ctx,
-        std::time::Duration::from_secs(10),
-    )
-    .await?;
-
-    //check if login reuest is not too early
-    if let Some(token) = last_token {
-        let now = chrono::Utc::now();
-        let diff = now
-            .signed_duration_since(chrono::DateTime::<chrono::Utc>::from_utc(
-                token.mod_timp,
-                chrono::Utc,
-            ))
-            .num_minutes();
-        if diff <= 10 {
-            return Err(actix_web::error::ErrorNotAcceptable(format!(
-                "wait-minutes:{}",
-                diff
-            )));
-        }
-    }
-
-    //prepare claims for new token
-    let iat = chrono::Utc::now();
-    let exp = iat
-        .checked_add_signed(chrono::Duration::minutes(10))
-        .unwrap_or(iat);
-
-    let claims = AuthClaims {
-        iss: ctx.general.app_domain.clone(),
-        sub: user.user_id,
-        jti: uuid::Uuid::new_v4(),
-        iat: iat.timestamp(),
-        exp: exp.timestamp(),
-    };
-
-    let jwt = claims.create_token(&ctx)?;
-    let to_addrs = vec![lettre::message::Mailbox::new(
-        Some(format!("{} {}", user.first_name, user.last_name)),
-        user.email
-            .parse()
-            .map_err(|e| actix_web::error::ErrorExpectationFailed(e))?,
-    )];
-    let subject = "Portal CDG - Autentificare".to_string();
-    let message = format!(
-        r#"
-        <span>Pentru autentificarea in portalul CdG va rog accesati urmatorul link: 
-        <a href="{0}{1}/auth?{2}={3}">autentificare</a>.</span><br/>
-        <span>Acest link expira in 10 minute</span>
-    "#,
-        ctx.general.app_domain,
-        ctx.general.app_path,
-        crate::Consts::AUTH_COOKIE_NAME,
-        jwt
-    );
-
-    //send login link to user
-    let _ = ctx
-        .mailer
-        .send(to_addrs, None, &subject, &message, None, None)
-        .map_err(|e| actix_web::error::ErrorExpectationFailed(e))?;
-
-    //save new token data
-    let _ = crate::model::users::db_persist_last_token_id(
-        &claims.into(),
-        &ctx,
-        std::time::Duration::from_secs(10),
-    )
-    .await?;
-
-    //return response
-    Ok(HttpResponse::Ok().finish())
-}
-
-pub async fn authenticate(
-    ctx: web::Data<AppContext>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-) -> Result<HttpResponse, actix_web::Error> {
-    //get token from request query string
-    let mut claims = auth_data.1;
-    //create new long lived authentication token
-    let iat = chrono::Utc::now();
-    let exp = iat
-        .checked_add_signed(chrono::Duration::days(90))
-        .unwrap_or(iat);
-
-    claims.jti = uuid::Uuid::new_v4();
-    claims.iat = iat.timestamp();
-    claims.exp = exp.timestamp();
-
-    let jwt = claims.create_token(&ctx)?;
-    let app_path: &str = if ctx.general.is_in_dev {
-        "/"
-    } else {
-        &ctx.general.app_path
-    };
-    //save new token data
-    let _ = crate::model::users::db_persist_last_token_id(
-        &claims.into(),
-        &ctx,
-        std::time::Duration::from_secs(10),
-    )
-    .await?;
-    //send response
-    Ok(HttpResponse::Found()
-        .append_header((crate::Consts::AUTH_HEADER_NAME, jwt.as_str()))
-        .cookie(
-            actix_web::cookie::Cookie::build(crate::Consts::AUTH_COOKIE_NAME, jwt)
-                .path(app_path)
-                .http_only(true)
-                .secure(true)
-                .expires(time::OffsetDateTime::now_utc().checked_add(time::Duration::days(90)))
-                .finish(),
-        )
-        //.append_header((actix_web::http::header::LOCATION, app_path))
-        .finish())
-}
-
-pub async fn is_authenticated(req: actix_web::HttpRequest) -> HttpResponse {
-    let res = match crate::extractors::auth::AuthenticateData::from_request(
-        &req,
-        &mut actix_web::dev::Payload::None,
-    )
-    .await
-    {
-        Ok(_) => true,
-        Err(_) => false,
-    };
-    HttpResponse::Ok().body(res.to_string())
-}
-
-pub async fn get_auth_user(
-    ctx: web::Data<AppContext>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
-    let Some(user) = crate::model::users::db_get_single(&user_id, &ctx, std::time::Duration::from_secs(10)).await? else {
-        return Err(actix_web::error::ErrorExpectationFailed("no auth user"));
-    };
-    Ok(HttpResponse::Ok().json(user))
-}
-
-pub async fn get_allowed_methods(
-    ctx: web::Data<AppContext>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
-    let res = crate::model::users::db_get_allowed_transaction_list(
-        &user_id,
-        &ctx,
-        std::time::Duration::from_secs(10),
-    )
-    .await?;
-    Ok(HttpResponse::Ok().json(res))
-}
-
-pub async fn user_logout(
-    ctx: web::Data<AppContext>,
-    auth_data: crate::extractors::auth::AuthenticateData,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
-    let new_token = crate::model::users::UserLastAuthToken {
-        id: None,
-        user_id,
-        token_id: uuid::Uuid::new_v4(),
-        mod_timp: chrono::Utc::now().naive_utc(),
-    };
-    let app_path: &str = if ctx.general.is_in_dev {
-        "/"
-    } else {
-        &ctx.general.app_path
-    };
-
-    //save new token data
-    let _ = crate::model::users::db_persist_last_token_id(
-        &new_token,
-        &ctx,
-        std::time::Duration::from_secs(10),
-    )
-    .await?;
-
-    //send response
-    Ok(HttpResponse::Found()
-        .append_header((crate::Consts::AUTH_HEADER_NAME, ""))
-        .cookie(
-            actix_web::cookie::Cookie::build(crate::Consts::AUTH_COOKIE_NAME, "")
-                .path(app_path)
-                .http_only(true)
-                .secure(true)
-                .max_age(time::Duration::seconds(0))
-                .expires(time::OffsetDateTime::now_utc().checked_sub(time::Duration::days(365)))
-                .finish(),
-        )
-        .finish())
-}
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+
+use crate::{
+    extractors::auth::AuthClaims,
+    model::{
+        audit::{db_audit_record, AuthAudit, AuthEvent, AuthOutcome},
+        mail_outbox::MailKind,
+    },
+    AppContext,
+};
+
+pub async fn user_login(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+    data: web::Json<crate::model::users::LoginData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    //get user account from request
+    let Some(user) = crate::model::users::db_get_single(data.user_id.as_str(), &ctx, std::time::Duration::from_secs(10))
+        .await
+        .map_err(|e| actix_web::error::ErrorExpectationFailed(e))? else {
+        let entry = AuthAudit::new(
+            &req,
+            Some(&data.user_id),
+            AuthEvent::LinkRequest,
+            AuthOutcome::Failure,
+            Some("user not found".into()),
+        );
+        db_audit_record(entry, &ctx).await;
+        return Err(actix_web::error::ErrorUnauthorized("user not found"))
+    };
+
+    //get last token data
+    let last_token = crate::model::users::db_get_last_token_id(
+        &user.user_id,
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+
+    //check if login reuest is not too early
+    if let Some(token) = last_token {
+        let now = chrono::Utc::now();
+        let diff = now
+            .signed_duration_since(chrono::DateTime::<chrono::Utc>::from_utc(
+                token.mod_timp,
+                chrono::Utc,
+            ))
+            .num_minutes();
+        if diff <= 10 {
+            db_audit_record(
+                AuthAudit::new(
+                    &req,
+                    Some(&user.user_id),
+                    AuthEvent::LinkRequest,
+                    AuthOutcome::Failure,
+                    Some(format!(
+                        "requested too early, {} minutes since last token",
+                        diff
+                    )),
+                ),
+                &ctx,
+            )
+            .await;
+            return Err(actix_web::error::ErrorNotAcceptable(format!(
+                "wait-minutes:{}",
+                diff
+            )));
+        }
+    }
+
+    db_audit_record(
+        AuthAudit::new(
+            &req,
+            Some(&user.user_id),
+            AuthEvent::LinkRequest,
+            AuthOutcome::Success,
+            None,
+        ),
+        &ctx,
+    )
+    .await;
+
+    //prepare claims for new token
+    let iat = chrono::Utc::now();
+    let exp = iat
+        .checked_add_signed(chrono::Duration::minutes(10))
+        .unwrap_or(iat);
+
+    let claims = AuthClaims {
+        iss: ctx.general.app_domain.clone(),
+        sub: user.user_id.clone(),
+        jti: uuid::Uuid::new_v4(),
+        iat: iat.timestamp(),
+        exp: exp.timestamp(),
+        auth_time: None,
+        purpose: None,
+    };
+
+    //queue login link for the user, the mail outbox worker signs its token and sends it
+    let lang = mail_language(&req, &user, &ctx);
+    queue_login_link(&user, &claims.jti, &exp, &lang, MailKind::Login, &ctx).await?;
+
+    //save new token data
+    let _ = crate::model::users::db_persist_last_token_id(
+        &claims.into(),
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+
+    //return response
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// mail language: user preference, then the request's `Accept-Language`, then the default one
+fn mail_language(
+    req: &HttpRequest,
+    user: &crate::model::users::User,
+    ctx: &web::Data<AppContext>,
+) -> String {
+    let accept_language = req
+        .headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok());
+    ctx.mail_templates
+        .pick_language(user.language.as_deref(), accept_language)
+}
+
+/// queues the magic link (valid for 10 minutes, until `exp`) for the `token_id` token,
+/// signed by the mail outbox worker when sending
+async fn queue_login_link(
+    user: &crate::model::users::User,
+    token_id: &uuid::Uuid,
+    exp: &chrono::DateTime<chrono::Utc>,
+    lang: &str,
+    kind: MailKind,
+    ctx: &web::Data<AppContext>,
+) -> Result<(), actix_web::Error> {
+    let to_addrs = vec![lettre::message::Mailbox::new(
+        Some(format!("{} {}", user.first_name, user.last_name)),
+        user.email
+            .parse()
+            .map_err(actix_web::error::ErrorExpectationFailed)?,
+    )];
+    let link = format!(
+        "{}{}/auth?{}={}",
+        ctx.general.app_domain,
+        ctx.general.app_path,
+        crate::Consts::AUTH_COOKIE_NAME,
+        crate::mail::LINK_TOKEN_MARK
+    );
+    let name = format!("{} {}", user.first_name, user.last_name);
+    let expiry = exp.format("%Y-%m-%d %H:%M").to_string();
+    let mail = crate::mail::render_mail(
+        "login",
+        lang,
+        &[
+            ("name", name.as_str()),
+            ("link", link.as_str()),
+            ("expiry", expiry.as_str()),
+        ],
+        ctx,
+    )
+    .await?;
+
+    let _ = crate::model::mail_outbox::db_outbox_enqueue(
+        kind,
+        Some(&user.user_id),
+        &to_addrs,
+        &mail,
+        Some((*token_id, exp.naive_utc())),
+        ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+    Ok(())
+}
+
+/// sends a magic link for re-authentication, required by the sensitive methods;
+/// the link keeps the token id of the current session, so the session stays valid until it's used
+pub async fn user_stepup(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = crate::extractors::auth::AuthClaims::from(auth_data);
+    let Some(user) = crate::model::users::db_get_single(&claims.sub, &ctx, std::time::Duration::from_secs(10)).await? else {
+        return Err(actix_web::error::ErrorExpectationFailed("no auth user"));
+    };
+
+    //the link carries the session's token id, so it replaces the current session
+    let iat = chrono::Utc::now();
+    let exp = iat
+        .checked_add_signed(chrono::Duration::minutes(10))
+        .unwrap_or(iat);
+    let lang = mail_language(&req, &user, &ctx);
+    queue_login_link(&user, &claims.jti, &exp, &lang, MailKind::StepUp, &ctx).await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn authenticate(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, actix_web::Error> {
+    //get the mail link token from request query string, a session token is not accepted here
+    let Some(token) = crate::helper::get_req_query_params(&req)?
+        .get(crate::Consts::AUTH_COOKIE_NAME)
+        .map(ToOwned::to_owned)
+    else {
+        return Err(actix_web::error::ErrorUnauthorized(
+            "missing authentication token",
+        ));
+    };
+    let link = match AuthClaims::decode_token(&token, &ctx) {
+        Ok(v) if v.purpose.is_some() => Ok(v),
+        Ok(_) => Err(actix_web::error::ErrorUnauthorized("not a sign-in link")),
+        Err(e) => Err(e),
+    };
+    let link = match link {
+        Ok(v) => v,
+        Err(e) => {
+            db_audit_record(
+                AuthAudit::new(
+                    &req,
+                    None,
+                    AuthEvent::TokenRejected,
+                    AuthOutcome::Failure,
+                    Some(e.to_string()),
+                ),
+                &ctx,
+            )
+            .await;
+            return Err(e);
+        }
+    };
+    //create new long lived authentication token
+    let iat = chrono::Utc::now();
+    let exp = iat
+        .checked_add_signed(chrono::Duration::days(90))
+        .unwrap_or(iat);
+
+    let mut claims = link.clone();
+    claims.jti = uuid::Uuid::new_v4();
+    claims.iat = iat.timestamp();
+    claims.exp = exp.timestamp();
+    claims.purpose = None;
+    //only a magic link refreshes the authentication time
+    claims.auth_time = Some(iat.timestamp());
+
+    let jwt = claims.create_token(&ctx)?;
+    let app_path: &str = if ctx.general.is_in_dev {
+        "/"
+    } else {
+        &ctx.general.app_path
+    };
+    //save new token data in place of the link's one, so the link can't be used again
+    let user_id = claims.sub.clone();
+    let used = crate::model::users::db_use_link_token_id(
+        &link.jti,
+        &claims.into(),
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+    if used.is_none() {
+        db_audit_record(
+            AuthAudit::new(
+                &req,
+                Some(&user_id),
+                AuthEvent::TokenRejected,
+                AuthOutcome::Failure,
+                Some(format!("link used or replaced, token id {}", link.jti)),
+            ),
+            &ctx,
+        )
+        .await;
+        return Err(actix_web::error::ErrorForbidden("invalid token"));
+    }
+    //warn the user about a sign-in from an unknown device/ ip, before it becomes a known one
+    //the sign-in already succeeded, so a failed check only skips the mail
+    let (ip, user_agent) = crate::helper::get_req_client_info(&req);
+    let is_new_device = crate::model::audit::db_audit_is_new_device(
+        &user_id,
+        ip.as_deref(),
+        user_agent.as_deref(),
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await
+    .unwrap_or_else(|err| {
+        log::error!("New sign-in check for {} -> {}", user_id, err);
+        false
+    });
+    if is_new_device {
+        //the delivery is audited by the mail outbox worker, here only a failure to queue it
+        let res =
+            queue_new_device_mail(&req, &user_id, ip.as_deref(), user_agent.as_deref(), &ctx).await;
+        if let Err(err) = res {
+            log::error!("New sign-in mail for {} -> {}", user_id, err);
+            db_audit_record(
+                AuthAudit::new(
+                    &req,
+                    Some(&user_id),
+                    AuthEvent::NewDeviceMail,
+                    AuthOutcome::Failure,
+                    Some(err.to_string()),
+                ),
+                &ctx,
+            )
+            .await;
+        }
+    }
+    db_audit_record(
+        AuthAudit::new(
+            &req,
+            Some(&user_id),
+            AuthEvent::Authenticate,
+            AuthOutcome::Success,
+            None,
+        ),
+        &ctx,
+    )
+    .await;
+    //send response
+    Ok(HttpResponse::Found()
+        .append_header((crate::Consts::AUTH_HEADER_NAME, jwt.as_str()))
+        .cookie(
+            actix_web::cookie::Cookie::build(crate::Consts::AUTH_COOKIE_NAME, jwt)
+                .path(app_path)
+                .http_only(true)
+                .secure(true)
+                .expires(time::OffsetDateTime::now_utc().checked_add(time::Duration::days(90)))
+                .finish(),
+        )
+        //.append_header((actix_web::http::header::LOCATION, app_path))
+        .finish())
+}
+
+/// queues the "new sign-in" mail with a single use link that revokes all the user's tokens
+async fn queue_new_device_mail(
+    req: &HttpRequest,
+    user_id: &str,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    ctx: &web::Data<AppContext>,
+) -> Result<(), actix_web::Error> {
+    let Some(user) = crate::model::users::db_get_single(user_id, ctx, std::time::Duration::from_secs(10)).await? else {
+        return Err(actix_web::error::ErrorExpectationFailed("user not found"));
+    };
+    let link = crate::model::users::db_create_revoke_link(
+        user_id,
+        ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+
+    let to_addrs = vec![lettre::message::Mailbox::new(
+        Some(format!("{} {}", user.first_name, user.last_name)),
+        user.email
+            .parse()
+            .map_err(actix_web::error::ErrorExpectationFailed)?,
+    )];
+    let time = chrono::Utc::now().format("%Y-%m-%d %H:%M").to_string();
+    let link = format!(
+        "{}{}/auth/revoke/{}",
+        ctx.general.app_domain, ctx.general.app_path, link.id
+    );
+    let lang = mail_language(req, &user, ctx);
+    let name = format!("{} {}", user.first_name, user.last_name);
+    let mail = crate::mail::render_mail(
+        "new_device",
+        &lang,
+        &[
+            ("name", name.as_str()),
+            ("time", time.as_str()),
+            ("ip", ip.unwrap_or("-")),
+            ("user_agent", user_agent.unwrap_or("-")),
+            ("link", link.as_str()),
+        ],
+        ctx,
+    )
+    .await?;
+
+    let _ = crate::model::mail_outbox::db_outbox_enqueue(
+        MailKind::NewDevice,
+        Some(&user.user_id),
+        &to_addrs,
+        &mail,
+        None,
+        ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+    Ok(())
+}
+
+/// "this wasn't me" link target: a page whose button posts to `revoke_all`, so the mail link
+/// scanners and prefetchers opening the link don't revoke anything
+pub async fn revoke_confirm() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(actix_web::http::header::ContentType::html())
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
+        .body(
+            "<form method=\"post\">\
+            <span>Inchideti toate sesiunile? Va trebui sa va autentificati din nou.</span> \
+            <button type=\"submit\">Inchide toate sesiunile</button>\
+            </form>",
+        )
+}
+
+/// revokes all the tokens of the user owning the link
+pub async fn revoke_all(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+    param_raw: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let link_id = param_raw.into_inner();
+    let Some(link) = crate::model::users::db_use_revoke_link(&link_id, &ctx, std::time::Duration::from_secs(10)).await? else {
+        return Err(actix_web::error::ErrorNotFound("invalid or expired link"));
+    };
+
+    //a new random token id invalidates every token issued until now
+    let new_token = crate::model::users::UserLastAuthToken {
+        id: None,
+        user_id: link.user_id,
+        token_id: uuid::Uuid::new_v4(),
+        mod_timp: chrono::Utc::now().naive_utc(),
+    };
+    let _ = crate::model::users::db_persist_last_token_id(
+        &new_token,
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+    db_audit_record(
+        AuthAudit::new(
+            &req,
+            Some(&new_token.user_id),
+            AuthEvent::RevokeAll,
+            AuthOutcome::Success,
+            Some(format!("revoke link {}", link_id)),
+        ),
+        &ctx,
+    )
+    .await;
+
+    Ok(HttpResponse::Ok()
+        .content_type(actix_web::http::header::ContentType::html())
+        .body(
+            "<span>Toate sesiunile au fost inchise. Va rugam sa va autentificati din nou.</span>",
+        ))
+}
+
+pub async fn is_authenticated(req: actix_web::HttpRequest) -> HttpResponse {
+    let res = match crate::extractors::auth::AuthenticateData::from_request(
+        &req,
+        &mut actix_web::dev::Payload::None,
+    )
+    .await
+    {
+        Ok(_) => true,
+        Err(_) => false,
+    };
+    HttpResponse::Ok().body(res.to_string())
+}
+
+pub async fn get_auth_user(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let Some(user) = crate::model::users::db_get_single(&user_id, &ctx, std::time::Duration::from_secs(10)).await? else {
+        return Err(actix_web::error::ErrorExpectationFailed("no auth user"));
+    };
+    Ok(HttpResponse::Ok().json(user))
+}
+
+pub async fn get_allowed_methods(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let res = crate::model::users::db_get_allowed_transaction_list(
+        &user_id,
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn user_logout(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let new_token = crate::model::users::UserLastAuthToken {
+        id: None,
+        user_id,
+        token_id: uuid::Uuid::new_v4(),
+        mod_timp: chrono::Utc::now().naive_utc(),
+    };
+    let app_path: &str = if ctx.general.is_in_dev {
+        "/"
+    } else {
+        &ctx.general.app_path
+    };
+
+    //save new token data
+    let _ = crate::model::users::db_persist_last_token_id(
+        &new_token,
+        &ctx,
+        std::time::Duration::from_secs(10),
+    )
+    .await?;
+    db_audit_record(
+        AuthAudit::new(
+            &req,
+            Some(&new_token.user_id),
+            AuthEvent::Logout,
+            AuthOutcome::Success,
+            None,
+        ),
+        &ctx,
+    )
+    .await;
+
+    //send response
+    Ok(HttpResponse::Found()
+        .append_header((crate::Consts::AUTH_HEADER_NAME, ""))
+        .cookie(
+            actix_web::cookie::Cookie::build(crate::Consts::AUTH_COOKIE_NAME, "")
+                .path(app_path)
+                .http_only(true)
+                .secure(true)
+                .max_age(time::Duration::seconds(0))
+                .expires(time::OffsetDateTime::now_utc().checked_sub(time::Duration::days(365)))
+                .finish(),
+        )
+        .finish())
+}
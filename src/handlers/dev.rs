@@ -0,0 +1,32 @@
+use crate::AppContext;
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use std::path::Path;
+
+fn sink_dir(ctx: &AppContext) -> Result<&Path, actix_web::Error> {
+    ctx.mailer
+        .sink_dir()
+        .ok_or_else(|| actix_web::error::ErrorNotFound("mail transport is not the file sink"))
+}
+
+/// mails written by the file transport (`MAIL_TRANSPORT=file`), newest first
+pub async fn sink_mail_list(ctx: web::Data<AppContext>) -> Result<HttpResponse, actix_web::Error> {
+    let res = crate::mail::transport::sink_list(sink_dir(&ctx)?)
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// raw .eml content of a sink mail, to pick the login link from
+pub async fn sink_mail_get(
+    ctx: web::Data<AppContext>,
+    param_raw: web::Path<String>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(path) =
+        crate::mail::transport::sink_file_path(sink_dir(&ctx)?, &param_raw.into_inner())
+    else {
+        return Err(actix_web::error::ErrorNotFound("mail not found"));
+    };
+    let content = std::fs::read_to_string(path)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::plaintext())
+        .body(content))
+}
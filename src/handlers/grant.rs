@@ -0,0 +1,82 @@
+use crate::AppContext;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use std::time::Duration;
+
+/// mandatory query parameter for app_code is "q"
+pub async fn grant_list(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    let Some(ctx) = req.app_data() else {
+        return Err(actix_web::error::ErrorExpectationFailed("app context not found"));
+    };
+    let mod_de = crate::extractors::auth::AuthenticateData::from_request(
+        &req,
+        &mut actix_web::dev::Payload::None,
+    )
+    .await?
+    .1;
+    let query = crate::helper::get_req_query_params(&req)?;
+    let Some(app_code) = query.get("q") else {
+        return Err(actix_web::error::ErrorBadRequest("missing query parameter 'q'"));
+    };
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, Some(app_code), ctx).await?;
+
+    let res =
+        crate::model::grant::db_get_grants_by_app_code(app_code, ctx, Duration::from_secs(10))
+            .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn grant_single_upsert(
+    ctx: web::Data<AppContext>,
+    grant: web::Json<crate::model::grant::Grant>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, Some(&grant.app_code), &ctx).await?;
+
+    let Some(res) = crate::model::grant::db_grant_single_upsert(
+        &grant,
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await? else {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "method '{}' not found for app code '{}'",
+            grant.method_code, grant.app_code
+        )));
+    };
+    crate::notify::notify(
+        crate::model::notification::NotificationKind::AccessGranted,
+        &[],
+        std::slice::from_ref(&res.group_id),
+        "Access granted",
+        &format!(
+            "Group '{}' can now use method '{}' of app '{}'",
+            res.group_id, res.method_code, res.app_code
+        ),
+        &ctx,
+    )
+    .await;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn grant_delete_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let Some(grant) =
+        crate::model::grant::db_grant_get_by_id(&id, &ctx, Duration::from_secs(10)).await? else {
+        return Ok(HttpResponse::NoContent().body("element not found"));
+    };
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, Some(&grant.app_code), &ctx).await?;
+
+    let res =
+        crate::model::grant::db_grant_delete_by_id(&id, &ctx, Duration::from_secs(10)).await?;
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body("element not found")
+    })
+}
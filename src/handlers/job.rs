@@ -0,0 +1,199 @@
+use crate::{
+    extractors::multipart::MultipartFormData,
+    jobs::{ExportParams, ImportParams},
+    model::job::{JobFileKind, JobKind},
+    upload::UploadFormat,
+    AppContext,
+};
+use actix_web::{web, HttpResponse};
+use std::time::Duration;
+
+/// queues an import of an .xlsx, .csv or .txt file; same form fields as the direct uploads
+/// (the text ones for .csv/ .txt files). Answers 202 with the job, its `result` being the
+/// `UploadReport` once done
+pub async fn app_method_import_job(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+    payload: actix_multipart::Multipart,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let file_prefix = format!("u-{}", mod_de.sub);
+    let temp_dir = ctx.general.temp_dir.as_path();
+
+    let form_data = MultipartFormData::from_multipart(
+        temp_dir,
+        &file_prefix,
+        payload,
+        Some(1),
+        Some(&["xlsx", "csv", "txt"]),
+    )
+    .await
+    .map_err(actix_web::error::ErrorExpectationFailed)?;
+
+    let Some(file_path) = form_data.file_paths.first() else {
+        return Err(actix_web::error::ErrorBadRequest(
+            "no file with 'xlsx', 'csv' or 'txt' extension loaded",
+        ));
+    };
+    let extension = file_path
+        .path
+        .extension()
+        .and_then(|v| v.to_str())
+        .unwrap_or_default();
+    let format = if extension == "xlsx" {
+        UploadFormat::Xlsx {
+            sheet_name: form_data.fields.get("sheet_name").cloned(),
+        }
+    } else {
+        crate::handlers::app_method::text_format(&form_data.fields)?
+    };
+    let app_code = form_data.fields.get("app_code");
+    crate::handlers::app_owner::check_app_scope(&mod_de.sub, app_code.map(String::as_str), &ctx)
+        .await?;
+    let options = crate::handlers::app_method::import_options(&form_data.fields, app_code)?;
+    let params = serde_json::to_value(ImportParams { format, options })?;
+
+    // the uploaded file is saved with the job, so any server can run it
+    let id = uuid::Uuid::new_v4();
+    let file_name = crate::jobs::job_file_name(&id, extension);
+    let content = std::fs::read(&file_path.path)?;
+    let Some(res) = crate::model::job::db_job_insert(
+        &id,
+        JobKind::AppMethodImport,
+        &params,
+        Some((file_name.as_str(), content.as_slice())),
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(60),
+    )
+    .await?
+    else {
+        return Err(actix_web::error::ErrorExpectationFailed(
+            "could not queue job",
+        ));
+    };
+    Ok(HttpResponse::Accepted().json(res))
+}
+
+/// queues an export, body `{"format": "xlsx" | "csv", "app_code": optional}`;
+/// answers 202 with the job, the file is at `/jobs/{id}/result` once done
+pub async fn app_method_export_job(
+    ctx: web::Data<AppContext>,
+    params: web::Json<ExportParams>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    let params = serde_json::to_value(params.into_inner())?;
+    let Some(res) = crate::model::job::db_job_insert(
+        &uuid::Uuid::new_v4(),
+        JobKind::AppMethodExport,
+        &params,
+        None,
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?
+    else {
+        return Err(actix_web::error::ErrorExpectationFailed(
+            "could not queue job",
+        ));
+    };
+    Ok(HttpResponse::Accepted().json(res))
+}
+
+/// last 100 jobs of the current user
+pub async fn job_list(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let res =
+        crate::model::job::db_job_get_for_user(&user_id, &ctx, Duration::from_secs(10)).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// status and progress of a job of the current user
+pub async fn job_get_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let Some(res) =
+        crate::model::job::db_job_get_by_id(&id, &user_id, &ctx, Duration::from_secs(10)).await?
+    else {
+        return Err(actix_web::error::ErrorNotFound("job not found"));
+    };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// a queued job is cancelled at once, a running one at its next stage
+pub async fn job_cancel(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let Some(res) =
+        crate::model::job::db_job_cancel(&id, &user_id, &ctx, Duration::from_secs(10)).await?
+    else {
+        return Err(actix_web::error::ErrorConflict(
+            "job not found or already finished",
+        ));
+    };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// file made by a finished export job, read from the db so any server can answer
+pub async fn job_result(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let Some(job) =
+        crate::model::job::db_job_get_by_id(&id, &user_id, &ctx, Duration::from_secs(10)).await?
+    else {
+        return Err(actix_web::error::ErrorNotFound("job not found"));
+    };
+    if job.status != "done" {
+        return Err(actix_web::error::ErrorConflict(format!(
+            "job is {}",
+            job.status
+        )));
+    }
+    let (Some(result_file), Some(content)) = (
+        job.result_file.as_deref(),
+        crate::model::job::db_job_file_get(
+            &job.id,
+            JobFileKind::Result,
+            &ctx,
+            Duration::from_secs(60),
+        )
+        .await?,
+    ) else {
+        return Err(actix_web::error::ErrorNotFound("job has no result file"));
+    };
+    let extension = std::path::Path::new(result_file)
+        .extension()
+        .and_then(|v| v.to_str())
+        .unwrap_or_default();
+    let file_name = job
+        .result
+        .as_ref()
+        .and_then(|v| v.get("file_name"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("result")
+        .to_string();
+
+    Ok(HttpResponse::Ok()
+        .content_type(actix_files::file_extension_to_mime(extension))
+        .insert_header(actix_web::http::header::ContentDisposition {
+            disposition: actix_web::http::header::DispositionType::Attachment,
+            parameters: vec![actix_web::http::header::DispositionParam::Filename(
+                file_name,
+            )],
+        })
+        .body(content.content))
+}
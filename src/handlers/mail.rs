@@ -0,0 +1,137 @@
+use crate::AppContext;
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::time::Duration;
+
+/// optional query parameter for status is "status" ("pending", "sending", "sent" or "dead");
+/// the mails are listed without their message
+pub async fn outbox_list(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let query = crate::helper::get_req_query_params(&req)?;
+    let vals = crate::model::mail_outbox::db_outbox_get_filtered(
+        query.get("status").map(String::as_str),
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(vals))
+}
+
+/// puts a "dead" mail back in the queue; the sign-in link mails (login, step-up) are not resent,
+/// the user asks for a new link
+pub async fn outbox_resend(
+    ctx: web::Data<AppContext>,
+    param_raw: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let id = param_raw.into_inner();
+    let Some(res) = crate::model::mail_outbox::db_outbox_resend(&id, &ctx, Duration::from_secs(10)).await? else {
+        return Err(actix_web::error::ErrorNotFound("no dead mail with this id, or a sign-in link mail"));
+    };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// optional query parameter for the template code is "q"
+pub async fn template_list(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let query = crate::helper::get_req_query_params(&req)?;
+    let res = crate::model::mail_template::db_mail_template_get_all(
+        query.get("q").map(String::as_str),
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn template_single_upsert(
+    ctx: web::Data<AppContext>,
+    template: web::Json<crate::model::mail_template::MailTemplate>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    check_template(&template, &ctx)?;
+    let res = crate::model::mail_template::db_mail_template_single_upsert(
+        &template,
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn template_delete_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let res = crate::model::mail_template::db_mail_template_delete_by_id(
+        &id,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body("element not found")
+    })
+}
+
+/// renders a template (saved or not) with sample data, as it would be sent
+pub async fn template_preview(
+    ctx: web::Data<AppContext>,
+    template: web::Json<crate::model::mail_template::MailTemplate>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let params = check_template(&template, &ctx)?;
+    let res = ctx.mail_templates.render_content(
+        &template.lang,
+        &template.subject,
+        &template.html,
+        template.text_body.as_deref(),
+        params,
+    )?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// known template code, supported language and the placeholders the template needs (ex.: the
+/// `{{link}}` of the login mail) in the html and the plain text; returns the template's sample params
+fn check_template(
+    template: &crate::model::mail_template::MailTemplate,
+    ctx: &web::Data<AppContext>,
+) -> Result<&'static [(&'static str, &'static str)], actix_web::Error> {
+    let Some(params) = crate::mail::sample_params(&template.template_code) else {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "unknown template code '{}'",
+            template.template_code
+        )));
+    };
+    if !ctx.mail_templates.languages.contains(&template.lang) {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "unsupported language '{}'",
+            template.lang
+        )));
+    }
+    if template.subject.trim().is_empty() || template.html.trim().is_empty() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "subject and html are mandatory",
+        ));
+    }
+    let bodies = [Some(template.html.as_str()), template.text_body.as_deref()];
+    for text in bodies.into_iter().flatten() {
+        let missing = crate::mail::missing_params(&template.template_code, text);
+        if !missing.is_empty() {
+            return Err(actix_web::error::ErrorBadRequest(format!(
+                "missing placeholders: {}",
+                missing
+                    .iter()
+                    .map(|v| format!("{{{{{}}}}}", v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+    }
+    Ok(params)
+}
@@ -1,4 +1,13 @@
-pub mod app_method;
-pub mod auth;
-pub mod other;
-pub mod users;
+pub mod announcement;
+pub mod app_method;
+pub mod app_owner;
+pub mod audit;
+pub mod auth;
+pub mod dev;
+pub mod grant;
+pub mod job;
+pub mod mail;
+pub mod notification;
+pub mod other;
+pub mod users;
+pub mod webhook;
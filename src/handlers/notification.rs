@@ -0,0 +1,65 @@
+use crate::AppContext;
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::time::Duration;
+
+/// notifications of the authenticated user; optional query parameter "unread=true"
+pub async fn notification_list(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let query = crate::helper::get_req_query_params(&req)?;
+    let unread_only = query.get("unread").map(|v| v == "true").unwrap_or(false);
+    let res = crate::model::notification::db_notification_get_for_user(
+        &user_id,
+        unread_only,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn notification_mark_read(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = crate::extractors::auth::AuthClaims::from(auth_data).sub;
+    let res = crate::model::notification::db_notification_mark_read(
+        &id,
+        &user_id,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body("element not found")
+    })
+}
+
+/// Server-Sent Events stream pushing the new notifications of the authenticated user,
+/// until the session ends
+pub async fn notification_stream(
+    ctx: web::Data<AppContext>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let claims = crate::extractors::auth::AuthClaims::from(auth_data);
+    let (user_id, token_id) = (claims.sub.clone(), claims.jti);
+    let receiver = ctx.notifier.subscribe();
+    let stream = crate::notify::sse_stream(
+        claims.sub,
+        receiver,
+        move || crate::notify::is_session_active(user_id.clone(), token_id, ctx.clone()),
+        crate::notify::KEEP_ALIVE_INTERVAL,
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(actix_web::http::header::CacheControl(vec![
+            actix_web::http::header::CacheDirective::NoCache,
+        ]))
+        .streaming(stream))
+}
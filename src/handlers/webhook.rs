@@ -0,0 +1,96 @@
+use crate::{model::webhook::WebhookEvent, AppContext};
+use actix_web::{web, HttpRequest, HttpResponse};
+use std::time::Duration;
+
+pub async fn webhook_list(ctx: web::Data<AppContext>) -> Result<HttpResponse, actix_web::Error> {
+    let res = crate::model::webhook::db_webhook_get_all(&ctx, Duration::from_secs(10)).await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+/// the secret is mandatory for a new webhook, left out on update it keeps the stored one
+pub async fn webhook_single_upsert(
+    ctx: web::Data<AppContext>,
+    webhook: web::Json<crate::model::webhook::Webhook>,
+    auth_data: crate::extractors::auth::AuthenticateData,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mod_de = crate::extractors::auth::AuthClaims::from(auth_data);
+    check_webhook(&webhook)?;
+    let Some(res) = crate::model::webhook::db_webhook_single_upsert(
+        &webhook,
+        &mod_de.sub,
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?
+    else {
+        return Err(actix_web::error::ErrorExpectationFailed(
+            "could not save webhook",
+        ));
+    };
+    Ok(HttpResponse::Ok().json(res))
+}
+
+pub async fn webhook_delete_by_id(
+    ctx: web::Data<AppContext>,
+    id: web::Path<uuid::Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let res =
+        crate::model::webhook::db_webhook_delete_by_id(&id, &ctx, Duration::from_secs(10)).await?;
+    Ok(if res > 0 {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::NoContent().body("element not found")
+    })
+}
+
+/// optional query parameters are "webhook_id" and
+/// "status" ("pending", "sending", "delivered" or "dead")
+pub async fn delivery_list(
+    req: HttpRequest,
+    ctx: web::Data<AppContext>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let query = crate::helper::get_req_query_params(&req)?;
+    let webhook_id = query
+        .get("webhook_id")
+        .map(|v| v.parse::<uuid::Uuid>())
+        .transpose()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let res = crate::model::webhook::db_delivery_get_filtered(
+        webhook_id.as_ref(),
+        query.get("status").map(String::as_str),
+        &ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(res))
+}
+
+fn check_webhook(webhook: &crate::model::webhook::Webhook) -> Result<(), actix_web::Error> {
+    if !(webhook.url.starts_with("http://") || webhook.url.starts_with("https://")) {
+        return Err(actix_web::error::ErrorBadRequest(
+            "webhook url must start with http:// or https://",
+        ));
+    }
+    if webhook.event_types.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest(
+            "webhook needs at least one event type",
+        ));
+    }
+    if let Some(v) = webhook
+        .event_types
+        .iter()
+        .find(|v| WebhookEvent::parse(v).is_none())
+    {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "unknown event type '{}', expected one of: {}",
+            v,
+            WebhookEvent::ALL.map(|k| k.as_str()).join(", ")
+        )));
+    }
+    match (&webhook.id, webhook.secret.as_deref()) {
+        (_, Some("")) | (None, None) => Err(actix_web::error::ErrorBadRequest(
+            "webhook secret is mandatory",
+        )),
+        _ => Ok(()),
+    }
+}
@@ -1,55 +1,106 @@
-use data_encoding::BASE64URL_NOPAD;
-use std::{collections::HashMap, path::PathBuf, str::FromStr};
-
-#[derive(Debug)]
-pub struct TempFile {
-    pub path: PathBuf,
-}
-
-impl Drop for TempFile {
-    fn drop(&mut self) {
-        if self.path.exists() {
-            let _ = std::fs::remove_file(self.path.as_path());
-        }
-    }
-}
-
-pub fn get_env(key: &str) -> Result<String, String> {
-    dotenv::var(key).map_err(|e| format!("{}: {}", e, key))
-}
-
-pub fn get_exist_path(v: &str) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
-    let p = PathBuf::from_str(v)?;
-    if !p.try_exists()? {
-        return Err(format!("path doesn't exists: {}", v).into());
-    }
-    Ok(p)
-}
-
-pub fn get_req_query_params(
-    req: &actix_web::HttpRequest,
-) -> Result<HashMap<String, String>, actix_web::error::Error> {
-    let res = serde_urlencoded::from_str::<HashMap<String, String>>(req.query_string())?;
-    Ok(res)
-}
-
-pub fn from_req_base64_query_params(
-    req: &actix_web::HttpRequest,
-) -> Result<HashMap<String, String>, actix_web::error::Error> {
-    let raw = get_req_query_params(req)?;
-    let mut res: HashMap<String, String> = HashMap::new();
-    for (k, v) in raw {
-        let d = BASE64URL_NOPAD
-            .decode(v.as_bytes())
-            .map_err(|e| actix_web::error::ErrorBadRequest(e))?;
-        let s = String::from_utf8(d).map_err(|e| actix_web::error::ErrorExpectationFailed(e))?;
-        res.insert(k, s);
-    }
-    Ok(res)
-}
-
-pub fn base64_decode(v: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    let vec = BASE64URL_NOPAD.decode(v)?;
-    let res = String::from_utf8(vec)?;
-    Ok(res)
-}
+use data_encoding::BASE64URL_NOPAD;
+use std::{collections::HashMap, net::IpAddr, path::PathBuf, str::FromStr};
+
+#[derive(Debug)]
+pub struct TempFile {
+    pub path: PathBuf,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            let _ = std::fs::remove_file(self.path.as_path());
+        }
+    }
+}
+
+pub fn get_env(key: &str) -> Result<String, String> {
+    dotenv::var(key).map_err(|e| format!("{}: {}", e, key))
+}
+
+pub fn get_exist_path(v: &str) -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+    let p = PathBuf::from_str(v)?;
+    if !p.try_exists()? {
+        return Err(format!("path doesn't exists: {}", v).into());
+    }
+    Ok(p)
+}
+
+pub fn get_req_query_params(
+    req: &actix_web::HttpRequest,
+) -> Result<HashMap<String, String>, actix_web::error::Error> {
+    let res = serde_urlencoded::from_str::<HashMap<String, String>>(req.query_string())?;
+    Ok(res)
+}
+
+pub fn from_req_base64_query_params(
+    req: &actix_web::HttpRequest,
+) -> Result<HashMap<String, String>, actix_web::error::Error> {
+    let raw = get_req_query_params(req)?;
+    let mut res: HashMap<String, String> = HashMap::new();
+    for (k, v) in raw {
+        let d = BASE64URL_NOPAD
+            .decode(v.as_bytes())
+            .map_err(|e| actix_web::error::ErrorBadRequest(e))?;
+        let s = String::from_utf8(d).map_err(|e| actix_web::error::ErrorExpectationFailed(e))?;
+        res.insert(k, s);
+    }
+    Ok(res)
+}
+
+pub fn base64_decode(v: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let vec = BASE64URL_NOPAD.decode(v)?;
+    let res = String::from_utf8(vec)?;
+    Ok(res)
+}
+
+/// client ip and user agent of the request. The ip is the peer address, unless the peer is one of
+/// the `GEN_TRUSTED_PROXIES`: then it's the last `X-Forwarded-For` address that is not a trusted
+/// proxy, the start of that header being whatever the client sent
+pub fn get_req_client_info(req: &actix_web::HttpRequest) -> (Option<String>, Option<String>) {
+    let trusted: &[IpAddr] = req
+        .app_data::<actix_web::web::Data<crate::AppContext>>()
+        .map(|v| v.general.trusted_proxies.as_slice())
+        .unwrap_or_default();
+    let ip = req.peer_addr().map(|peer| {
+        let mut ip = peer.ip();
+        if trusted.contains(&ip) {
+            let forwarded = req
+                .headers()
+                .get_all("x-forwarded-for")
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(','))
+                .collect::<Vec<_>>();
+            for v in forwarded.into_iter().rev() {
+                let Ok(v) = v.trim().parse::<IpAddr>() else {
+                    break;
+                };
+                ip = v;
+                if !trusted.contains(&v) {
+                    break;
+                }
+            }
+        }
+        ip.to_string()
+    });
+    let user_agent = req
+        .headers()
+        .get(actix_web::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(ToOwned::to_owned);
+    (ip, user_agent)
+}
+
+/// strong ETag values of an `If-Match` header, `["*"]` for any; weak ETags never match, so they are left out
+pub fn if_match_tags(if_match: Option<&actix_web::http::header::IfMatch>) -> Option<Vec<String>> {
+    match if_match? {
+        actix_web::http::header::IfMatch::Any => Some(vec!["*".to_string()]),
+        actix_web::http::header::IfMatch::Items(items) => Some(
+            items
+                .iter()
+                .filter(|v| !v.weak)
+                .map(|v| v.tag().to_string())
+                .collect(),
+        ),
+    }
+}
@@ -0,0 +1,376 @@
+use actix_web::web;
+use futures_util::future::Either;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{
+    model::job::{Job, JobFileKind, JobKind},
+    upload::{ImportOptions, UploadFormat},
+    AppContext,
+};
+
+/// jobs run at the same time by each server
+pub const WORKERS: usize = 2;
+/// how often an idle worker looks for queued jobs
+pub const POLL_INTERVAL: Duration = Duration::from_secs(3);
+/// how often a running job tells it's alive, even while stuck in a long db statement
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// a running job without heartbeat for this long lost its server, so it's claimed again
+pub const STALE_SECS: i32 = 120;
+/// claims of a job before it's failed, so a job stopping its server can't loop forever
+pub const MAX_ATTEMPTS: i32 = 3;
+/// db timeout of the job statements, instead of the request ones
+pub const JOB_TIMEOUT: Duration = Duration::from_secs(3600);
+/// how often the stale jobs are ended and the old ones removed
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+/// finished jobs, with their files, are removed after these days
+pub const KEEP_DAYS: i32 = 7;
+/// job files are named "j-{job id}.{extension}"; they are kept in the db, the server
+/// running a job writes them to its temp directory only while it needs them
+pub const FILE_PREFIX: &str = "j-";
+
+/// params of an app method import job, read from the upload form fields
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportParams {
+    pub format: UploadFormat,
+    pub options: ImportOptions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Xlsx,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Xlsx => "xlsx",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// params of an app method export job; all app codes when `app_code` is missing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportParams {
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub app_code: Option<String>,
+}
+
+impl ExportParams {
+    /// name the result is downloaded with, the same as for the direct downloads
+    pub fn file_name(&self) -> String {
+        match &self.app_code {
+            Some(v) => format!("app_transactions_for_{}.{}", v, self.format.extension()),
+            None => format!("app_transactions_all.{}", self.format.extension()),
+        }
+    }
+}
+
+/// name of a job file
+pub fn job_file_name(id: &uuid::Uuid, extension: &str) -> String {
+    format!("{}{}.{}", FILE_PREFIX, id, extension)
+}
+
+/// stage reached by a running job, kept in the db with each heartbeat; `attempts` tells this
+/// run from a later one, claimed after this server lost the heartbeat
+pub struct Progress<'a> {
+    id: uuid::Uuid,
+    attempts: i32,
+    ctx: &'a web::Data<AppContext>,
+    state: Mutex<(&'static str, i32)>,
+    cancelled: AtomicBool,
+}
+
+impl<'a> Progress<'a> {
+    fn new(id: uuid::Uuid, attempts: i32, ctx: &'a web::Data<AppContext>) -> Self {
+        Self {
+            id,
+            attempts,
+            ctx,
+            state: Mutex::new(("starting", 0)),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// records the stage reached, `progress` being from 0 to 100;
+    /// fails once the job is cancelled, so the job stops at its next stage
+    pub async fn set(&self, stage: &'static str, progress: i32) -> Result<(), actix_web::Error> {
+        if let Ok(mut state) = self.state.lock() {
+            *state = (stage, progress);
+        }
+        self.beat().await?;
+        if self.is_cancelled() {
+            return Err(actix_web::error::ErrorGone("job cancelled"));
+        }
+        Ok(())
+    }
+
+    /// true when the job was cancelled, is not running anymore or was claimed again
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    async fn beat(&self) -> Result<(), actix_web::Error> {
+        let (stage, progress) = self.state.lock().map(|v| *v).unwrap_or(("", 0));
+        let job = crate::model::job::db_job_heartbeat(
+            &self.id,
+            self.attempts,
+            stage,
+            progress,
+            self.ctx,
+            Duration::from_secs(10),
+        )
+        .await?;
+        if job.map(|v| v.cancel_requested).unwrap_or(true) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+/// what a finished job leaves: the result shown with the job and an optional file to download,
+/// its name and content
+struct JobOutput {
+    result: serde_json::Value,
+    result_file: Option<(String, Vec<u8>)>,
+}
+
+/// starts the workers and the cleanup; the jobs and their files are in the db, so the ones
+/// queued or running when a server stopped are picked up by any other one
+pub async fn run_workers(ctx: web::Data<AppContext>) {
+    for worker in 0..WORKERS {
+        actix_web::rt::spawn(run_worker(ctx.clone(), worker));
+    }
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = cleanup(&ctx).await {
+            log::error!("Job cleanup -> {}", err);
+        }
+    }
+}
+
+async fn run_worker(ctx: web::Data<AppContext>, worker: usize) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        loop {
+            match crate::model::job::db_job_claim(
+                STALE_SECS,
+                MAX_ATTEMPTS,
+                &ctx,
+                Duration::from_secs(10),
+            )
+            .await
+            {
+                Ok(Some(job)) => run_job(job, &ctx).await,
+                Ok(None) => break,
+                Err(err) => {
+                    log::error!("Job worker {} -> {}", worker, err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// ends the stale jobs that won't be claimed again and removes the old ones
+async fn cleanup(ctx: &web::Data<AppContext>) -> Result<(), actix_web::Error> {
+    let stale = crate::model::job::db_job_fail_stale(
+        STALE_SECS,
+        MAX_ATTEMPTS,
+        ctx,
+        Duration::from_secs(10),
+    )
+    .await?;
+    for job in stale.iter() {
+        log::error!(
+            "Job {} -> stopped before finishing, now {}",
+            job.id,
+            job.status
+        );
+    }
+    crate::model::job::db_job_delete_finished(KEEP_DAYS, ctx, Duration::from_secs(10)).await?;
+    Ok(())
+}
+
+/// runs a claimed job, beating its heartbeat meanwhile, and records how it ended
+async fn run_job(job: Job, ctx: &web::Data<AppContext>) {
+    let progress = Progress::new(job.id, job.attempts, ctx);
+    let work = Box::pin(execute(&job, &progress, ctx));
+    let heartbeat = Box::pin(async {
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            if let Err(err) = progress.beat().await {
+                log::error!("Job {} heartbeat -> {}", job.id, err);
+            }
+        }
+    });
+    let res = match futures_util::future::select(work, heartbeat).await {
+        Either::Left((res, _)) => res,
+        Either::Right((_, work)) => work.await,
+    };
+
+    let (status, result, result_file, error) = match res {
+        Ok(v) => ("done", Some(v.result), v.result_file, None),
+        Err(_) if progress.is_cancelled() => ("cancelled", None, None, None),
+        Err(err) => {
+            log::error!("Job {} -> {}", job.id, err);
+            ("failed", None, None, Some(err.to_string()))
+        }
+    };
+    match crate::model::job::db_job_finish(
+        &job,
+        status,
+        result.as_ref(),
+        result_file
+            .as_ref()
+            .map(|(name, content)| (name.as_str(), content.as_slice())),
+        error.as_deref(),
+        ctx,
+        JOB_TIMEOUT,
+    )
+    .await
+    {
+        Ok(Some(_)) => {}
+        // claimed again after this run lost its heartbeat, the later run owns the job and its
+        // outcome; this one counts as cancelled
+        Ok(None) => log::info!(
+            "Job {} attempt {} -> claimed again, outcome dropped",
+            job.id,
+            job.attempts
+        ),
+        Err(err) => log::error!("Job {} finish -> {}", job.id, err),
+    }
+}
+
+async fn execute(
+    job: &Job,
+    progress: &Progress<'_>,
+    ctx: &web::Data<AppContext>,
+) -> Result<JobOutput, actix_web::Error> {
+    match JobKind::parse(&job.kind) {
+        Some(JobKind::AppMethodImport) => {
+            let params: ImportParams = serde_json::from_value(job.params.clone())?;
+            let (Some(file_name), Some(input)) = (
+                job.input_file.as_deref(),
+                crate::model::job::db_job_file_get(&job.id, JobFileKind::Input, ctx, JOB_TIMEOUT)
+                    .await?,
+            ) else {
+                return Err(actix_web::error::ErrorExpectationFailed(
+                    "import job without input file",
+                ));
+            };
+            // the import reads a file, so the input is written to this server's temp dir meanwhile
+            let input_file = crate::helper::TempFile {
+                path: ctx.general.temp_dir.join(file_name),
+            };
+            std::fs::write(&input_file.path, input.content)?;
+            let report = crate::upload::import_file(
+                &input_file.path,
+                &params.format,
+                &params.options,
+                &job.mod_de,
+                ctx,
+                JOB_TIMEOUT,
+                Some(progress),
+            )
+            .await?;
+            Ok(JobOutput {
+                result: serde_json::to_value(report)?,
+                result_file: None,
+            })
+        }
+        Some(JobKind::AppMethodExport) => {
+            let params: ExportParams = serde_json::from_value(job.params.clone())?;
+            progress.set("exporting", 10).await?;
+            let app_zone = params
+                .app_code
+                .as_ref()
+                .map(|v| crate::model::app_method::AppCode {
+                    app_code: v.to_owned(),
+                });
+            let path = match (params.format, app_zone) {
+                (ExportFormat::Xlsx, Some(app_zone)) => {
+                    crate::model::app_method::db_methods_by_app_code_down_xlsx(
+                        &app_zone,
+                        &job.mod_de,
+                        ctx,
+                        JOB_TIMEOUT,
+                    )
+                    .await?
+                }
+                (ExportFormat::Xlsx, None) => {
+                    crate::model::app_method::db_methods_all_down_xlsx(
+                        &job.mod_de,
+                        ctx,
+                        JOB_TIMEOUT,
+                    )
+                    .await?
+                }
+                (ExportFormat::Csv, Some(app_zone)) => {
+                    crate::model::app_method::db_methods_by_app_code_down_csv(
+                        &app_zone,
+                        &job.mod_de,
+                        ctx,
+                        JOB_TIMEOUT,
+                    )
+                    .await?
+                }
+                (ExportFormat::Csv, None) => {
+                    crate::model::app_method::db_methods_all_down_csv(&job.mod_de, ctx, JOB_TIMEOUT)
+                        .await?
+                }
+            };
+            let path = crate::helper::TempFile { path };
+            let content = std::fs::read(&path.path)?;
+            Ok(JobOutput {
+                result: serde_json::json!({ "file_name": params.file_name() }),
+                result_file: Some((job_file_name(&job.id, params.format.extension()), content)),
+            })
+        }
+        None => Err(actix_web::error::ErrorExpectationFailed(format!(
+            "unknown job kind '{}'",
+            job.kind
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn job_params_from_json() {
+        let params: super::ImportParams = serde_json::from_value(serde_json::json!({
+            "format": { "text": { "delimiter": 59, "quote": 34, "quote_escape": null } },
+            "options": { "app_code": "portal", "dry_run": false, "error_mode": "skip_bad_rows", "sync": false }
+        }))
+        .unwrap();
+        assert_eq!(
+            crate::upload::UploadFormat::Text {
+                delimiter: b';',
+                quote: Some(b'"'),
+                quote_escape: None,
+                encoding: None,
+            },
+            params.format
+        );
+        assert_eq!(
+            crate::upload::ErrorMode::SkipBadRows,
+            params.options.error_mode
+        );
+
+        let params: super::ExportParams =
+            serde_json::from_value(serde_json::json!({ "format": "xlsx" })).unwrap();
+        assert_eq!("app_transactions_all.xlsx", params.file_name());
+    }
+}
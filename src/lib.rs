@@ -1,327 +1,769 @@
-pub mod extractors;
-pub mod handlers;
-pub mod helper;
-pub mod middleware;
-pub mod model;
-
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-
-pub struct Consts;
-
-impl Consts {
-    pub const AUTH_COOKIE_NAME: &str = "atk";
-    pub const AUTH_HEADER_NAME: &str = "X-Auth-Token";
-    pub const EMAIL_REGEX_PATT: &str =
-        r"^([a-z0-9_+]([a-z0-9_+.]*[a-z0-9_+])?)@([a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,6})";
-    pub const DB_APP_CODE: &str = "portal";
-    pub const TXT_FILE_COLUMN_DELIM: &str = "^[,;\t|/]{1}$";
-    pub const TXT_FILE_COLUMN_QUOTE: &str = r#"^["'|\\/]{1}$"#;
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
-pub struct AuthQueryParam {
-    pub atk: String, //field name must match AUTH_COOKIE_NAME constant(see above)
-}
-
-pub struct GeneralSettings {
-    pub is_in_dev: bool,
-    pub app_domain: String,
-    pub app_path: String,
-    pub sql_dir: PathBuf,
-    pub static_files_dir: PathBuf,
-    pub temp_dir: PathBuf,
-}
-
-impl GeneralSettings {
-    pub fn get_sql(&self, file_name: &str) -> Result<String, actix_web::Error> {
-        let file_path = self.sql_dir.join(file_name);
-        let res = std::fs::read_to_string(file_path)?;
-        Ok(res)
-    }
-}
-
-pub struct AppContext {
-    pub general: GeneralSettings,
-    pub rsa_keys: utils::rsakeys::RsaKeys,
-    pub pgsql_pool: dbpool::pgsql::Pool,
-    pub mailer: utils::mailer::Mailer,
-}
-
-pub fn init_logger() -> Result<flexi_logger::LoggerHandle, Box<dyn std::error::Error + Send + Sync>>
-{
-    //init logger
-    let file_specs = flexi_logger::FileSpec::default()
-        .directory(&crate::helper::get_env("GEN_TEMP_DIRECTORY")?)
-        .basename("log")
-        .suffix("log");
-
-    let logger = flexi_logger::Logger::try_with_str("info")?
-        .log_to_file(file_specs)
-        .duplicate_to_stderr(flexi_logger::Duplicate::Warn)
-        .write_mode(flexi_logger::WriteMode::Async)
-        .format_for_files(flexi_logger::opt_format)
-        .rotate(
-            flexi_logger::Criterion::Age(flexi_logger::Age::Day),
-            flexi_logger::Naming::Timestamps,
-            flexi_logger::Cleanup::KeepLogFiles(7),
-        )
-        .start()?;
-    Ok(logger)
-}
-
-pub fn init_app_data(
-) -> Result<actix_web::web::Data<AppContext>, Box<dyn std::error::Error + Send + Sync>> {
-    // init paths constants
-    let is_in_dev: bool = crate::helper::get_env("GEN_UNDER_DEVELOPMENT")?.parse()?;
-    let app_domain = crate::helper::get_env("GEN_APPLICATION_DOMAIN")?;
-    let app_path = crate::helper::get_env("GEN_APPLICATION_PATH")?;
-    let sql_resource_dir = crate::helper::get_env("GEN_SQL_RESOURCE_DIR")?;
-    let static_files_dir = crate::helper::get_env("GEN_STATIC_FILES_DIR")?;
-    let temp_dir = crate::helper::get_env("GEN_TEMP_DIRECTORY")?;
-    let paths = GeneralSettings {
-        is_in_dev,
-        app_domain,
-        app_path,
-        sql_dir: crate::helper::get_exist_path(sql_resource_dir.as_str())?,
-        static_files_dir: crate::helper::get_exist_path(static_files_dir.as_str())?,
-        temp_dir: crate::helper::get_exist_path(temp_dir.as_str())?,
-    };
-
-    // init RSA KEYS
-    let rsa_pass = crate::helper::get_env("RSA_PASS")?;
-    let rsa_priv_path = crate::helper::get_env("RSA_PRIV_KEY_PATH")?;
-    let rsa_publ_path = crate::helper::get_env("RSA_PUB_KEY_PATH")?;
-    let rsa_keys = utils::rsakeys::RsaKeys::init(&rsa_pass, &rsa_priv_path, &rsa_publ_path)?;
-
-    // init PGSQL db pool
-    let pgsql_conn_string = crate::helper::get_env("PGSQL_CONN_STRING")?;
-    let pgsql_max_conn = crate::helper::get_env("PGSQL_POOL_MAX_CONN")?.parse()?;
-    let pgsql_batch_size = crate::helper::get_env("PGSQL_BATCH_INSERTS")?.parse()?;
-    let pgsql_pool =
-        dbpool::pgsql::Pool::init(pgsql_conn_string, None, pgsql_max_conn, pgsql_batch_size)?;
-
-    // init mail client
-    let mail_config = utils::mailer::Config {
-        from_addrs: lettre::message::Mailbox::new(
-            Some(crate::helper::get_env("MAIL_FROM_NAME")?),
-            crate::helper::get_env("MAIL_FROM_ADRS")?.parse::<lettre::Address>()?,
-        ),
-        reply_to: lettre::message::Mailbox::new(
-            Some(crate::helper::get_env("MAIL_FROM_NAME")?),
-            crate::helper::get_env("MAIL_REPLY_TO")?.parse::<lettre::Address>()?,
-        ),
-        server: crate::helper::get_env("MAIL_SMTP_SERVER")?,
-        port: crate::helper::get_env("MAIL_SMTP_PORT")?.parse()?,
-        user_name: crate::helper::get_env("MAIL_SMTP_USER")?,
-        password: crate::helper::get_env("MAIL_SMTP_PASS")?,
-        template_dir_path: crate::helper::get_env("MAIL_TEMPLATE_DIR")?,
-        template_name_format: crate::helper::get_env("MAIL_TEMPLATE_NAME_FORMAT")?,
-        languages: crate::helper::get_env("MAIL_LANGS")?
-            .split(',')
-            .filter(|v| !v.is_empty())
-            .map(ToString::to_string)
-            .collect(),
-        default_language: crate::helper::get_env("MAIL_LANG_DEFAULT")?.to_lowercase(),
-    };
-    let mailer = utils::mailer::Mailer::init(mail_config);
-
-    Ok(actix_web::web::Data::new(AppContext {
-        general: paths,
-        rsa_keys,
-        pgsql_pool,
-        mailer,
-    }))
-}
-
-fn config_public(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.service(
-        actix_web::web::resource("/static/{filename:.*}")
-            .route(actix_web::web::get().to(crate::handlers::other::static_files)),
-    )
-    .service(
-        actix_web::web::resource("/rsa/keys")
-            .route(actix_web::web::get().to(crate::handlers::other::rsa_public)),
-    );
-}
-
-fn config_auth(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.service(
-        actix_web::web::resource("/login")
-            .route(actix_web::web::post().to(crate::handlers::auth::user_login)),
-    )
-    .service(
-        actix_web::web::resource("/auth")
-            .wrap(crate::middleware::auth::AuthenticateFactory)
-            .route(actix_web::web::get().to(crate::handlers::auth::authenticate)),
-    )
-    .service(
-        actix_web::web::resource("/auth/isauth")
-            .route(actix_web::web::get().to(crate::handlers::auth::is_authenticated)),
-    )
-    .service(
-        actix_web::web::resource("/auth/user")
-            .wrap(crate::middleware::auth::AuthenticateFactory)
-            .route(actix_web::web::get().to(crate::handlers::auth::get_auth_user)),
-    )
-    .service(
-        actix_web::web::resource("/auth/methods")
-            .wrap(crate::middleware::auth::AuthenticateFactory)
-            .route(actix_web::web::get().to(crate::handlers::auth::get_allowed_methods)),
-    )
-    .service(
-        actix_web::web::resource("/logout")
-            .wrap(crate::middleware::auth::AuthenticateFactory)
-            .route(actix_web::web::get().to(crate::handlers::auth::user_logout)),
-    );
-}
-
-fn config_users(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.service(
-        actix_web::web::scope("/users")
-            .wrap(crate::middleware::auth::AuthenticateFactory)
-            .service(
-                actix_web::web::resource("/get/{user_id}")
-                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                        "portal",
-                        "user_single_get",
-                    ))
-                    .route(actix_web::web::get().to(crate::handlers::users::user_get_single)),
-            )
-            .service(
-                actix_web::web::resource("/list")
-                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                        "portal",
-                        "user_all_list",
-                    ))
-                    .route(
-                        actix_web::web::get()
-                            .to(|| async { actix_web::HttpResponse::Ok().body("user list") }),
-                    ),
-            ),
-    );
-}
-
-fn config_app_method(cfg: &mut actix_web::web::ServiceConfig) {
-    cfg.service(
-        actix_web::web::scope("/app_methods")
-            .wrap(crate::middleware::auth::AuthenticateFactory)
-            .service(
-                actix_web::web::resource("")
-                    .route(
-                        actix_web::web::get()
-                            .to(crate::handlers::app_method::app_method_list)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_list_all",
-                            )),
-                    )
-                    .route(
-                        actix_web::web::post()
-                            .to(crate::handlers::app_method::app_method_single_upsert)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_upsert_single",
-                            )),
-                    ),
-            )
-            .service(
-                actix_web::web::resource("/app_codes")
-                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                        "portal",
-                        "app_method_app_codes",
-                    ))
-                    .route(
-                        actix_web::web::get()
-                            .to(crate::handlers::app_method::app_method_get_app_code_list),
-                    ),
-            )
-            .service(
-                actix_web::web::resource("/xlsx")
-                    .route(
-                        actix_web::web::get()
-                            .to(crate::handlers::app_method::app_method_down_xlsx)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_list_all",
-                            )),
-                    )
-                    .route(
-                        actix_web::web::post()
-                            .to(crate::handlers::app_method::app_method_up_xlsx)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_upsert_all",
-                            )),
-                    ),
-            )
-            .service(
-                actix_web::web::resource("/csv")
-                    .route(
-                        actix_web::web::get()
-                            .to(crate::handlers::app_method::app_method_down_csv)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_list_all",
-                            )),
-                    )
-                    .route(
-                        actix_web::web::post()
-                            .to(crate::handlers::app_method::app_method_up_txt)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_upsert_all",
-                            )),
-                    ),
-            )
-            .service(
-                actix_web::web::resource("/{id}")
-                    .route(
-                        actix_web::web::get()
-                            .to(crate::handlers::app_method::app_method_get_single_by_id)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_get_single_by_id",
-                            )),
-                    )
-                    .route(
-                        actix_web::web::delete()
-                            .to(crate::handlers::app_method::app_method_delete_by_id)
-                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
-                                "portal",
-                                "app_method_del_single_by_id",
-                            )),
-                    ),
-            ),
-    );
-}
-
-pub fn init_app_service(
-    app_data: actix_web::web::Data<AppContext>,
-) -> actix_web::App<
-    impl actix_web::dev::ServiceFactory<
-        actix_web::dev::ServiceRequest,
-        Config = (),
-        Response = actix_web::dev::ServiceResponse,
-        Error = actix_web::Error,
-        InitError = (),
-    >,
-> {
-    let app_path = app_data.general.app_path.clone();
-
-    actix_web::App::new()
-        .app_data(app_data)
-        .wrap(crate::middleware::logger::LoggerFactory)
-        .wrap(actix_web::middleware::NormalizePath::trim())
-        .service(actix_web::web::scope(&app_path).configure(|cfg| {
-            config_public(cfg);
-            config_auth(cfg);
-            config_users(cfg);
-            config_app_method(cfg);
-        }))
-        .route(
-            "/",
-            actix_web::web::get().to(|| async { "Hello from the Server" }),
-        )
-        .default_service(actix_web::web::route().method(actix_web::http::Method::GET))
-}
+pub mod export;
+pub mod extractors;
+pub mod handlers;
+pub mod helper;
+pub mod jobs;
+pub mod mail;
+pub mod middleware;
+pub mod model;
+pub mod notify;
+pub mod upload;
+pub mod webhook;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub struct Consts;
+
+impl Consts {
+    pub const AUTH_COOKIE_NAME: &str = "atk";
+    pub const AUTH_HEADER_NAME: &str = "X-Auth-Token";
+    pub const EMAIL_REGEX_PATT: &str =
+        r"^([a-z0-9_+]([a-z0-9_+.]*[a-z0-9_+])?)@([a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,6})";
+    pub const DB_APP_CODE: &str = "portal";
+    pub const TXT_FILE_COLUMN_DELIM: &str = "^[,;\t|/]{1}$";
+    pub const TXT_FILE_COLUMN_QUOTE: &str = r#"^["'|\\/]{1}$"#;
+    pub const JSON_UPLOAD_MAX_BYTES: usize = 32 * 1024 * 1024;
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuthQueryParam {
+    pub atk: String, //field name must match AUTH_COOKIE_NAME constant(see above)
+}
+
+pub struct GeneralSettings {
+    pub is_in_dev: bool,
+    pub app_domain: String,
+    pub app_path: String,
+    pub sql_dir: PathBuf,
+    pub static_files_dir: PathBuf,
+    pub temp_dir: PathBuf,
+    pub auth_stepup_minutes: i64, // max age of the last authentication for sensitive methods
+    pub trusted_proxies: Vec<std::net::IpAddr>, // peers whose X-Forwarded-For header is read
+}
+
+impl GeneralSettings {
+    pub fn get_sql(&self, file_name: &str) -> Result<String, actix_web::Error> {
+        let file_path = self.sql_dir.join(file_name);
+        let res = std::fs::read_to_string(file_path)?;
+        Ok(res)
+    }
+}
+
+pub struct AppContext {
+    pub general: GeneralSettings,
+    pub rsa_keys: utils::rsakeys::RsaKeys,
+    pub pgsql_pool: dbpool::pgsql::Pool,
+    pub mailer: Box<dyn crate::mail::transport::Mailer>,
+    pub mail_templates: crate::mail::MailTemplates,
+    pub notifier: crate::notify::Notifier,
+}
+
+pub fn init_logger() -> Result<flexi_logger::LoggerHandle, Box<dyn std::error::Error + Send + Sync>>
+{
+    //init logger
+    let file_specs = flexi_logger::FileSpec::default()
+        .directory(&crate::helper::get_env("GEN_TEMP_DIRECTORY")?)
+        .basename("log")
+        .suffix("log");
+
+    let logger = flexi_logger::Logger::try_with_str("info")?
+        .log_to_file(file_specs)
+        .duplicate_to_stderr(flexi_logger::Duplicate::Warn)
+        .write_mode(flexi_logger::WriteMode::Async)
+        .format_for_files(flexi_logger::opt_format)
+        .rotate(
+            flexi_logger::Criterion::Age(flexi_logger::Age::Day),
+            flexi_logger::Naming::Timestamps,
+            flexi_logger::Cleanup::KeepLogFiles(7),
+        )
+        .start()?;
+    Ok(logger)
+}
+
+pub fn init_app_data(
+) -> Result<actix_web::web::Data<AppContext>, Box<dyn std::error::Error + Send + Sync>> {
+    init_app_data_with_mailer(None)
+}
+
+/// same as `init_app_data`, with the given mail transport instead of the one picked by
+/// `MAIL_TRANSPORT` (ex.: a `RecordingMailer` in tests)
+pub fn init_app_data_with_mailer(
+    mailer: Option<Box<dyn crate::mail::transport::Mailer>>,
+) -> Result<actix_web::web::Data<AppContext>, Box<dyn std::error::Error + Send + Sync>> {
+    // init paths constants
+    let is_in_dev: bool = crate::helper::get_env("GEN_UNDER_DEVELOPMENT")?.parse()?;
+    let app_domain = crate::helper::get_env("GEN_APPLICATION_DOMAIN")?;
+    let app_path = crate::helper::get_env("GEN_APPLICATION_PATH")?;
+    let sql_resource_dir = crate::helper::get_env("GEN_SQL_RESOURCE_DIR")?;
+    let static_files_dir = crate::helper::get_env("GEN_STATIC_FILES_DIR")?;
+    let temp_dir = crate::helper::get_env("GEN_TEMP_DIRECTORY")?;
+    let auth_stepup_minutes: i64 = match crate::helper::get_env("GEN_AUTH_STEPUP_MINUTES") {
+        Ok(v) => v.parse()?,
+        Err(_) => 15,
+    };
+    let trusted_proxies = match crate::helper::get_env("GEN_TRUSTED_PROXIES") {
+        Ok(v) => v
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<std::net::IpAddr>, _>>()?,
+        Err(_) => Vec::new(),
+    };
+    let paths = GeneralSettings {
+        is_in_dev,
+        app_domain,
+        app_path,
+        sql_dir: crate::helper::get_exist_path(sql_resource_dir.as_str())?,
+        static_files_dir: crate::helper::get_exist_path(static_files_dir.as_str())?,
+        temp_dir: crate::helper::get_exist_path(temp_dir.as_str())?,
+        auth_stepup_minutes,
+        trusted_proxies,
+    };
+
+    // init RSA KEYS
+    let rsa_pass = crate::helper::get_env("RSA_PASS")?;
+    let rsa_priv_path = crate::helper::get_env("RSA_PRIV_KEY_PATH")?;
+    let rsa_publ_path = crate::helper::get_env("RSA_PUB_KEY_PATH")?;
+    let rsa_keys = utils::rsakeys::RsaKeys::init(&rsa_pass, &rsa_priv_path, &rsa_publ_path)?;
+
+    // init PGSQL db pool
+    let pgsql_conn_string = crate::helper::get_env("PGSQL_CONN_STRING")?;
+    let pgsql_max_conn = crate::helper::get_env("PGSQL_POOL_MAX_CONN")?.parse()?;
+    let pgsql_batch_size = crate::helper::get_env("PGSQL_BATCH_INSERTS")?.parse()?;
+    let notifier = crate::notify::Notifier::new(pgsql_conn_string.clone());
+    let pgsql_pool =
+        dbpool::pgsql::Pool::init(pgsql_conn_string, None, pgsql_max_conn, pgsql_batch_size)?;
+
+    // init mail client
+    let mail_templates = crate::mail::MailTemplates {
+        dir: crate::helper::get_exist_path(&crate::helper::get_env("MAIL_TEMPLATE_DIR")?)?,
+        name_format: crate::helper::get_env("MAIL_TEMPLATE_NAME_FORMAT")?,
+        languages: crate::helper::get_env("MAIL_LANGS")?
+            .split(',')
+            .filter(|v| !v.is_empty())
+            .map(|v| v.trim().to_lowercase())
+            .collect(),
+        default_language: crate::helper::get_env("MAIL_LANG_DEFAULT")?.to_lowercase(),
+    };
+    let mailer = match mailer {
+        Some(v) => v,
+        None => init_mailer(&paths)?,
+    };
+
+    Ok(actix_web::web::Data::new(AppContext {
+        general: paths,
+        rsa_keys,
+        pgsql_pool,
+        mailer,
+        mail_templates,
+        notifier,
+    }))
+}
+
+/// mail transport picked by `MAIL_TRANSPORT`
+fn init_mailer(
+    general: &GeneralSettings,
+) -> Result<Box<dyn crate::mail::transport::Mailer>, Box<dyn std::error::Error + Send + Sync>> {
+    let from_addrs = lettre::message::Mailbox::new(
+        Some(crate::helper::get_env("MAIL_FROM_NAME")?),
+        crate::helper::get_env("MAIL_FROM_ADRS")?.parse::<lettre::Address>()?,
+    );
+    // "smtp" or "file" (.eml files in the temp dir, for development); defaults to "file" in dev
+    let mail_transport = match crate::helper::get_env("MAIL_TRANSPORT") {
+        Ok(v) => v.to_lowercase(),
+        Err(_) if general.is_in_dev => "file".to_string(),
+        Err(_) => "smtp".to_string(),
+    };
+    let mailer: Box<dyn crate::mail::transport::Mailer> = match mail_transport.as_str() {
+        "smtp" => Box::new(crate::mail::transport::SmtpMailer::new(
+            &crate::helper::get_env("MAIL_SMTP_SERVER")?,
+            crate::helper::get_env("MAIL_SMTP_PORT")?.parse()?,
+            crate::helper::get_env("MAIL_SMTP_USER")?,
+            crate::helper::get_env("MAIL_SMTP_PASS")?,
+            from_addrs,
+            lettre::message::Mailbox::new(
+                Some(crate::helper::get_env("MAIL_FROM_NAME")?),
+                crate::helper::get_env("MAIL_REPLY_TO")?.parse::<lettre::Address>()?,
+            ),
+        )?),
+        "file" => Box::new(crate::mail::transport::FileSinkMailer::new(
+            general
+                .temp_dir
+                .join(crate::mail::transport::FileSinkMailer::SUB_DIR),
+            from_addrs,
+        )?),
+        other => return Err(format!("unknown MAIL_TRANSPORT '{}'", other).into()),
+    };
+    Ok(mailer)
+}
+
+fn config_public(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::resource("/static/{filename:.*}")
+            .route(actix_web::web::get().to(crate::handlers::other::static_files)),
+    )
+    .service(
+        actix_web::web::resource("/rsa/keys")
+            .route(actix_web::web::get().to(crate::handlers::other::rsa_public)),
+    );
+}
+
+fn config_auth(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::resource("/login")
+            .route(actix_web::web::post().to(crate::handlers::auth::user_login)),
+    )
+    .service(
+        actix_web::web::resource("/auth")
+            .route(actix_web::web::get().to(crate::handlers::auth::authenticate)),
+    )
+    .service(
+        actix_web::web::resource("/auth/stepup")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .route(actix_web::web::post().to(crate::handlers::auth::user_stepup)),
+    )
+    .service(
+        actix_web::web::resource("/auth/revoke/{id}")
+            .route(actix_web::web::get().to(crate::handlers::auth::revoke_confirm))
+            .route(actix_web::web::post().to(crate::handlers::auth::revoke_all)),
+    )
+    .service(
+        actix_web::web::resource("/auth/isauth")
+            .route(actix_web::web::get().to(crate::handlers::auth::is_authenticated)),
+    )
+    .service(
+        actix_web::web::resource("/auth/user")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .route(actix_web::web::get().to(crate::handlers::auth::get_auth_user)),
+    )
+    .service(
+        actix_web::web::resource("/auth/methods")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .route(actix_web::web::get().to(crate::handlers::auth::get_allowed_methods)),
+    )
+    .service(
+        actix_web::web::resource("/logout")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .route(actix_web::web::get().to(crate::handlers::auth::user_logout)),
+    );
+}
+
+fn config_users(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/users")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("/get/{user_id}")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "user_single_get",
+                    ))
+                    .route(actix_web::web::get().to(crate::handlers::users::user_get_single)),
+            )
+            .service(
+                actix_web::web::resource("/list")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "user_all_list",
+                    ))
+                    .route(
+                        actix_web::web::get()
+                            .to(|| async { actix_web::HttpResponse::Ok().body("user list") }),
+                    ),
+            ),
+    );
+}
+
+fn config_app_method(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/app_methods")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_list)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_list_all",
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::app_method::app_method_single_upsert)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_upsert_single",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/app_codes")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "app_method_app_codes",
+                    ))
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_get_app_code_list),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/xlsx")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_down_xlsx)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::all_of(
+                                "portal",
+                                &["app_method_list_all", "app_method_export"],
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::app_method::app_method_up_xlsx)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_upsert_all",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/xlsx/template")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "app_method_upsert_all",
+                    ))
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_template_xlsx),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/csv")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_down_csv)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::all_of(
+                                "portal",
+                                &["app_method_list_all", "app_method_export"],
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::app_method::app_method_up_txt)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_upsert_all",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/json")
+                    .app_data(
+                        actix_web::web::JsonConfig::default().limit(Consts::JSON_UPLOAD_MAX_BYTES),
+                    )
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_down_json)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::all_of(
+                                "portal",
+                                &["app_method_list_all", "app_method_export"],
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::app_method::app_method_up_json)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_upsert_all",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/jobs/import").route(
+                    actix_web::web::post()
+                        .to(crate::handlers::job::app_method_import_job)
+                        .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                            "portal",
+                            "app_method_upsert_all",
+                        )),
+                ),
+            )
+            .service(
+                actix_web::web::resource("/jobs/export").route(
+                    actix_web::web::post()
+                        .to(crate::handlers::job::app_method_export_job)
+                        .wrap(crate::middleware::auth::AuthorizeFactory::all_of(
+                            "portal",
+                            &["app_method_list_all", "app_method_export"],
+                        )),
+                ),
+            )
+            .service(
+                actix_web::web::resource("/upload_errors/{name}")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "app_method_upsert_all",
+                    ))
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_upload_errors),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/{id}")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_method::app_method_get_single_by_id)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_get_single_by_id",
+                            )),
+                    )
+                    .route(
+                        actix_web::web::delete()
+                            .to(crate::handlers::app_method::app_method_delete_by_id)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_method_del_single_by_id",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/{id}/history")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "app_method_history",
+                    ))
+                    .route(
+                        actix_web::web::get().to(crate::handlers::app_method::app_method_history),
+                    ),
+            ),
+    );
+}
+
+fn config_app_owner(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/app_owners")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::app_owner::app_owner_list)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_owner_list",
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::app_owner::app_owner_single_upsert)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "app_owner_upsert",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/{id}")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "app_owner_delete",
+                    ))
+                    .route(
+                        actix_web::web::delete()
+                            .to(crate::handlers::app_owner::app_owner_delete_by_id),
+                    ),
+            ),
+    );
+}
+
+fn config_grant(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/grants")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::grant::grant_list)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "grant_list",
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::grant::grant_single_upsert)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "grant_upsert",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/{id}")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "grant_delete",
+                    ))
+                    .route(actix_web::web::delete().to(crate::handlers::grant::grant_delete_by_id)),
+            ),
+    );
+}
+
+fn config_audit(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/audit")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("/auth")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "audit_auth_list",
+                    ))
+                    .route(actix_web::web::get().to(crate::handlers::audit::auth_audit_list)),
+            ),
+    );
+}
+
+fn config_mail(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/mail/outbox")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "mail_outbox_list",
+                    ))
+                    .route(actix_web::web::get().to(crate::handlers::mail::outbox_list)),
+            )
+            .service(
+                actix_web::web::resource("/{id}/resend")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "mail_outbox_resend",
+                    ))
+                    .route(actix_web::web::post().to(crate::handlers::mail::outbox_resend)),
+            ),
+    )
+    .service(
+        actix_web::web::scope("/mail/templates")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::mail::template_list)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "mail_template_list",
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::mail::template_single_upsert)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "mail_template_upsert",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/preview")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "mail_template_upsert",
+                    ))
+                    .route(actix_web::web::post().to(crate::handlers::mail::template_preview)),
+            )
+            .service(
+                actix_web::web::resource("/{id}")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "mail_template_delete",
+                    ))
+                    .route(
+                        actix_web::web::delete().to(crate::handlers::mail::template_delete_by_id),
+                    ),
+            ),
+    );
+}
+
+fn config_announcement(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/announcements")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(
+                        actix_web::web::get().to(crate::handlers::announcement::announcement_list),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::announcement::announcement_create)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "announcement_create",
+                            )),
+                    ),
+            )
+            .service(actix_web::web::resource("/{id}/read").route(
+                actix_web::web::post().to(crate::handlers::announcement::announcement_mark_read),
+            )),
+    );
+}
+
+fn config_notification(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/notifications")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("").route(
+                    actix_web::web::get().to(crate::handlers::notification::notification_list),
+                ),
+            )
+            .service(actix_web::web::resource("/stream").route(
+                actix_web::web::get().to(crate::handlers::notification::notification_stream),
+            ))
+            .service(actix_web::web::resource("/{id}/read").route(
+                actix_web::web::post().to(crate::handlers::notification::notification_mark_read),
+            )),
+    );
+}
+
+fn config_job(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/jobs")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(actix_web::web::get().to(crate::handlers::job::job_list)),
+            )
+            .service(
+                actix_web::web::resource("/{id}")
+                    .route(actix_web::web::get().to(crate::handlers::job::job_get_by_id)),
+            )
+            .service(
+                actix_web::web::resource("/{id}/cancel")
+                    .route(actix_web::web::post().to(crate::handlers::job::job_cancel)),
+            )
+            .service(
+                actix_web::web::resource("/{id}/result")
+                    .route(actix_web::web::get().to(crate::handlers::job::job_result)),
+            ),
+    );
+}
+
+fn config_webhook(cfg: &mut actix_web::web::ServiceConfig) {
+    cfg.service(
+        actix_web::web::scope("/webhooks")
+            .wrap(crate::middleware::auth::AuthenticateFactory)
+            .service(
+                actix_web::web::resource("")
+                    .route(
+                        actix_web::web::get()
+                            .to(crate::handlers::webhook::webhook_list)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "webhook_list",
+                            )),
+                    )
+                    .route(
+                        actix_web::web::post()
+                            .to(crate::handlers::webhook::webhook_single_upsert)
+                            .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                                "portal",
+                                "webhook_upsert",
+                            )),
+                    ),
+            )
+            .service(
+                actix_web::web::resource("/deliveries")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "webhook_list",
+                    ))
+                    .route(actix_web::web::get().to(crate::handlers::webhook::delivery_list)),
+            )
+            .service(
+                actix_web::web::resource("/{id}")
+                    .wrap(crate::middleware::auth::AuthorizeFactory::new(
+                        "portal",
+                        "webhook_delete",
+                    ))
+                    .route(
+                        actix_web::web::delete().to(crate::handlers::webhook::webhook_delete_by_id),
+                    ),
+            ),
+    );
+}
+
+/// development only helpers, registered when `GEN_UNDER_DEVELOPMENT` is true and the mails
+/// go to the file sink; not authenticated, so they answer only to loopback peers
+fn config_dev(cfg: &mut actix_web::web::ServiceConfig) {
+    let is_loopback = || {
+        actix_web::guard::fn_guard(|ctx| {
+            ctx.head()
+                .peer_addr
+                .map(|v| v.ip().is_loopback())
+                .unwrap_or(false)
+        })
+    };
+    cfg.service(
+        actix_web::web::resource("/dev/mails")
+            .guard(is_loopback())
+            .route(actix_web::web::get().to(crate::handlers::dev::sink_mail_list)),
+    )
+    .service(
+        actix_web::web::resource("/dev/mails/{name}")
+            .guard(is_loopback())
+            .route(actix_web::web::get().to(crate::handlers::dev::sink_mail_get)),
+    );
+}
+
+pub fn init_app_service(
+    app_data: actix_web::web::Data<AppContext>,
+) -> actix_web::App<
+    impl actix_web::dev::ServiceFactory<
+        actix_web::dev::ServiceRequest,
+        Config = (),
+        Response = actix_web::dev::ServiceResponse,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let app_path = app_data.general.app_path.clone();
+    let has_dev_mails = app_data.general.is_in_dev && app_data.mailer.sink_dir().is_some();
+
+    actix_web::App::new()
+        .app_data(app_data)
+        .wrap(crate::middleware::logger::LoggerFactory)
+        .wrap(actix_web::middleware::NormalizePath::trim())
+        .service(actix_web::web::scope(&app_path).configure(|cfg| {
+            config_public(cfg);
+            config_auth(cfg);
+            config_users(cfg);
+            config_app_method(cfg);
+            config_app_owner(cfg);
+            config_grant(cfg);
+            config_audit(cfg);
+            config_mail(cfg);
+            config_announcement(cfg);
+            config_notification(cfg);
+            config_webhook(cfg);
+            config_job(cfg);
+            if has_dev_mails {
+                config_dev(cfg);
+            }
+        }))
+        .route(
+            "/",
+            actix_web::web::get().to(|| async { "Hello from the Server" }),
+        )
+        .default_service(actix_web::web::route().method(actix_web::http::Method::GET))
+}
@@ -0,0 +1,322 @@
+pub mod outbox;
+pub mod transport;
+
+use actix_web::web;
+use std::path::PathBuf;
+
+use crate::AppContext;
+
+/// mail content rendered from templates, ready to be sent
+#[derive(Debug, serde::Serialize, Clone, PartialEq, Eq)]
+pub struct MailContent {
+    pub subject: String,
+    pub html: String,
+    pub text: Option<String>, // plain text alternative
+}
+
+/// stands for the token in the queued sign-in link mails, the outbox worker signs the token
+/// when sending, so the queue holds no usable link
+pub const LINK_TOKEN_MARK: &str = "{{link_token}}";
+
+/// the mails sent by the app, with their placeholders and sample values used by the previews
+pub const TEMPLATE_PARAMS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "login",
+        &[
+            ("name", "Ion Popescu"),
+            ("link", "https://example.com/portal/auth?atk=sample-token"),
+            ("expiry", "2024-01-01 12:10"),
+        ],
+    ),
+    (
+        "new_device",
+        &[
+            ("name", "Ion Popescu"),
+            ("time", "2024-01-01 12:00"),
+            ("ip", "192.0.2.10"),
+            ("user_agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64)"),
+            ("link", "https://example.com/portal/auth/revoke/sample-id"),
+        ],
+    ),
+    (
+        "announcement",
+        &[
+            ("title", "Reporting period closing"),
+            (
+                "message",
+                "The reporting period closes on Friday.\nPlease upload your files until then.",
+            ),
+        ],
+    ),
+];
+
+/// placeholders a message of the template can't go without (the links the user acts on)
+pub const REQUIRED_PARAMS: &[(&str, &[&str])] = &[
+    ("login", &["link"]),
+    ("new_device", &["link"]),
+    ("announcement", &["message"]),
+];
+
+/// placeholders of `template` missing from `text`
+pub fn missing_params(template: &str, text: &str) -> Vec<&'static str> {
+    REQUIRED_PARAMS
+        .iter()
+        .find(|(k, _)| *k == template)
+        .map(|(_, v)| *v)
+        .unwrap_or_default()
+        .iter()
+        .filter(|v| !text.contains(&format!("{{{{{}}}}}", v)))
+        .copied()
+        .collect()
+}
+
+/// sample placeholder values for `template`, `None` for templates the app doesn't send
+pub fn sample_params(template: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    TEMPLATE_PARAMS
+        .iter()
+        .find(|(k, _)| *k == template)
+        .map(|(_, v)| *v)
+}
+
+/// renders the `template` message from the db (`lang`, then the default language),
+/// else from the template files
+pub async fn render_mail(
+    template: &str,
+    lang: &str,
+    params: &[(&str, &str)],
+    ctx: &web::Data<AppContext>,
+) -> Result<MailContent, actix_web::Error> {
+    let templates = &ctx.mail_templates;
+    for lang in [lang, templates.default_language.as_str()] {
+        if let Some(v) = crate::model::mail_template::db_mail_template_get_single(
+            template,
+            lang,
+            ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await?
+        {
+            return templates.render_content(
+                lang,
+                &v.subject,
+                &v.html,
+                v.text_body.as_deref(),
+                params,
+            );
+        }
+    }
+    templates.render(template, lang, params)
+}
+
+/// html mail templates from `dir`:
+/// - the layout, named by `name_format` with `{}` replaced by the language (ex.: `email_{}.html`),
+///   wraps the message into its `{{contents}}` placeholder
+/// - the messages, named `<template>_<language>.html`, having the subject on the first line
+///   as `<!-- subject: ... -->`
+///
+/// `{{name}}` placeholders in subjects and messages are replaced with the render params
+pub struct MailTemplates {
+    pub dir: PathBuf,
+    pub name_format: String,
+    pub languages: Vec<String>,
+    pub default_language: String,
+}
+
+impl MailTemplates {
+    const SUBJECT_PREFIX: &'static str = "<!-- subject:";
+    const SUBJECT_SUFFIX: &'static str = "-->";
+
+    /// first supported language from the user preference, then from the `Accept-Language` header,
+    /// else the default language
+    pub fn pick_language(&self, preferred: Option<&str>, accept_language: Option<&str>) -> String {
+        let is_supported = |v: &str| self.languages.iter().any(|l| l.eq_ignore_ascii_case(v));
+
+        if let Some(v) = preferred.map(str::trim).filter(|v| is_supported(v)) {
+            return v.to_lowercase();
+        }
+        if let Some(v) = accept_language
+            .map(parse_accept_language)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|v| is_supported(v))
+        {
+            return v;
+        }
+        self.default_language.clone()
+    }
+
+    /// renders the `template` message in `lang` (or the default language if not available)
+    /// inside the layout of the same language
+    pub fn render(
+        &self,
+        template: &str,
+        lang: &str,
+        params: &[(&str, &str)],
+    ) -> Result<MailContent, actix_web::Error> {
+        let lang = if self
+            .dir
+            .join(format!("{}_{}.html", template, lang))
+            .exists()
+        {
+            lang
+        } else {
+            self.default_language.as_str()
+        };
+
+        let raw = std::fs::read_to_string(self.dir.join(format!("{}_{}.html", template, lang)))?;
+        let (first_line, body) = raw.split_once('\n').unwrap_or((raw.as_str(), ""));
+        let Some(subject) = first_line
+            .trim()
+            .strip_prefix(Self::SUBJECT_PREFIX)
+            .and_then(|v| v.strip_suffix(Self::SUBJECT_SUFFIX)) else {
+            return Err(actix_web::error::ErrorExpectationFailed(format!(
+                "mail template '{}_{}' has no subject line",
+                template, lang
+            )));
+        };
+
+        self.render_content(lang, subject.trim(), body, None, params)
+    }
+
+    /// fills the placeholders of a message and puts its html into the `lang` layout
+    /// (or the default language one if not available)
+    pub fn render_content(
+        &self,
+        lang: &str,
+        subject: &str,
+        html: &str,
+        text: Option<&str>,
+        params: &[(&str, &str)],
+    ) -> Result<MailContent, actix_web::Error> {
+        let mut layout_path = self.dir.join(self.name_format.replace("{}", lang));
+        if !layout_path.exists() {
+            layout_path = self
+                .dir
+                .join(self.name_format.replace("{}", &self.default_language));
+        }
+        let layout = std::fs::read_to_string(layout_path)?;
+        let html = layout.replace("{{contents}}", &fill_placeholders(html, params, true));
+
+        Ok(MailContent {
+            subject: fill_placeholders(subject, params, false),
+            html,
+            text: text.map(|v| fill_placeholders(v, params, false)),
+        })
+    }
+}
+
+fn fill_placeholders(text: &str, params: &[(&str, &str)], is_html: bool) -> String {
+    params.iter().fold(text.to_string(), |acc, (k, v)| {
+        let v = if is_html {
+            escape_html(v)
+        } else {
+            v.to_string()
+        };
+        acc.replace(&format!("{{{{{}}}}}", k), &v)
+    })
+}
+
+fn escape_html(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// primary language subtags from an `Accept-Language` header value, by descending quality
+fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut langs: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+            let lang = tag.split('-').next()?.trim().to_lowercase();
+            if lang.is_empty() || lang == "*" {
+                return None;
+            }
+            let quality = parts
+                .find_map(|v| v.trim().strip_prefix("q="))
+                .and_then(|v| v.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((lang, quality))
+        })
+        .collect();
+    langs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    langs.into_iter().map(|(v, _)| v).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    fn templates() -> super::MailTemplates {
+        super::MailTemplates {
+            dir: std::path::PathBuf::from("./resources/mail"),
+            name_format: "email_{}.html".into(),
+            languages: vec!["ro".into(), "en".into()],
+            default_language: "ro".into(),
+        }
+    }
+
+    #[test]
+    fn pick_language() {
+        let tpl = templates();
+        assert_eq!("en", tpl.pick_language(Some("EN"), Some("ro-RO")));
+        assert_eq!(
+            "en",
+            tpl.pick_language(None, Some("de-DE,en-US;q=0.8,ro;q=0.5"))
+        );
+        assert_eq!("ro", tpl.pick_language(Some("fr"), Some("de, fr;q=0.9")));
+        assert_eq!("ro", tpl.pick_language(None, None));
+    }
+
+    #[test]
+    fn render_login() {
+        let tpl = templates();
+        let res = tpl
+            .render("login", "en", &[("link", "http://localhost/auth?atk=123")])
+            .unwrap();
+        assert!(!res.subject.is_empty());
+        assert!(res.html.contains("http://localhost/auth?atk=123"));
+
+        let res = tpl
+            .render("new_device", "en", &[("user_agent", "<script>")])
+            .unwrap();
+        assert!(res.html.contains("&lt;script&gt;"));
+        assert!(!res.html.contains("{{contents}}"));
+
+        let res = tpl.render("login", "de", &[]).unwrap();
+        assert!(res.html.contains("lang=\"ro\""));
+    }
+
+    #[test]
+    fn render_content() {
+        let tpl = templates();
+        let params = super::sample_params("login").unwrap();
+        let res = tpl
+            .render_content(
+                "de",
+                "Sign in, {{name}}",
+                "<a href=\"{{link}}\">sign in</a> until {{expiry}}",
+                Some("Sign in: {{link}}"),
+                params,
+            )
+            .unwrap();
+        assert_eq!("Sign in, Ion Popescu", res.subject);
+        assert!(res.html.contains("until 2024-01-01 12:10"));
+        assert_eq!(
+            Some("Sign in: https://example.com/portal/auth?atk=sample-token"),
+            res.text.as_deref()
+        );
+        assert!(super::sample_params("unknown").is_none());
+    }
+
+    #[test]
+    fn missing_params() {
+        assert!(super::missing_params("login", "<a href=\"{{link}}\">sign in</a>").is_empty());
+        assert_eq!(
+            vec!["link"],
+            super::missing_params("new_device", "{{name}}, {{ip}}")
+        );
+        assert!(super::missing_params("unknown", "").is_empty());
+    }
+}
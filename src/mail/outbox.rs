@@ -0,0 +1,217 @@
+use actix_web::web;
+use std::time::Duration;
+
+use crate::{
+    extractors::auth::{AuthClaims, TokenPurpose},
+    model::{
+        audit::{db_audit_record, AuthAudit, AuthEvent, AuthOutcome},
+        mail_outbox::{MailKind, OutboxMail},
+    },
+    AppContext,
+};
+
+/// how often the worker looks for due mails
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// mails sent on each poll
+pub const BATCH_SIZE: i64 = 20;
+/// failed attempts before a mail goes "dead"
+pub const MAX_ATTEMPTS: i32 = 8;
+/// first retry delay, doubled on each failed attempt
+pub const BACKOFF_BASE_SECS: i32 = 30;
+
+/// background worker sending the queued mails, runs for the whole life of the server
+pub async fn run_worker(ctx: web::Data<AppContext>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = process_batch(&ctx).await {
+            log::error!("Mail outbox worker -> {}", err);
+        }
+    }
+}
+
+/// sends one batch of due mails; returns the number of mails sent
+///
+/// a failed status update is logged and the batch goes on, the mail stays "sending"
+/// and is picked up again by a later claim
+pub async fn process_batch(ctx: &web::Data<AppContext>) -> Result<usize, actix_web::Error> {
+    let mails =
+        crate::model::mail_outbox::db_outbox_claim(BATCH_SIZE, ctx, Duration::from_secs(10))
+            .await?;
+
+    let mut sent = 0;
+    for mail in mails {
+        //an expired sign-in link is useless, no retry
+        let is_expired = mail
+            .link_exp
+            .is_some_and(|v| v <= chrono::Utc::now().naive_utc());
+        let res = if is_expired {
+            Err("sign-in link expired".to_string())
+        } else {
+            send(&mail, ctx).await
+        };
+        match res {
+            Ok(_) => {
+                if let Err(err) = crate::model::mail_outbox::db_outbox_mark_sent(
+                    &mail.id,
+                    ctx,
+                    Duration::from_secs(10),
+                )
+                .await
+                {
+                    log::error!("Mail outbox {} mark sent -> {}", mail.id, err);
+                }
+                audit_delivery(&mail, None, ctx).await;
+                sent += 1;
+            }
+            Err(err) => {
+                log::error!(
+                    "Mail outbox {} attempt {} -> {}",
+                    mail.id,
+                    mail.attempts + 1,
+                    err
+                );
+                let max_attempts = if is_expired { 0 } else { MAX_ATTEMPTS };
+                if let Err(db_err) = crate::model::mail_outbox::db_outbox_mark_failed(
+                    &mail.id,
+                    &err,
+                    max_attempts,
+                    BACKOFF_BASE_SECS,
+                    ctx,
+                    Duration::from_secs(10),
+                )
+                .await
+                {
+                    log::error!("Mail outbox {} mark failed -> {}", mail.id, db_err);
+                }
+                audit_delivery(&mail, Some(err), ctx).await;
+            }
+        }
+    }
+    Ok(sent)
+}
+
+async fn send(mail: &OutboxMail, ctx: &web::Data<AppContext>) -> Result<(), String> {
+    let to_addrs = mail
+        .to_addrs
+        .iter()
+        .map(|v| v.parse::<lettre::message::Mailbox>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let subject = mail.subject.clone();
+    let (html, text) = match link_token(mail, ctx)? {
+        Some(token) => (
+            mail.html.replace(super::LINK_TOKEN_MARK, &token),
+            mail.text_body
+                .as_ref()
+                .map(|v| v.replace(super::LINK_TOKEN_MARK, &token)),
+        ),
+        None => (mail.html.clone(), mail.text_body.clone()),
+    };
+
+    //smtp/ file calls are blocking
+    let ctx = ctx.clone();
+    web::block(move || {
+        ctx.mailer
+            .send(to_addrs, &subject, &html, text.as_deref())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// signs the token of a sign-in link mail, `None` for the other mails
+fn link_token(mail: &OutboxMail, ctx: &web::Data<AppContext>) -> Result<Option<String>, String> {
+    let (Some(jti), Some(exp)) = (mail.link_token_id, mail.link_exp) else {
+        return Ok(None);
+    };
+    let purpose = match MailKind::parse(&mail.kind) {
+        Some(MailKind::Login) => TokenPurpose::Login,
+        Some(MailKind::StepUp) => TokenPurpose::StepUp,
+        _ => return Err(format!("{} mail with a sign-in link", mail.kind)),
+    };
+    let Some(user_id) = mail.user_id.clone() else {
+        return Err("sign-in link mail without user".to_string());
+    };
+    let claims = AuthClaims {
+        iss: ctx.general.app_domain.clone(),
+        sub: user_id,
+        jti,
+        iat: chrono::Utc::now().timestamp(),
+        exp: exp.timestamp(),
+        auth_time: None,
+        purpose: Some(purpose),
+    };
+    claims
+        .create_token(ctx)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// login related mails end up in the auth audit trail
+async fn audit_delivery(mail: &OutboxMail, error: Option<String>, ctx: &web::Data<AppContext>) {
+    let (success, failure) = match MailKind::parse(&mail.kind) {
+        Some(MailKind::Login) | Some(MailKind::StepUp) => {
+            (AuthEvent::LinkSent, AuthEvent::LinkFailed)
+        }
+        Some(MailKind::NewDevice) => (AuthEvent::NewDeviceMail, AuthEvent::NewDeviceMail),
+        Some(MailKind::Announcement) | None => return,
+    };
+    let entry = match error {
+        None => AuthAudit::without_request(
+            mail.user_id.as_deref(),
+            success,
+            AuthOutcome::Success,
+            Some(format!("{} mail {}", mail.kind, mail.id)),
+        ),
+        Some(err) => AuthAudit::without_request(
+            mail.user_id.as_deref(),
+            failure,
+            AuthOutcome::Failure,
+            Some(format!("{} mail {}: {}", mail.kind, mail.id, err)),
+        ),
+    };
+    db_audit_record(entry, ctx).await;
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn process_batch() {
+        let mailer = crate::mail::transport::RecordingMailer::default();
+        let ctx = crate::init_app_data_with_mailer(Some(Box::new(mailer.clone()))).unwrap();
+        let to_addrs = vec!["Catalin <mail@example.com>".parse().unwrap()];
+        let mail = crate::model::mail_outbox::db_outbox_enqueue(
+            crate::model::mail_outbox::MailKind::Login,
+            Some("catalin"),
+            &to_addrs,
+            &crate::mail::MailContent {
+                subject: "Portal CDG - test".into(),
+                html: "<span>test</span>".into(),
+                text: None,
+            },
+            None,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+
+        let res = super::process_batch(&ctx).await.unwrap();
+        assert!(res > 0);
+        assert!(mailer
+            .sent()
+            .iter()
+            .any(|v| v.subject == "Portal CDG - test"
+                && v.to_addrs == vec!["mail@example.com".to_string()]));
+
+        let list = crate::model::mail_outbox::db_outbox_get_filtered(
+            Some("sent"),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(list.iter().any(|v| v.id == mail.id));
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+pub type MailError = Box<dyn std::error::Error + Send + Sync>;
+
+/// sends html mails, with an optional plain text alternative;
+/// picked at start up by the `MAIL_TRANSPORT` setting
+pub trait Mailer: Send + Sync {
+    fn send(
+        &self,
+        to_addrs: Vec<lettre::message::Mailbox>,
+        subject: &str,
+        html: &str,
+        text: Option<&str>,
+    ) -> Result<(), MailError>;
+
+    /// directory of the written mails, only for the development file sink
+    fn sink_dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// real mails through the smtp server, with the plain text alternative when there is one
+pub struct SmtpMailer {
+    transport: lettre::SmtpTransport,
+    from_addrs: lettre::message::Mailbox,
+    reply_to: lettre::message::Mailbox,
+}
+
+impl SmtpMailer {
+    /// tls from the start on port 465, upgraded with STARTTLS on the others
+    pub fn new(
+        server: &str,
+        port: u16,
+        user_name: String,
+        password: String,
+        from_addrs: lettre::message::Mailbox,
+        reply_to: lettre::message::Mailbox,
+    ) -> Result<Self, MailError> {
+        let builder = if port == 465 {
+            lettre::SmtpTransport::relay(server)?
+        } else {
+            lettre::SmtpTransport::starttls_relay(server)?
+        };
+        let transport = builder
+            .port(port)
+            .credentials(lettre::transport::smtp::authentication::Credentials::new(
+                user_name, password,
+            ))
+            .build();
+        Ok(Self {
+            transport,
+            from_addrs,
+            reply_to,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(
+        &self,
+        to_addrs: Vec<lettre::message::Mailbox>,
+        subject: &str,
+        html: &str,
+        text: Option<&str>,
+    ) -> Result<(), MailError> {
+        let builder = lettre::Message::builder()
+            .from(self.from_addrs.clone())
+            .reply_to(self.reply_to.clone());
+        let message = message(builder, to_addrs, subject, html, text, |v| {
+            lettre::message::Body::new(v.to_string())
+        })?;
+        lettre::Transport::send(&self.transport, &message)?;
+        Ok(())
+    }
+}
+
+/// development sink: each mail is written as an .eml file into `dir`, see `sink_list`
+pub struct FileSinkMailer {
+    pub dir: PathBuf,
+    pub from_addrs: lettre::message::Mailbox,
+}
+
+impl FileSinkMailer {
+    /// sub directory of the temp dir holding the sink files
+    pub const SUB_DIR: &'static str = "mails";
+
+    pub fn new(dir: PathBuf, from_addrs: lettre::message::Mailbox) -> Result<Self, MailError> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, from_addrs })
+    }
+}
+
+impl Mailer for FileSinkMailer {
+    fn send(
+        &self,
+        to_addrs: Vec<lettre::message::Mailbox>,
+        subject: &str,
+        html: &str,
+        text: Option<&str>,
+    ) -> Result<(), MailError> {
+        let builder = lettre::Message::builder().from(self.from_addrs.clone());
+        let message = message(builder, to_addrs, subject, html, text, readable_body)?;
+
+        let file_name = format!(
+            "{}_{}.eml",
+            chrono::Utc::now().format("%Y%m%d%H%M%S%3f"),
+            uuid::Uuid::new_v4()
+        );
+        std::fs::write(self.dir.join(file_name), message.formatted())?;
+        Ok(())
+    }
+
+    fn sink_dir(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+}
+
+/// html mail, or a multipart alternative of plain text and html when there is a `text`;
+/// `body` encodes each part
+fn message(
+    mut builder: lettre::message::MessageBuilder,
+    to_addrs: Vec<lettre::message::Mailbox>,
+    subject: &str,
+    html: &str,
+    text: Option<&str>,
+    body: fn(&str) -> lettre::message::Body,
+) -> Result<lettre::Message, MailError> {
+    builder = builder.subject(subject);
+    for addr in to_addrs {
+        builder = builder.to(addr);
+    }
+    let html_part = lettre::message::SinglePart::builder()
+        .header(lettre::message::header::ContentType::TEXT_HTML)
+        .body(body(html));
+    let message = match text {
+        Some(text) => builder.multipart(
+            lettre::message::MultiPart::alternative()
+                .singlepart(
+                    lettre::message::SinglePart::builder()
+                        .header(lettre::message::header::ContentType::TEXT_PLAIN)
+                        .body(body(text)),
+                )
+                .singlepart(html_part),
+        )?,
+        None => builder.singlepart(html_part)?,
+    };
+    Ok(message)
+}
+
+/// 8 bit body (no quoted-printable) when possible, so the links can be copied from the file
+fn readable_body(v: &str) -> lettre::message::Body {
+    lettre::message::Body::new_with_encoding(
+        v.to_string(),
+        lettre::message::header::ContentTransferEncoding::EightBit,
+    )
+    .unwrap_or_else(lettre::message::Body::new)
+}
+
+/// .eml files in the sink `dir`, newest first; a missing dir means no mails
+pub fn sink_list(dir: &Path) -> Result<Vec<SinkMail>, MailError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut res = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.path().extension().and_then(|v| v.to_str()) != Some("eml") {
+            continue;
+        }
+        let meta = entry.metadata()?;
+        res.push(SinkMail {
+            file_name: entry.file_name().to_string_lossy().to_string(),
+            size: meta.len(),
+            modified: chrono::DateTime::<chrono::Utc>::from(meta.modified()?).naive_utc(),
+        });
+    }
+    res.sort_by_key(|v| std::cmp::Reverse(v.modified));
+    Ok(res)
+}
+
+/// path of a sink file, only for plain .eml file names (no directories)
+pub fn sink_file_path(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let is_plain = file_name.ends_with(".eml")
+        && !file_name.starts_with('.')
+        && !file_name.contains(['/', '\\']);
+    let path = dir.join(file_name);
+    if is_plain && path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// in memory transport keeping every sent mail, for tests; clones share the same mails
+#[derive(Debug, Clone, Default)]
+pub struct RecordingMailer {
+    mails: Arc<Mutex<Vec<SentMail>>>,
+}
+
+impl RecordingMailer {
+    /// mails sent so far, oldest first
+    pub fn sent(&self) -> Vec<SentMail> {
+        self.mails.lock().map(|v| v.clone()).unwrap_or_default()
+    }
+}
+
+impl Mailer for RecordingMailer {
+    fn send(
+        &self,
+        to_addrs: Vec<lettre::message::Mailbox>,
+        subject: &str,
+        html: &str,
+        text: Option<&str>,
+    ) -> Result<(), MailError> {
+        let mut mails = self.mails.lock().map_err(|e| e.to_string())?;
+        mails.push(SentMail {
+            to_addrs: to_addrs.iter().map(|v| v.email.to_string()).collect(),
+            subject: subject.to_string(),
+            html: html.to_string(),
+            text: text.map(ToString::to_string),
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMail {
+    pub to_addrs: Vec<String>, // addresses only, without display names
+    pub subject: String,
+    pub html: String,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize, Clone, PartialEq, Eq)]
+pub struct SinkMail {
+    pub file_name: String,
+    pub size: u64,
+    pub modified: chrono::NaiveDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mailer;
+
+    #[test]
+    fn file_sink() {
+        let dir = std::env::temp_dir().join(format!("mail_sink_{}", uuid::Uuid::new_v4()));
+        let sink =
+            super::FileSinkMailer::new(dir.clone(), "Portal <portal@example.com>".parse().unwrap())
+                .unwrap();
+        sink.send(
+            vec!["Catalin <mail@example.com>".parse().unwrap()],
+            "Portal CDG - test",
+            "<a href=\"http://localhost/auth?atk=123\">link</a>",
+            Some("link: http://localhost/auth?atk=123"),
+        )
+        .unwrap();
+
+        let list = super::sink_list(&dir).unwrap();
+        assert_eq!(1, list.len());
+        let path = super::sink_file_path(&dir, &list[0].file_name).unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        assert!(content.contains("To: Catalin <mail@example.com>"));
+        assert!(content.contains("<a href=\"http://localhost/auth?atk=123\">"));
+        assert!(content.contains("link: http://localhost/auth?atk=123"));
+        assert!(super::sink_file_path(&dir, "../secret.eml").is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn text_alternative() {
+        let builder =
+            lettre::Message::builder().from("Portal <portal@example.com>".parse().unwrap());
+        let message = super::message(
+            builder,
+            vec!["Catalin <mail@example.com>".parse().unwrap()],
+            "Portal CDG - test",
+            "<span>test</span>",
+            Some("test text"),
+            |v| lettre::message::Body::new(v.to_string()),
+        )
+        .unwrap();
+        let content = String::from_utf8(message.formatted()).unwrap();
+        assert!(content.contains("multipart/alternative"));
+        assert!(content.contains("text/plain"));
+        assert!(content.contains("test text"));
+        assert!(content.contains("<span>test</span>"));
+    }
+
+    #[test]
+    fn recording() {
+        let mailer = super::RecordingMailer::default();
+        let shared = mailer.clone();
+        mailer
+            .send(
+                vec!["Catalin <mail@example.com>".parse().unwrap()],
+                "Portal CDG - test",
+                "<span>test</span>",
+                None,
+            )
+            .unwrap();
+
+        let sent = shared.sent();
+        assert_eq!(1, sent.len());
+        assert_eq!(vec!["mail@example.com".to_string()], sent[0].to_addrs);
+        assert_eq!("Portal CDG - test", sent[0].subject);
+    }
+}
@@ -12,6 +12,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         err
     })?;
 
+    //send the queued mails in background
+    actix_web::rt::spawn(cdg_portal::mail::outbox::run_worker(app_data.clone()));
+    //post the queued webhook deliveries in background
+    actix_web::rt::spawn(cdg_portal::webhook::run_worker(app_data.clone()));
+    //run the queued import/ export jobs in background
+    actix_web::rt::spawn(cdg_portal::jobs::run_workers(app_data.clone()));
+    //push the notifications stored by any server to the SSE streams of this one
+    actix_web::rt::spawn(cdg_portal::notify::run_listener(app_data.clone()));
+
     actix_web::HttpServer::new(move || cdg_portal::init_app_service(app_data.clone()))
         .bind(("0.0.0.0", 3001))
         .map_err(|err| {
@@ -1,175 +1,339 @@
-use actix_web::{
-    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    web, Error, FromRequest, HttpMessage,
-};
-use futures::future::LocalBoxFuture;
-
-use crate::extractors::auth::AuthenticateData;
-
-pub struct AuthenticateMiddleware<S> {
-    service: std::rc::Rc<S>,
-}
-
-impl<S: 'static, B> Service<ServiceRequest> for AuthenticateMiddleware<S>
-where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
-    B: 'static,
-{
-    type Response = ServiceResponse<B>;
-    type Error = Error;
-    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
-
-    forward_ready!(service);
-
-    fn call(&self, req: ServiceRequest) -> Self::Future {
-        let svc = self.service.clone();
-
-        Box::pin(async move {
-            let (req, mut payload) = req.into_parts();
-            let auth_data =
-                match crate::extractors::auth::AuthenticateData::from_request(&req, &mut payload)
-                    .await
-                {
-                    Ok(v) => v,
-                    Err(e) => return Err(e),
-                };
-            let Some(ctx) = req.app_data() else {
-                return Err(actix_web::error::ErrorExpectationFailed("app context missing"));
-            };
-
-            //check that is the last token
-            let Some(last_token) = crate::model::users::db_get_last_token_id(
-                &auth_data.1.sub,
-                ctx,
-                std::time::Duration::from_secs(10),
-            )
-            .await? else {
-                return Err(actix_web::error::ErrorForbidden("no active token registration"));
-            };
-
-            if last_token.token_id.ne(&auth_data.1.jti) {
-                return Err(actix_web::error::ErrorForbidden("invalid token"));
-            }
-
-            //go further through the call chain
-            req.extensions_mut().insert(auth_data);
-            let req = ServiceRequest::from_parts(req, payload);
-
-            let res = svc.call(req).await?;
-            Ok(res)
-        })
-    }
-}
-
-pub struct AuthenticateFactory;
-
-impl<S: 'static, B> Transform<S, ServiceRequest> for AuthenticateFactory
-where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
-    B: 'static,
-{
-    type Response = ServiceResponse<B>;
-    type Error = Error;
-    type InitError = ();
-    type Transform = AuthenticateMiddleware<S>;
-    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
-
-    fn new_transform(&self, service: S) -> Self::Future {
-        std::future::ready(Ok(AuthenticateMiddleware {
-            service: std::rc::Rc::new(service),
-        }))
-    }
-}
-
-pub struct AuthorizeMiddleware<S> {
-    app_code: &'static str,
-    method_code: &'static str,
-    service: std::rc::Rc<S>,
-}
-
-impl<S: 'static, B> Service<ServiceRequest> for AuthorizeMiddleware<S>
-where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
-    B: 'static,
-{
-    type Response = ServiceResponse<B>;
-    type Error = Error;
-    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
-
-    forward_ready!(service);
-
-    fn call(&self, req: ServiceRequest) -> Self::Future {
-        let svc = self.service.clone();
-        let app_code = self.app_code;
-        let method_code = self.method_code;
-
-        Box::pin(async move {
-            let (req, mut payload) = req.into_parts();
-            let user_id =
-                crate::extractors::auth::AuthenticateData::from_request(&req, &mut payload)
-                    .await
-                    .map(|AuthenticateData(_, v)| v.sub)?;
-
-            let Some(ctx) = req.app_data::<web::Data<crate::AppContext>>() else {
-                return Err(actix_web::error::ErrorExpectationFailed("app context missing"));
-            };
-
-            //check if user is allowed on transaction
-            let check = crate::model::users::db_check_authorization(
-                &user_id,
-                app_code,
-                method_code,
-                &ctx,
-                std::time::Duration::from_secs(10),
-            )
-            .await?;
-
-            if !check {
-                return Err(actix_web::error::ErrorUnauthorized("insufficient rights"));
-            }
-
-            //go further through the call chain
-            let req = ServiceRequest::from_parts(req, payload);
-            let res = svc.call(req).await?;
-            Ok(res)
-        })
-    }
-}
-
-pub struct AuthorizeFactory {
-    app_code: &'static str,
-    method_code: &'static str,
-}
-
-impl AuthorizeFactory {
-    pub fn new(app_code: &'static str, method_code: &'static str) -> Self {
-        Self {
-            app_code,
-            method_code,
-        }
-    }
-}
-
-impl<S: 'static, B> Transform<S, ServiceRequest> for AuthorizeFactory
-where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
-    S::Future: 'static,
-    B: 'static,
-{
-    type Response = ServiceResponse<B>;
-    type Error = Error;
-    type InitError = ();
-    type Transform = AuthorizeMiddleware<S>;
-    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
-
-    fn new_transform(&self, service: S) -> Self::Future {
-        std::future::ready(Ok(AuthorizeMiddleware {
-            app_code: self.app_code,
-            method_code: self.method_code,
-            service: std::rc::Rc::new(service),
-        }))
-    }
-}
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, FromRequest, HttpMessage,
+};
+use futures::future::LocalBoxFuture;
+
+use crate::{
+    extractors::auth::AuthenticateData,
+    model::{
+        audit::{db_audit_record, AuthAudit, AuthEvent, AuthOutcome},
+        users::AuthorizedMethod,
+    },
+};
+
+pub struct AuthenticateMiddleware<S> {
+    service: std::rc::Rc<S>,
+}
+
+impl<S: 'static, B> Service<ServiceRequest> for AuthenticateMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+
+        Box::pin(async move {
+            let (req, mut payload) = req.into_parts();
+            let Some(ctx) = req.app_data::<web::Data<crate::AppContext>>() else {
+                return Err(actix_web::error::ErrorExpectationFailed("app context missing"));
+            };
+            let auth_data =
+                match crate::extractors::auth::AuthenticateData::from_request(&req, &mut payload)
+                    .await
+                {
+                    Ok(v) => v,
+                    Err(e) => {
+                        //a missing token is not audited, only the rejected ones (expired, bad signature)
+                        if AuthenticateData::get_token(&req).is_some() {
+                            db_audit_record(
+                                AuthAudit::new(
+                                    &req,
+                                    None,
+                                    AuthEvent::TokenRejected,
+                                    AuthOutcome::Failure,
+                                    Some(e.to_string()),
+                                ),
+                                ctx,
+                            )
+                            .await;
+                        }
+                        return Err(e);
+                    }
+                };
+
+            //check that is the last token
+            let last_token = crate::model::users::db_get_last_token_id(
+                &auth_data.1.sub,
+                ctx,
+                std::time::Duration::from_secs(10),
+            )
+            .await?;
+            let rejected = match last_token {
+                None => Some("no active token registration"),
+                Some(v) if v.token_id.ne(&auth_data.1.jti) => Some("invalid token"),
+                Some(_) => None,
+            };
+            if let Some(reason) = rejected {
+                db_audit_record(
+                    AuthAudit::new(
+                        &req,
+                        Some(&auth_data.1.sub),
+                        AuthEvent::TokenRejected,
+                        AuthOutcome::Failure,
+                        Some(format!("{}, token id {}", reason, auth_data.1.jti)),
+                    ),
+                    ctx,
+                )
+                .await;
+                return Err(actix_web::error::ErrorForbidden(reason));
+            }
+
+            //go further through the call chain
+            req.extensions_mut().insert(auth_data);
+            let req = ServiceRequest::from_parts(req, payload);
+
+            let res = svc.call(req).await?;
+            Ok(res)
+        })
+    }
+}
+
+pub struct AuthenticateFactory;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for AuthenticateFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticateMiddleware<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(AuthenticateMiddleware {
+            service: std::rc::Rc::new(service),
+        }))
+    }
+}
+
+/// error body returned for sensitive methods when the last authentication is too old
+pub const STEP_UP_REQUIRED: &str = "step-up-required";
+
+/// method codes a route requires, all evaluated against the same app code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthRule {
+    AnyOf(Vec<&'static str>),
+    AllOf(Vec<&'static str>),
+}
+
+impl AuthRule {
+    pub fn method_codes(&self) -> &[&'static str] {
+        match self {
+            Self::AnyOf(v) | Self::AllOf(v) => v,
+        }
+    }
+
+    /// returns the description of what is missing, if the granted methods don't satisfy the rule
+    pub fn missing(&self, granted: &[AuthorizedMethod]) -> Option<String> {
+        let is_granted = |v: &str| granted.iter().any(|g| g.method_code == v);
+        match self {
+            Self::AnyOf(v) => {
+                if v.iter().any(|c| is_granted(c)) {
+                    None
+                } else {
+                    Some(format!("one of [{}]", v.join(", ")))
+                }
+            }
+            Self::AllOf(v) => {
+                let missing: Vec<&str> = v.iter().filter(|c| !is_granted(c)).copied().collect();
+                if missing.is_empty() {
+                    None
+                } else {
+                    Some(format!("all of [{}]", missing.join(", ")))
+                }
+            }
+        }
+    }
+
+    /// for all-of every method is used, so any sensitive one requires the step-up;
+    /// for any-of only if there is no granted alternative that is not sensitive
+    pub fn requires_step_up(&self, granted: &[AuthorizedMethod]) -> bool {
+        let used: Vec<&AuthorizedMethod> = granted
+            .iter()
+            .filter(|g| self.method_codes().contains(&g.method_code.as_str()))
+            .collect();
+        match self {
+            Self::AnyOf(_) => !used.is_empty() && used.iter().all(|g| g.sensitive),
+            Self::AllOf(_) => used.iter().any(|g| g.sensitive),
+        }
+    }
+}
+
+pub struct AuthorizeMiddleware<S> {
+    app_code: &'static str,
+    rule: AuthRule,
+    service: std::rc::Rc<S>,
+}
+
+impl<S: 'static, B> Service<ServiceRequest> for AuthorizeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let svc = self.service.clone();
+        let app_code = self.app_code;
+        let rule = self.rule.clone();
+
+        Box::pin(async move {
+            let (req, mut payload) = req.into_parts();
+            let claims =
+                crate::extractors::auth::AuthenticateData::from_request(&req, &mut payload)
+                    .await
+                    .map(|AuthenticateData(_, v)| v)?;
+
+            let Some(ctx) = req.app_data::<web::Data<crate::AppContext>>() else {
+                return Err(actix_web::error::ErrorExpectationFailed("app context missing"));
+            };
+
+            //check which of the rule's methods the user is allowed on
+            let granted = crate::model::users::db_get_authorized_methods(
+                &claims.sub,
+                app_code,
+                rule.method_codes(),
+                ctx,
+                std::time::Duration::from_secs(10),
+            )
+            .await?;
+
+            if let Some(missing) = rule.missing(&granted) {
+                return Err(actix_web::error::ErrorUnauthorized(format!(
+                    "insufficient rights, missing {} for app code '{}'",
+                    missing, app_code
+                )));
+            }
+
+            //sensitive methods need a recent authentication, the frontend starts the step-up on this error
+            if rule.requires_step_up(&granted)
+                && !claims.is_recent_auth(ctx.general.auth_stepup_minutes)
+            {
+                return Err(actix_web::error::ErrorForbidden(STEP_UP_REQUIRED));
+            }
+
+            //go further through the call chain
+            let req = ServiceRequest::from_parts(req, payload);
+            let res = svc.call(req).await?;
+            Ok(res)
+        })
+    }
+}
+
+pub struct AuthorizeFactory {
+    app_code: &'static str,
+    rule: AuthRule,
+}
+
+impl AuthorizeFactory {
+    pub fn new(app_code: &'static str, method_code: &'static str) -> Self {
+        Self {
+            app_code,
+            rule: AuthRule::AllOf(vec![method_code]),
+        }
+    }
+
+    /// user needs at least one of the method codes
+    pub fn any_of(app_code: &'static str, method_codes: &[&'static str]) -> Self {
+        Self {
+            app_code,
+            rule: AuthRule::AnyOf(method_codes.to_vec()),
+        }
+    }
+
+    /// user needs every one of the method codes
+    pub fn all_of(app_code: &'static str, method_codes: &[&'static str]) -> Self {
+        Self {
+            app_code,
+            rule: AuthRule::AllOf(method_codes.to_vec()),
+        }
+    }
+}
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for AuthorizeFactory
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthorizeMiddleware<S>;
+    type Future = std::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        std::future::ready(Ok(AuthorizeMiddleware {
+            app_code: self.app_code,
+            rule: self.rule.clone(),
+            service: std::rc::Rc::new(service),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AuthRule;
+    use crate::model::users::AuthorizedMethod;
+
+    fn method(method_code: &str, sensitive: bool) -> AuthorizedMethod {
+        AuthorizedMethod {
+            method_code: method_code.into(),
+            sensitive,
+        }
+    }
+
+    #[test]
+    fn auth_rule_missing() {
+        let granted = vec![method("app_method_list_all", false)];
+
+        let rule = AuthRule::AnyOf(vec!["app_method_get_single_by_id", "app_method_list_all"]);
+        assert_eq!(None, rule.missing(&granted));
+
+        let rule = AuthRule::AllOf(vec!["app_method_list_all", "app_method_export"]);
+        assert_eq!(
+            Some("all of [app_method_export]".to_string()),
+            rule.missing(&granted)
+        );
+
+        let rule = AuthRule::AnyOf(vec!["user_single_get", "user_all_list"]);
+        assert_eq!(
+            Some("one of [user_single_get, user_all_list]".to_string()),
+            rule.missing(&[])
+        );
+    }
+
+    #[test]
+    fn auth_rule_step_up() {
+        let granted = vec![
+            method("app_method_list_all", false),
+            method("app_method_del_single_by_id", true),
+        ];
+
+        let rule = AuthRule::AllOf(vec!["app_method_del_single_by_id"]);
+        assert!(rule.requires_step_up(&granted));
+
+        let rule = AuthRule::AnyOf(vec!["app_method_del_single_by_id", "app_method_list_all"]);
+        assert!(!rule.requires_step_up(&granted));
+
+        let rule = AuthRule::AllOf(vec!["app_method_list_all"]);
+        assert!(!rule.requires_step_up(&granted));
+    }
+}
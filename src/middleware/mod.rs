@@ -1,2 +1,2 @@
-pub mod auth;
-pub mod logger;
+pub mod auth;
+pub mod logger;
@@ -0,0 +1,318 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+/// message from the admins to all the users of the `groups`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Announcement {
+    pub id: Option<uuid::Uuid>,
+    pub title: String,
+    pub message: String, // plain text
+    pub groups: Vec<String>,
+    pub mod_de: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::Row> for Announcement {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            title: row.try_get("title")?,
+            message: row.try_get("message")?,
+            groups: row.try_get("groups")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// announcement as seen by one of its users
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct UserAnnouncement {
+    pub id: uuid::Uuid,
+    pub title: String,
+    pub message: String,
+    pub mod_de: String,
+    pub mod_timp: chrono::NaiveDateTime,
+    pub read_timp: Option<chrono::NaiveDateTime>, // `None` while unread
+}
+
+impl TryFrom<tokio_postgres::Row> for UserAnnouncement {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            title: row.try_get("title")?,
+            message: row.try_get("message")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+            read_timp: row.try_get("read_timp")?,
+        })
+    }
+}
+
+/// user targeted by an announcement, with the preferred mail language
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Recipient {
+    pub user_id: String,
+    pub language: Option<String>,
+}
+
+impl TryFrom<tokio_postgres::Row> for Recipient {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user_id: row.try_get("user_id")?,
+            language: row.try_get("language")?,
+        })
+    }
+}
+
+/// announcement mail rendered in one language, for the users of that language
+#[derive(Debug, Clone)]
+pub struct AnnouncementMail {
+    pub user_ids: Vec<String>,
+    pub mail: crate::mail::MailContent,
+}
+
+/// saves the announcement and queues its `mails` in the same statement, so both or none are
+/// stored; returns `None` if any of the groups doesn't exist
+pub async fn db_announcement_insert(
+    announcement: &Announcement,
+    mod_de: &str,
+    mails: &[AnnouncementMail],
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Announcement>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_announcement_insert.sql")?;
+    //a row for each user with the (1 based) index of its mail, then the mails
+    let (user_ids, mail_idxs): (Vec<&str>, Vec<i64>) = mails
+        .iter()
+        .enumerate()
+        .flat_map(|(i, v)| v.user_ids.iter().map(move |u| (u.as_str(), i as i64 + 1)))
+        .unzip();
+    let subjects: Vec<&str> = mails.iter().map(|v| v.mail.subject.as_str()).collect();
+    let htmls: Vec<&str> = mails.iter().map(|v| v.mail.html.as_str()).collect();
+    let texts: Vec<Option<&str>> = mails.iter().map(|v| v.mail.text.as_deref()).collect();
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::INT8_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &announcement.title,
+        &announcement.message,
+        &announcement.groups,
+        &mod_de,
+        &user_ids,
+        &mail_idxs,
+        &subjects,
+        &htmls,
+        &texts,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Announcement> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// users belonging to any of the `groups`
+pub async fn db_announcement_get_recipients(
+    groups: &[String],
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Recipient>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_announcement_get_recipients.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::TEXT_ARRAY];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&groups];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Recipient> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// last 1000 announcements for the groups of `user_id`, newest first
+pub async fn db_announcement_get_for_user(
+    user_id: &str,
+    unread_only: bool,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<UserAnnouncement>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_announcement_get_for_user.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::TEXT, postgres_types::Type::BOOL];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&user_id, &unread_only];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<UserAnnouncement> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// returns 0 if the announcement doesn't exist or isn't meant for `user_id`;
+/// marking it again keeps the first read time
+pub async fn db_announcement_mark_read(
+    id: &uuid::Uuid,
+    user_id: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_announcement_mark_read.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &user_id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn announcement_read_state() {
+        let ctx = crate::init_app_data().unwrap();
+        let announcement = super::Announcement {
+            id: None,
+            title: "test".into(),
+            message: "test message".into(),
+            groups: vec!["cdg_admin".into()],
+            mod_de: None,
+            mod_timp: None,
+        };
+        let mails = vec![super::AnnouncementMail {
+            user_ids: vec!["catalin".into()],
+            mail: crate::mail::MailContent {
+                subject: "test announcement mail".into(),
+                html: "<span>test message</span>".into(),
+                text: None,
+            },
+        }];
+        let res = super::db_announcement_insert(
+            &announcement,
+            "catalin",
+            &mails,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        let id = res.id.unwrap();
+
+        let queued = crate::model::mail_outbox::db_outbox_get_filtered(
+            None,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(queued
+            .iter()
+            .any(|v| v.subject == "test announcement mail"
+                && v.user_id.as_deref() == Some("catalin")));
+
+        let recipients = super::db_announcement_get_recipients(
+            &announcement.groups,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(recipients.iter().any(|v| v.user_id == "catalin"));
+
+        let unread = super::db_announcement_get_for_user(
+            "catalin",
+            true,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(unread.iter().any(|v| v.id == id));
+
+        let res = super::db_announcement_mark_read(
+            &id,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, res);
+
+        let unread = super::db_announcement_get_for_user(
+            "catalin",
+            true,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(!unread.iter().any(|v| v.id == id));
+
+        let unknown = super::Announcement {
+            groups: vec!["no_such_group".into()],
+            ..announcement
+        };
+        let res = super::db_announcement_insert(
+            &unknown,
+            "catalin",
+            &[],
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res.is_none());
+    }
+}
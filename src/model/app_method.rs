@@ -1,718 +1,988 @@
-use actix_web::web;
-use dbpool::generics::{ColumnDefault, GenericWrapper};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, time::Duration};
-
-use crate::AppContext;
-
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
-pub struct AppMethod {
-    pub id: Option<uuid::Uuid>,
-    pub app_code: String,
-    pub method_code: String,
-    pub descr: String,
-    pub mod_de: Option<String>,
-    pub mod_timp: Option<chrono::NaiveDateTime>,
-}
-
-impl TryFrom<tokio_postgres::row::Row> for AppMethod {
-    type Error = dbpool::error::ErrorReport;
-
-    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
-        Ok(Self {
-            id: row.try_get("id")?,
-            app_code: row.try_get("app_code")?,
-            method_code: row.try_get("method_code")?,
-            descr: row.try_get("descr")?,
-            mod_de: row.try_get("mod_de")?,
-            mod_timp: row.try_get("mod_timp")?,
-        })
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
-pub struct AppCode {
-    pub app_code: String,
-}
-
-impl TryFrom<tokio_postgres::Row> for AppCode {
-    type Error = dbpool::error::ErrorReport;
-
-    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
-        Ok(Self {
-            app_code: row.try_get("app_code")?,
-        })
-    }
-}
-
-pub async fn db_get_app_code_list(
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<Vec<AppCode>, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_app_code_list.sql")?;
-
-    let callable =
-        |conn| async move { dbpool::pgsql::connection_get(&conn, sql.as_str(), None, None).await };
-
-    let res: Vec<AppCode> = db
-        .conn_get(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_get_methods_all(
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<Vec<AppMethod>, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
-    let callable =
-        |conn| async move { dbpool::pgsql::connection_get(&conn, sql.as_str(), None, None).await };
-
-    let res: Vec<AppMethod> = db
-        .conn_get(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_get_methods_by_app_code(
-    app_zone: &AppCode,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<Vec<AppMethod>, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_method_list_for_app_code.sql")?;
-    let param_types = &[postgres_types::Type::TEXT];
-    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_zone.app_code];
-
-    let callable = |conn| async move {
-        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
-            .await
-    };
-
-    let res: Vec<AppMethod> = db
-        .conn_get(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_methods_all_down_xlsx(
-    mod_de: &str,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<std::path::PathBuf, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let temp_dir = &ctx.general.temp_dir;
-    let file_path = temp_dir.join(&format!("d-{}-{}.xlsx", mod_de, uuid::Uuid::new_v4()));
-    let file_path_ref = file_path.as_path();
-    if file_path_ref.exists() {
-        std::fs::remove_file(file_path_ref)?;
-    }
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
-
-    let callable = |conn| async move {
-        dbpool::pgsql::download_to_xlsx(
-            &conn,
-            sql.as_str(),
-            None,
-            None,
-            file_path_ref,
-            Some("DATA"),
-        )
-        .await
-    };
-
-    let _ = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(file_path)
-}
-
-pub async fn db_methods_all_up_xlsx(
-    mod_de: &str,
-    file_path: &std::path::Path,
-    sheet_name: Option<&str>,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<usize, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let batch_size = db.get_batch_size();
-    if !file_path.exists() {
-        return Err(actix_web::error::ErrorExpectationFailed(
-            "upload file not found",
-        ));
-    }
-
-    let upsert_constraint = &["app_code", "method_code"];
-    let mut restricted_cols: HashMap<String, ColumnDefault<GenericWrapper>> = HashMap::new();
-    restricted_cols.insert("id".into(), ColumnDefault::ByDatabase);
-    restricted_cols.insert("mod_de".into(), ColumnDefault::Value(mod_de.into()));
-    restricted_cols.insert(
-        "mod_timp".into(),
-        ColumnDefault::Formula("current_timestamp"),
-    );
-
-    let callable = |mut conn| async move {
-        dbpool::pgsql::upload_from_xlsx_file(
-            &mut conn,
-            batch_size,
-            "portal",
-            "tbl_int_app_transactions",
-            Some(upsert_constraint),
-            Some(&restricted_cols),
-            file_path,
-            sheet_name,
-        )
-        .await
-    };
-
-    let res = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_methods_all_down_csv(
-    mod_de: &str,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<std::path::PathBuf, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let temp_dir = &ctx.general.temp_dir;
-    let file_path = temp_dir.join(&format!("d-{}-{}.csv", mod_de, uuid::Uuid::new_v4()));
-    let file_path_ref = file_path.as_path();
-    if file_path_ref.exists() {
-        std::fs::remove_file(file_path_ref)?;
-    }
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
-
-    let callable = |conn| async move {
-        dbpool::pgsql::download_to_csv(&conn, sql.as_str(), None, None, file_path_ref).await
-    };
-
-    let _ = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(file_path)
-}
-
-pub async fn db_methods_all_up_txt(
-    mod_de: &str,
-    file_path: &std::path::Path,
-    file_column_delimiter: u8,
-    file_column_quote_char: Option<u8>,
-    file_quote_char_escape: Option<u8>,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<usize, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let batch_size = db.get_batch_size();
-    if !file_path.exists() {
-        return Err(actix_web::error::ErrorExpectationFailed(
-            "upload file not found",
-        ));
-    }
-
-    let upsert_constraint = &["app_code", "method_code"];
-    let mut restricted_cols: HashMap<String, ColumnDefault<GenericWrapper>> = HashMap::new();
-    restricted_cols.insert("id".into(), ColumnDefault::ByDatabase);
-    restricted_cols.insert("mod_de".into(), ColumnDefault::Value(mod_de.into()));
-    restricted_cols.insert(
-        "mod_timp".into(),
-        ColumnDefault::Formula("current_timestamp"),
-    );
-
-    let callable = |mut conn| async move {
-        dbpool::pgsql::upload_from_text_file(
-            &mut conn,
-            batch_size,
-            "portal",
-            "tbl_int_app_transactions",
-            Some(upsert_constraint),
-            Some(&restricted_cols),
-            file_path,
-            file_column_delimiter,
-            file_column_quote_char,
-            file_quote_char_escape,
-        )
-        .await
-    };
-
-    let res = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_methods_by_app_code_down_xlsx(
-    app_zone: &AppCode,
-    mod_de: &str,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<std::path::PathBuf, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let temp_dir = &ctx.general.temp_dir;
-    let file_path = temp_dir.join(&format!("{}-{}.xlsx", mod_de, uuid::Uuid::new_v4()));
-    let file_path_ref = file_path.as_path();
-    if file_path_ref.exists() {
-        std::fs::remove_file(file_path_ref)?;
-    }
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_method_list_for_app_code.sql")?;
-    let param_types = &[postgres_types::Type::TEXT];
-    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_zone.app_code];
-
-    let callable = |conn| async move {
-        dbpool::pgsql::download_to_xlsx(
-            &conn,
-            sql.as_str(),
-            Some(param_types),
-            Some(param_values),
-            file_path_ref,
-            Some("DATA"),
-        )
-        .await
-    };
-
-    let _ = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(file_path)
-}
-
-pub async fn db_methods_by_app_code_up_xlsx(
-    app_zone: &AppCode,
-    mod_de: &str,
-    file_path: &std::path::Path,
-    sheet_name: Option<&str>,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<usize, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let batch_size = db.get_batch_size();
-    if !file_path.exists() {
-        return Err(actix_web::error::ErrorExpectationFailed(
-            "upload file not found",
-        ));
-    }
-
-    let upsert_constraint = &["app_code", "method_code"];
-    let mut restricted_cols: HashMap<String, ColumnDefault<GenericWrapper>> = HashMap::new();
-    restricted_cols.insert("id".into(), ColumnDefault::ByDatabase);
-    restricted_cols.insert(
-        "app_code".into(),
-        ColumnDefault::Value(GenericWrapper::from(app_zone.app_code.as_str())),
-    );
-    restricted_cols.insert("mod_de".into(), ColumnDefault::Value(mod_de.into()));
-    restricted_cols.insert(
-        "mod_timp".into(),
-        ColumnDefault::Formula("current_timestamp"),
-    );
-
-    let callable = |mut conn| async move {
-        dbpool::pgsql::upload_from_xlsx_file(
-            &mut conn,
-            batch_size,
-            "portal",
-            "tbl_int_app_transactions",
-            Some(upsert_constraint),
-            Some(&restricted_cols),
-            file_path,
-            sheet_name,
-        )
-        .await
-    };
-
-    let res = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_methods_by_app_code_down_csv(
-    app_zone: &AppCode,
-    mod_de: &str,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<std::path::PathBuf, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let temp_dir = &ctx.general.temp_dir;
-    let file_path = temp_dir.join(&format!("{}-{}.csv", mod_de, uuid::Uuid::new_v4()));
-    let file_path_ref = file_path.as_path();
-    if file_path_ref.exists() {
-        std::fs::remove_file(file_path_ref)?;
-    }
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
-    let param_types = &[postgres_types::Type::TEXT];
-    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_zone.app_code];
-
-    let callable = |conn| async move {
-        dbpool::pgsql::download_to_csv(
-            &conn,
-            sql.as_str(),
-            Some(param_types),
-            Some(param_values),
-            file_path_ref,
-        )
-        .await
-    };
-
-    let _ = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(file_path)
-}
-
-pub async fn db_methods_by_app_code_up_txt(
-    app_zone: &AppCode,
-    mod_de: &str,
-    file_path: &std::path::Path,
-    file_column_delimiter: u8,
-    file_column_quote_char: Option<u8>,
-    file_quote_char_escape: Option<u8>,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<usize, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let batch_size = db.get_batch_size();
-    if !file_path.exists() {
-        return Err(actix_web::error::ErrorExpectationFailed(
-            "upload file not found",
-        ));
-    }
-
-    let upsert_constraint = &["app_code", "method_code"];
-    let mut restricted_cols: HashMap<String, ColumnDefault<GenericWrapper>> = HashMap::new();
-    restricted_cols.insert("id".into(), ColumnDefault::ByDatabase);
-    restricted_cols.insert(
-        "app_code".into(),
-        ColumnDefault::Value(GenericWrapper::from(app_zone.app_code.as_str())),
-    );
-    restricted_cols.insert("mod_de".into(), ColumnDefault::Value(mod_de.into()));
-    restricted_cols.insert(
-        "mod_timp".into(),
-        ColumnDefault::Formula("current_timestamp"),
-    );
-
-    let callable = |mut conn| async move {
-        dbpool::pgsql::upload_from_text_file(
-            &mut conn,
-            batch_size,
-            "portal",
-            "tbl_int_app_transactions",
-            Some(upsert_constraint),
-            Some(&restricted_cols),
-            file_path,
-            file_column_delimiter,
-            file_column_quote_char,
-            file_quote_char_escape,
-        )
-        .await
-    };
-
-    let res = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_method_get_by_id(
-    id: &uuid::Uuid,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<Option<AppMethod>, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_single_get_by_id.sql")?;
-    let param_types = &[postgres_types::Type::UUID];
-    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
-
-    let callable = |conn| async move {
-        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
-            .await
-    };
-
-    let res: Vec<AppMethod> = db
-        .conn_get(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res.get(0).map(ToOwned::to_owned))
-}
-
-pub async fn db_method_delete_by_id(
-    id: &uuid::Uuid,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<usize, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_single_delete_by_id.sql")?;
-    let param_types = &[postgres_types::Type::UUID];
-    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
-
-    let callable = |conn| async move {
-        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
-            .await
-    };
-
-    let res = db
-        .conn_run(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res)
-}
-
-pub async fn db_method_single_upsert(
-    method: &AppMethod,
-    mod_de: &str,
-    ctx: &web::Data<AppContext>,
-    timeout: Duration,
-) -> Result<Option<AppMethod>, actix_web::Error> {
-    let db = &ctx.pgsql_pool;
-    let sql = ctx
-        .general
-        .get_sql("pgsql_api_app_mthd_single_upsert.sql")?;
-    let param_types = &[
-        postgres_types::Type::TEXT,
-        postgres_types::Type::TEXT,
-        postgres_types::Type::TEXT,
-        postgres_types::Type::TEXT,
-    ];
-    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
-        &method.app_code,
-        &method.method_code,
-        &method.descr,
-        &mod_de,
-    ];
-
-    let callable = |conn| async move {
-        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
-            .await
-    };
-
-    let res: Vec<AppMethod> = db
-        .conn_get(callable, timeout)
-        .await
-        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
-    Ok(res.get(0).map(ToOwned::to_owned))
-}
-
-#[cfg(test)]
-mod tests {
-    use super::AppCode;
-
-    #[actix_web::test]
-    async fn get_app_code_list() {
-        let ctx = crate::init_app_data().unwrap();
-        let res = super::db_get_app_code_list(&ctx, std::time::Duration::from_secs(10))
-            .await
-            .unwrap();
-        assert!(res.contains(&super::AppCode {
-            app_code: "portal".to_string()
-        }));
-    }
-
-    #[actix_web::test]
-    async fn methods_list_all() {
-        let ctx = crate::init_app_data().unwrap();
-        let res = super::db_get_methods_all(&ctx, std::time::Duration::from_secs(10))
-            .await
-            .unwrap();
-        assert!(res.len() > 0);
-    }
-
-    #[actix_web::test]
-    async fn methods_list_by_app_code() {
-        let ctx = crate::init_app_data().unwrap();
-        let app_zone = AppCode {
-            app_code: "portal".into(),
-        };
-        let res =
-            super::db_get_methods_by_app_code(&app_zone, &ctx, std::time::Duration::from_secs(10))
-                .await
-                .unwrap();
-        assert!(res.len() > 0);
-    }
-
-    #[actix_web::test]
-    async fn methods_all_xlsx() {
-        let ctx = crate::init_app_data().unwrap();
-        let res =
-            super::db_methods_all_down_xlsx("catalin", &ctx, std::time::Duration::from_secs(20))
-                .await
-                .unwrap();
-        assert!(res.exists());
-
-        let file_path = res.as_path();
-        let res = super::db_methods_all_up_xlsx(
-            "catalin",
-            file_path,
-            Some("DATA"),
-            &ctx,
-            std::time::Duration::from_secs(20),
-        )
-        .await
-        .unwrap();
-        assert!(res > 0);
-        std::fs::remove_file(file_path).unwrap();
-    }
-
-    #[actix_web::test]
-    async fn methods_all_csv() {
-        let ctx = crate::init_app_data().unwrap();
-        let res =
-            super::db_methods_all_down_csv("catalin", &ctx, std::time::Duration::from_secs(20))
-                .await
-                .unwrap();
-        assert!(res.exists());
-
-        let file_path = res.as_path();
-        let res = super::db_methods_all_up_txt(
-            "catalin",
-            file_path,
-            b',',
-            Some(b'"'),
-            Some(b'"'),
-            &ctx,
-            std::time::Duration::from_secs(20),
-        )
-        .await
-        .unwrap();
-        assert!(res > 0);
-        std::fs::remove_file(file_path).unwrap();
-    }
-
-    #[actix_web::test]
-    async fn methods_by_app_code_xlsx() {
-        let ctx = crate::init_app_data().unwrap();
-        let app_zone = super::AppCode {
-            app_code: "portal".into(),
-        };
-        let res = super::db_methods_by_app_code_down_xlsx(
-            &app_zone,
-            "catalin",
-            &ctx,
-            std::time::Duration::from_secs(20),
-        )
-        .await
-        .unwrap();
-        assert!(res.exists());
-
-        let file_path = res.as_path();
-        let res = super::db_methods_by_app_code_up_xlsx(
-            &app_zone,
-            "catalin",
-            file_path,
-            Some("DATA"),
-            &ctx,
-            std::time::Duration::from_secs(20),
-        )
-        .await
-        .unwrap();
-        assert!(res > 0);
-        std::fs::remove_file(file_path).unwrap();
-    }
-
-    #[actix_web::test]
-    async fn methods_by_app_code_csv() {
-        let ctx = crate::init_app_data().unwrap();
-        let app_zone = super::AppCode {
-            app_code: "portal".into(),
-        };
-        let res = super::db_methods_by_app_code_down_csv(
-            &app_zone,
-            "catalin",
-            &ctx,
-            std::time::Duration::from_secs(20),
-        )
-        .await
-        .unwrap();
-        assert!(res.exists());
-
-        let file_path = res.as_path();
-        let res = super::db_methods_by_app_code_up_txt(
-            &app_zone,
-            "catalin",
-            file_path,
-            b',',
-            Some(b'"'),
-            Some(b'"'),
-            &ctx,
-            std::time::Duration::from_secs(20),
-        )
-        .await
-        .unwrap();
-        assert!(res > 0);
-        std::fs::remove_file(file_path).unwrap();
-    }
-
-    #[actix_web::test]
-    async fn method_single() {
-        let ctx = crate::init_app_data().unwrap();
-        let mut method = super::AppMethod {
-            id: None,
-            app_code: "portal".into(),
-            method_code: "testare".into(),
-            descr: "testare".into(),
-            mod_de: None,
-            mod_timp: None,
-        };
-
-        method = super::db_method_single_upsert(
-            &method,
-            "catalin",
-            &ctx,
-            std::time::Duration::from_secs(10),
-        )
-        .await
-        .unwrap()
-        .unwrap();
-        assert!(method.id.is_some());
-
-        let res = super::db_method_get_by_id(
-            &method.id.unwrap(),
-            &ctx,
-            std::time::Duration::from_secs(10),
-        )
-        .await
-        .unwrap()
-        .unwrap();
-        assert_eq!(method.id, res.id);
-
-        let res = super::db_method_delete_by_id(
-            &method.id.unwrap(),
-            &ctx,
-            std::time::Duration::from_secs(10),
-        )
-        .await
-        .unwrap();
-        assert!(res > 0);
-    }
-}
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{model::webhook::WebhookEvent, AppContext};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AppMethod {
+    pub id: Option<uuid::Uuid>,
+    pub app_code: String,
+    pub method_code: String,
+    pub descr: String,
+    pub mod_de: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::row::Row> for AppMethod {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            app_code: row.try_get("app_code")?,
+            method_code: row.try_get("method_code")?,
+            descr: row.try_get("descr")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+impl AppMethod {
+    /// strong ETag from `mod_timp`, with microseconds like the db timestamp;
+    /// the sql files compare it as `to_char(mod_timp, 'YYYYMMDDHH24MISSUS')`
+    pub fn etag(&self) -> Option<actix_web::http::header::EntityTag> {
+        self.mod_timp.map(|v| {
+            actix_web::http::header::EntityTag::new_strong(v.format("%Y%m%d%H%M%S%6f").to_string())
+        })
+    }
+}
+
+/// list item with the ETag of the method, to be sent back in `If-Match`
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct AppMethodTagged {
+    #[serde(flatten)]
+    pub method: AppMethod,
+    pub etag: Option<String>,
+}
+
+impl From<AppMethod> for AppMethodTagged {
+    fn from(method: AppMethod) -> Self {
+        Self {
+            etag: method.etag().map(|v| v.to_string()),
+            method,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AppCode {
+    pub app_code: String,
+}
+
+impl TryFrom<tokio_postgres::Row> for AppCode {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            app_code: row.try_get("app_code")?,
+        })
+    }
+}
+
+/// one change of an app method row; `operation` is one of "insert", "update" or "delete",
+/// `before_data`/ `after_data` hold the whole row and are `None` for inserts/ deletes
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AppMethodHistory {
+    pub id: uuid::Uuid,
+    pub app_method_id: uuid::Uuid,
+    pub operation: String,
+    pub before_data: Option<serde_json::Value>,
+    pub after_data: Option<serde_json::Value>,
+    pub mod_de: String,
+    pub mod_timp: chrono::NaiveDateTime,
+}
+
+impl TryFrom<tokio_postgres::Row> for AppMethodHistory {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            app_method_id: row.try_get("app_method_id")?,
+            operation: row.try_get("operation")?,
+            before_data: row.try_get("before_data")?,
+            after_data: row.try_get("after_data")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// method of the synchronized app code that is missing from the upload file;
+/// deleted unless `grants` (user group authorizations) still reference it
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct MissingMethod {
+    pub id: uuid::Uuid,
+    pub app_code: String,
+    pub method_code: String,
+    pub grants: i64,
+}
+
+impl TryFrom<tokio_postgres::Row> for MissingMethod {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            app_code: row.try_get("app_code")?,
+            method_code: row.try_get("method_code")?,
+            grants: row.try_get("grants")?,
+        })
+    }
+}
+
+pub async fn db_get_app_code_list(
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppCode>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_app_code_list.sql")?;
+
+    let callable =
+        |conn| async move { dbpool::pgsql::connection_get(&conn, sql.as_str(), None, None).await };
+
+    let res: Vec<AppCode> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(res)
+}
+
+pub async fn db_get_methods_all(
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
+    let callable =
+        |conn| async move { dbpool::pgsql::connection_get(&conn, sql.as_str(), None, None).await };
+
+    let res: Vec<AppMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(res)
+}
+
+pub async fn db_get_methods_by_app_code(
+    app_zone: &AppCode,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_method_list_for_app_code.sql")?;
+    let param_types = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_zone.app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(res)
+}
+
+pub async fn db_methods_all_down_xlsx(
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<std::path::PathBuf, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let temp_dir = &ctx.general.temp_dir;
+    let file_path = temp_dir.join(&format!("d-{}-{}.xlsx", mod_de, uuid::Uuid::new_v4()));
+    let file_path_ref = file_path.as_path();
+    if file_path_ref.exists() {
+        std::fs::remove_file(file_path_ref)?;
+    }
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
+
+    let callable = |conn| async move {
+        dbpool::pgsql::download_to_xlsx(
+            &conn,
+            sql.as_str(),
+            None,
+            None,
+            file_path_ref,
+            Some("DATA"),
+        )
+        .await
+    };
+
+    let _ = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(file_path)
+}
+
+pub async fn db_methods_all_down_csv(
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<std::path::PathBuf, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let temp_dir = &ctx.general.temp_dir;
+    let file_path = temp_dir.join(&format!("d-{}-{}.csv", mod_de, uuid::Uuid::new_v4()));
+    let file_path_ref = file_path.as_path();
+    if file_path_ref.exists() {
+        std::fs::remove_file(file_path_ref)?;
+    }
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_method_all.sql")?;
+
+    let callable = |conn| async move {
+        dbpool::pgsql::download_to_csv(&conn, sql.as_str(), None, None, file_path_ref).await
+    };
+
+    let _ = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(file_path)
+}
+
+pub async fn db_methods_by_app_code_down_xlsx(
+    app_zone: &AppCode,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<std::path::PathBuf, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let temp_dir = &ctx.general.temp_dir;
+    let file_path = temp_dir.join(&format!("{}-{}.xlsx", mod_de, uuid::Uuid::new_v4()));
+    let file_path_ref = file_path.as_path();
+    if file_path_ref.exists() {
+        std::fs::remove_file(file_path_ref)?;
+    }
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_method_list_for_app_code.sql")?;
+    let param_types = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_zone.app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::download_to_xlsx(
+            &conn,
+            sql.as_str(),
+            Some(param_types),
+            Some(param_values),
+            file_path_ref,
+            Some("DATA"),
+        )
+        .await
+    };
+
+    let _ = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(file_path)
+}
+
+pub async fn db_methods_by_app_code_down_csv(
+    app_zone: &AppCode,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<std::path::PathBuf, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let temp_dir = &ctx.general.temp_dir;
+    let file_path = temp_dir.join(&format!("{}-{}.csv", mod_de, uuid::Uuid::new_v4()));
+    let file_path_ref = file_path.as_path();
+    if file_path_ref.exists() {
+        std::fs::remove_file(file_path_ref)?;
+    }
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_get_method_list_for_app_code.sql")?;
+    let param_types = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_zone.app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::download_to_csv(
+            &conn,
+            sql.as_str(),
+            Some(param_types),
+            Some(param_values),
+            file_path_ref,
+        )
+        .await
+    };
+
+    let _ = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(file_path)
+}
+
+pub async fn db_method_get_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<AppMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_single_get_by_id.sql")?;
+    let param_types = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    Ok(res.get(0).map(ToOwned::to_owned))
+}
+
+/// `mod_de` is recorded as the actor in the method history;
+/// with `if_match` (ETag values or "*") the method is deleted only if its current ETag is listed
+pub async fn db_method_delete_by_id(
+    id: &uuid::Uuid,
+    mod_de: &str,
+    if_match: Option<&[String]>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_single_delete_by_id.sql")?;
+    let param_types = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT_ARRAY,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &mod_de, &if_match];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    if res > 0 {
+        crate::webhook::publish(
+            WebhookEvent::AppMethodDeleted,
+            serde_json::json!({ "id": id }),
+            ctx,
+        )
+        .await;
+    }
+    Ok(res)
+}
+
+/// with `if_match` (ETag values or "*") only an existing method with a listed ETag is updated;
+/// returns `None` when that precondition fails
+pub async fn db_method_single_upsert(
+    method: &AppMethod,
+    mod_de: &str,
+    if_match: Option<&[String]>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<AppMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_single_upsert.sql")?;
+    let param_types = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT_ARRAY,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &method.app_code,
+        &method.method_code,
+        &method.descr,
+        &mod_de,
+        &if_match,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(|err| actix_web::error::ErrorExpectationFailed(err))?;
+    let res = res.get(0).map(ToOwned::to_owned);
+    if let Some(method) = &res {
+        crate::webhook::publish(
+            WebhookEvent::AppMethodUpserted,
+            serde_json::json!(method),
+            ctx,
+        )
+        .await;
+    }
+    Ok(res)
+}
+
+/// upserts the rows of a checked upload file in one statement, so they are saved all or none;
+/// the unchanged ones are left as they are (same ETag, no history), only the inserted and
+/// changed ones are counted. `app_code` is the upload scope, reported to the webhooks
+pub async fn db_methods_upsert_rows(
+    methods: &[AppMethod],
+    app_code: Option<&str>,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_app_mthd_upsert_rows.sql")?;
+    let app_codes: Vec<&str> = methods.iter().map(|v| v.app_code.as_str()).collect();
+    let method_codes: Vec<&str> = methods.iter().map(|v| v.method_code.as_str()).collect();
+    let descrs: Vec<&str> = methods.iter().map(|v| v.descr.as_str()).collect();
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&app_codes, &method_codes, &descrs, &mod_de];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    crate::webhook::publish(
+        WebhookEvent::AppMethodUploaded,
+        serde_json::json!({ "app_code": app_code, "rows": res }),
+        ctx,
+    )
+    .await;
+    Ok(res)
+}
+
+/// synchronizes `app_code` with the rows of a checked upload file in one statement:
+/// upserts the rows and deletes the methods of the app code missing from them, except the ones
+/// still granted to some group; answers with all the missing methods
+pub async fn db_methods_sync_rows(
+    methods: &[AppMethod],
+    app_code: &str,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<MissingMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_app_mthd_sync_rows.sql")?;
+    let app_codes: Vec<&str> = methods.iter().map(|v| v.app_code.as_str()).collect();
+    let method_codes: Vec<&str> = methods.iter().map(|v| v.method_code.as_str()).collect();
+    let descrs: Vec<&str> = methods.iter().map(|v| v.descr.as_str()).collect();
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&app_codes, &method_codes, &descrs, &app_code, &mod_de];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<MissingMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    crate::webhook::publish(
+        WebhookEvent::AppMethodUploaded,
+        serde_json::json!({
+            "app_code": Some(app_code),
+            "rows": methods.len(),
+            "deleted": res.iter().filter(|v| v.grants == 0).map(|v| v.id).collect::<Vec<_>>(),
+        }),
+        ctx,
+    )
+    .await;
+    Ok(res)
+}
+
+/// the methods of `app_code` missing from `methods`, what a sync upload would delete
+pub async fn db_methods_sync_get_missing(
+    methods: &[AppMethod],
+    app_code: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<MissingMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_sync_get_missing.sql")?;
+    let app_codes: Vec<&str> = methods.iter().map(|v| v.app_code.as_str()).collect();
+    let method_codes: Vec<&str> = methods.iter().map(|v| v.method_code.as_str()).collect();
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&app_codes, &method_codes, &app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<MissingMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// the methods among `methods` already in the db, matched by app and method code
+pub async fn db_methods_get_by_codes(
+    methods: &[AppMethod],
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppMethod>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_app_mthd_get_by_codes.sql")?;
+    let app_codes: Vec<&str> = methods.iter().map(|v| v.app_code.as_str()).collect();
+    let method_codes: Vec<&str> = methods.iter().map(|v| v.method_code.as_str()).collect();
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_codes, &method_codes];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppMethod> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// last 1000 changes of the method, newest first; still available after the method is deleted
+pub async fn db_method_history_get_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppMethodHistory>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_mthd_history_get_by_id.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppMethodHistory> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AppCode;
+
+    /// `mod_timp` and history length of a seeded method, which a re-upload must not touch
+    async fn seeded_method_state(
+        ctx: &actix_web::web::Data<crate::AppContext>,
+    ) -> (Option<chrono::NaiveDateTime>, usize) {
+        let method = super::db_get_methods_all(ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|v| v.app_code == "portal" && v.method_code == "app_method_history")
+            .unwrap();
+        let history = super::db_method_history_get_by_id(
+            &method.id.unwrap(),
+            ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        (method.mod_timp, history.len())
+    }
+
+    #[actix_web::test]
+    async fn get_app_code_list() {
+        let ctx = crate::init_app_data().unwrap();
+        let res = super::db_get_app_code_list(&ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(res.contains(&super::AppCode {
+            app_code: "portal".to_string()
+        }));
+    }
+
+    #[actix_web::test]
+    async fn methods_list_all() {
+        let ctx = crate::init_app_data().unwrap();
+        let res = super::db_get_methods_all(&ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(res.len() > 0);
+    }
+
+    #[actix_web::test]
+    async fn methods_list_by_app_code() {
+        let ctx = crate::init_app_data().unwrap();
+        let app_zone = AppCode {
+            app_code: "portal".into(),
+        };
+        let res =
+            super::db_get_methods_by_app_code(&app_zone, &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+        assert!(res.len() > 0);
+    }
+
+    #[actix_web::test]
+    async fn methods_all_xlsx() {
+        let ctx = crate::init_app_data().unwrap();
+        let before = seeded_method_state(&ctx).await;
+        let res =
+            super::db_methods_all_down_xlsx("catalin", &ctx, std::time::Duration::from_secs(20))
+                .await
+                .unwrap();
+        assert!(res.exists());
+
+        let file_path = res.as_path();
+        let format = crate::upload::UploadFormat::Xlsx {
+            sheet_name: Some("DATA".into()),
+        };
+        let res = crate::upload::import_file(
+            file_path,
+            &format,
+            &Default::default(),
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(20),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(res.saved && res.unchanged > 0);
+        assert_eq!(before, seeded_method_state(&ctx).await);
+        std::fs::remove_file(file_path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn methods_all_csv() {
+        let ctx = crate::init_app_data().unwrap();
+        let before = seeded_method_state(&ctx).await;
+        let res =
+            super::db_methods_all_down_csv("catalin", &ctx, std::time::Duration::from_secs(20))
+                .await
+                .unwrap();
+        assert!(res.exists());
+
+        let file_path = res.as_path();
+        let format = crate::upload::UploadFormat::Text {
+            delimiter: b',',
+            quote: Some(b'"'),
+            quote_escape: Some(b'"'),
+            encoding: None,
+        };
+        let res = crate::upload::import_file(
+            file_path,
+            &format,
+            &Default::default(),
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(20),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(res.saved && res.unchanged > 0);
+        assert_eq!(before, seeded_method_state(&ctx).await);
+        std::fs::remove_file(file_path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn methods_by_app_code_xlsx() {
+        let ctx = crate::init_app_data().unwrap();
+        let app_zone = super::AppCode {
+            app_code: "portal".into(),
+        };
+        let res = super::db_methods_by_app_code_down_xlsx(
+            &app_zone,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(20),
+        )
+        .await
+        .unwrap();
+        assert!(res.exists());
+
+        let file_path = res.as_path();
+        let format = crate::upload::UploadFormat::Xlsx {
+            sheet_name: Some("DATA".into()),
+        };
+        let options = crate::upload::ImportOptions {
+            app_code: Some(app_zone.app_code.clone()),
+            ..Default::default()
+        };
+        let res = crate::upload::import_file(
+            file_path,
+            &format,
+            &options,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(20),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(res.saved && res.unchanged > 0);
+        std::fs::remove_file(file_path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn methods_by_app_code_csv() {
+        let ctx = crate::init_app_data().unwrap();
+        let app_zone = super::AppCode {
+            app_code: "portal".into(),
+        };
+        let res = super::db_methods_by_app_code_down_csv(
+            &app_zone,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(20),
+        )
+        .await
+        .unwrap();
+        assert!(res.exists());
+        // only the methods of the app code, one line each after the header
+        let methods =
+            super::db_get_methods_by_app_code(&app_zone, &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+        let content = std::fs::read_to_string(&res).unwrap();
+        assert_eq!(methods.len() + 1, content.lines().count());
+
+        let file_path = res.as_path();
+        let format = crate::upload::UploadFormat::Text {
+            delimiter: b',',
+            quote: Some(b'"'),
+            quote_escape: Some(b'"'),
+            encoding: None,
+        };
+        let options = crate::upload::ImportOptions {
+            app_code: Some(app_zone.app_code.clone()),
+            ..Default::default()
+        };
+        let res = crate::upload::import_file(
+            file_path,
+            &format,
+            &options,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(20),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(res.saved && res.unchanged > 0);
+        std::fs::remove_file(file_path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn methods_sync_rows() {
+        let ctx = crate::init_app_data().unwrap();
+        let timeout = std::time::Duration::from_secs(10);
+        let app_code = format!(
+            "sync_test_{}",
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+        let other_code = format!("{}_other", app_code);
+        let method = |app_code: &str, method_code: &str| super::AppMethod {
+            id: None,
+            app_code: app_code.into(),
+            method_code: method_code.into(),
+            descr: format!("Sync test {}", method_code),
+            mod_de: None,
+            mod_timp: None,
+        };
+        let methods = vec![
+            method(&app_code, "kept"),
+            method(&app_code, "missing"),
+            method(&app_code, "granted"),
+            method(&other_code, "missing"),
+        ];
+        super::db_methods_upsert_rows(&methods, None, "catalin", &ctx, timeout)
+            .await
+            .unwrap();
+        let grant = crate::model::grant::db_grant_single_upsert(
+            &crate::model::grant::Grant {
+                id: None,
+                group_id: "cdg_controller".into(),
+                app_method_id: None,
+                app_code: app_code.clone(),
+                method_code: "granted".into(),
+                mod_de: None,
+                mod_timp: None,
+            },
+            "catalin",
+            &ctx,
+            timeout,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let res = super::db_methods_sync_rows(
+            &[method(&app_code, "kept")],
+            &app_code,
+            "catalin",
+            &ctx,
+            timeout,
+        )
+        .await
+        .unwrap();
+        let report = crate::upload::UploadReport::default().with_missing(res);
+        assert_eq!(1, report.deleted);
+        assert_eq!(
+            vec!["granted"],
+            report
+                .in_use
+                .iter()
+                .map(|v| v.method_code.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let codes = |app_code: String| {
+            let ctx = ctx.clone();
+            async move {
+                let mut res =
+                    super::db_get_methods_by_app_code(&AppCode { app_code }, &ctx, timeout)
+                        .await
+                        .unwrap()
+                        .into_iter()
+                        .map(|v| v.method_code)
+                        .collect::<Vec<_>>();
+                res.sort();
+                res
+            }
+        };
+        assert_eq!(vec!["granted", "kept"], codes(app_code.clone()).await);
+        assert_eq!(vec!["missing"], codes(other_code.clone()).await);
+
+        crate::model::grant::db_grant_delete_by_id(&grant.id.unwrap(), &ctx, timeout)
+            .await
+            .unwrap();
+        for app_code in [app_code, other_code] {
+            for v in super::db_get_methods_by_app_code(&AppCode { app_code }, &ctx, timeout)
+                .await
+                .unwrap()
+            {
+                super::db_method_delete_by_id(&v.id.unwrap(), "catalin", None, &ctx, timeout)
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn method_single() {
+        let ctx = crate::init_app_data().unwrap();
+        let mut method = super::AppMethod {
+            id: None,
+            app_code: "portal".into(),
+            method_code: "testare".into(),
+            descr: "testare".into(),
+            mod_de: None,
+            mod_timp: None,
+        };
+
+        method = super::db_method_single_upsert(
+            &method,
+            "catalin",
+            None,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(method.id.is_some());
+
+        // stale ETag, the method is left as it is
+        let res = super::db_method_single_upsert(
+            &method,
+            "catalin",
+            Some(&["20000101000000000000".to_string()]),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res.is_none());
+
+        let res = super::db_method_get_by_id(
+            &method.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(method.id, res.id);
+
+        let etag = res.etag().unwrap().tag().to_string();
+        let res = super::db_method_delete_by_id(
+            &method.id.unwrap(),
+            "catalin",
+            Some(&[etag]),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res > 0);
+
+        let res = super::db_method_history_get_by_id(
+            &method.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        let last = res.first().unwrap();
+        assert_eq!(
+            ("delete", "catalin"),
+            (last.operation.as_str(), last.mod_de.as_str())
+        );
+        assert!(last.before_data.is_some() && last.after_data.is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AppOwner {
+    pub id: Option<uuid::Uuid>,
+    pub app_code: String,
+    pub owner_type: String, // "user" or "group"
+    pub owner_id: String,
+    pub mod_de: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::Row> for AppOwner {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            app_code: row.try_get("app_code")?,
+            owner_type: row.try_get("owner_type")?,
+            owner_id: row.try_get("owner_id")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// checks if the user may administer methods and grants of `app_code`,
+/// either by holding `app_admin_all` or by owning the app code (directly or through a group);
+/// with no `app_code` only the global right is checked
+pub async fn db_check_app_scope(
+    user_id: &str,
+    app_code: Option<&str>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<bool, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_app_owner_scope_check.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::TEXT, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&user_id, &app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let rows: Vec<dbpool::generics::GenericSqlRow<String, dbpool::generics::GenericWrapper>> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+
+    let res = match rows.first() {
+        Some(m) => match m.as_ref().get_index(0) {
+            Some((_, dbpool::generics::GenericWrapper::Bool(v))) => *v,
+            _ => false,
+        },
+        None => false,
+    };
+    Ok(res)
+}
+
+pub async fn db_get_owners_all(
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppOwner>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_app_owner_get_all.sql")?;
+
+    let callable =
+        |conn| async move { dbpool::pgsql::connection_get(&conn, sql.as_str(), None, None).await };
+
+    let res: Vec<AppOwner> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_get_owners_by_app_code(
+    app_code: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AppOwner>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_owner_get_for_app_code.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppOwner> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// returns `None` if the owner (user or group) doesn't exist
+pub async fn db_owner_single_upsert(
+    owner: &AppOwner,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<AppOwner>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_owner_single_upsert.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&owner.app_code, &owner.owner_type, &owner.owner_id, &mod_de];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AppOwner> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+pub async fn db_owner_delete_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_app_owner_single_delete_by_id.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn check_app_scope() {
+        let ctx = crate::init_app_data().unwrap();
+        let res =
+            super::db_check_app_scope("catalin", None, &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+        assert!(res);
+    }
+
+    #[actix_web::test]
+    async fn owner_single() {
+        let ctx = crate::init_app_data().unwrap();
+        let owner = super::AppOwner {
+            id: None,
+            app_code: "testare".into(),
+            owner_type: "user".into(),
+            owner_id: "catalin".into(),
+            mod_de: None,
+            mod_timp: None,
+        };
+
+        let res = super::db_owner_single_upsert(
+            &owner,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(res.id.is_some());
+
+        let list =
+            super::db_get_owners_by_app_code("testare", &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+        assert!(list.iter().any(|v| v.id == res.id));
+
+        let res = super::db_owner_delete_by_id(
+            &res.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res > 0);
+    }
+}
@@ -0,0 +1,296 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthEvent {
+    LinkRequest,
+    LinkSent,
+    LinkFailed,
+    Authenticate,
+    Logout,
+    TokenRejected,
+    NewDeviceMail,
+    RevokeAll,
+}
+
+impl AuthEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LinkRequest => "link_request",
+            Self::LinkSent => "link_sent",
+            Self::LinkFailed => "link_failed",
+            Self::Authenticate => "authenticate",
+            Self::Logout => "logout",
+            Self::TokenRejected => "token_rejected",
+            Self::NewDeviceMail => "new_device_mail",
+            Self::RevokeAll => "revoke_all",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthOutcome {
+    Success,
+    Failure,
+}
+
+impl AuthOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+/// login/ authentication audit trail entry
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AuthAudit {
+    pub id: Option<uuid::Uuid>,
+    pub user_id: Option<String>,
+    pub event: String,
+    pub outcome: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl AuthAudit {
+    pub fn new(
+        req: &actix_web::HttpRequest,
+        user_id: Option<&str>,
+        event: AuthEvent,
+        outcome: AuthOutcome,
+        details: Option<String>,
+    ) -> Self {
+        let (ip, user_agent) = crate::helper::get_req_client_info(req);
+        Self {
+            id: None,
+            user_id: user_id.map(ToOwned::to_owned),
+            event: event.as_str().to_owned(),
+            outcome: outcome.as_str().to_owned(),
+            ip,
+            user_agent,
+            details,
+            mod_timp: None,
+        }
+    }
+
+    /// entry for events happening outside of a request (ex.: mail delivery), without client info
+    pub fn without_request(
+        user_id: Option<&str>,
+        event: AuthEvent,
+        outcome: AuthOutcome,
+        details: Option<String>,
+    ) -> Self {
+        Self {
+            id: None,
+            user_id: user_id.map(ToOwned::to_owned),
+            event: event.as_str().to_owned(),
+            outcome: outcome.as_str().to_owned(),
+            ip: None,
+            user_agent: None,
+            details,
+            mod_timp: None,
+        }
+    }
+}
+
+impl TryFrom<tokio_postgres::Row> for AuthAudit {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            event: row.try_get("event")?,
+            outcome: row.try_get("outcome")?,
+            ip: row.try_get("ip")?,
+            user_agent: row.try_get("user_agent")?,
+            details: row.try_get("details")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// audit trail query filters, all optional; `from`/ `to` are inclusive dates
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct AuthAuditFilter {
+    pub user_id: Option<String>,
+    pub from: Option<chrono::NaiveDate>,
+    pub to: Option<chrono::NaiveDate>,
+    pub outcome: Option<AuthOutcome>,
+    pub event: Option<AuthEvent>,
+}
+
+pub async fn db_audit_insert(
+    entry: &AuthAudit,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_audit_auth_insert.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &entry.user_id,
+        &entry.event,
+        &entry.outcome,
+        &entry.ip,
+        &entry.user_agent,
+        &entry.details,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// saves the audit entry; a failure is only logged so that auditing never breaks the audited request
+pub async fn db_audit_record(entry: AuthAudit, ctx: &web::Data<AppContext>) {
+    if let Err(err) = db_audit_insert(&entry, ctx, Duration::from_secs(10)).await {
+        log::error!("Auth audit {:?} -> {}", entry, err);
+    }
+}
+
+/// true if the user has previous sessions, but none from this ip with the same browser family and os
+pub async fn db_audit_is_new_device(
+    user_id: &str,
+    ip: Option<&str>,
+    user_agent: Option<&str>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<bool, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_audit_auth_new_device_check.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&user_id, &ip, &user_agent];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let rows: Vec<dbpool::generics::GenericSqlRow<String, dbpool::generics::GenericWrapper>> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+
+    let res = match rows.first() {
+        Some(m) => match m.as_ref().get_index(0) {
+            Some((_, dbpool::generics::GenericWrapper::Bool(v))) => *v,
+            _ => false,
+        },
+        None => false,
+    };
+    Ok(res)
+}
+
+pub async fn db_audit_get_filtered(
+    filter: &AuthAuditFilter,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<AuthAudit>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_audit_auth_get_filtered.sql")?;
+    let outcome = filter.outcome.map(|v| v.as_str());
+    let event = filter.event.map(|v| v.as_str());
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::DATE,
+        postgres_types::Type::DATE,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&filter.user_id, &filter.from, &filter.to, &outcome, &event];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<AuthAudit> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn audit_insert_and_filter() {
+        let ctx = crate::init_app_data().unwrap();
+        let req = actix_web::test::TestRequest::default()
+            .insert_header((actix_web::http::header::USER_AGENT, "audit-test"))
+            .to_http_request();
+        let entry = super::AuthAudit::new(
+            &req,
+            Some("catalin"),
+            super::AuthEvent::TokenRejected,
+            super::AuthOutcome::Failure,
+            Some("test entry".into()),
+        );
+        let res = super::db_audit_insert(&entry, &ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(res, 1);
+
+        let filter = super::AuthAuditFilter {
+            user_id: Some("catalin".into()),
+            from: Some(chrono::Utc::now().date_naive()),
+            outcome: Some(super::AuthOutcome::Failure),
+            event: Some(super::AuthEvent::TokenRejected),
+            ..Default::default()
+        };
+        let res = super::db_audit_get_filtered(&filter, &ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(res
+            .iter()
+            .any(|v| v.user_agent.as_deref() == Some("audit-test")));
+    }
+
+    #[actix_web::test]
+    async fn new_device_check() {
+        let ctx = crate::init_app_data().unwrap();
+        let res = super::db_audit_is_new_device(
+            "user-without-sessions",
+            Some("127.0.0.1"),
+            None,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(!res);
+    }
+}
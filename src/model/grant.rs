@@ -0,0 +1,207 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{model::webhook::WebhookEvent, AppContext};
+
+/// app method granted to a user group
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Grant {
+    pub id: Option<uuid::Uuid>,
+    pub group_id: String,
+    pub app_method_id: Option<uuid::Uuid>,
+    pub app_code: String,
+    pub method_code: String,
+    pub mod_de: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::Row> for Grant {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            group_id: row.try_get("group_id")?,
+            app_method_id: row.try_get("app_method_id")?,
+            app_code: row.try_get("app_code")?,
+            method_code: row.try_get("method_code")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+pub async fn db_get_grants_by_app_code(
+    app_code: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Grant>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_grant_get_for_app_code.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&app_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Grant> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_grant_get_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Grant>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_grant_single_get_by_id.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Grant> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// returns `None` if the method `app_code`/`method_code` doesn't exist
+pub async fn db_grant_single_upsert(
+    grant: &Grant,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Grant>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_grant_single_upsert.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &grant.group_id,
+        &grant.app_code,
+        &grant.method_code,
+        &mod_de,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Grant> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    let res = res.first().map(ToOwned::to_owned);
+    if let Some(grant) = &res {
+        crate::webhook::publish(WebhookEvent::GrantUpserted, serde_json::json!(grant), ctx).await;
+    }
+    Ok(res)
+}
+
+pub async fn db_grant_delete_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_grant_single_delete_by_id.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    if res > 0 {
+        crate::webhook::publish(
+            WebhookEvent::GrantDeleted,
+            serde_json::json!({ "id": id }),
+            ctx,
+        )
+        .await;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn grants_by_app_code() {
+        let ctx = crate::init_app_data().unwrap();
+        let res =
+            super::db_get_grants_by_app_code("portal", &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+        assert!(res
+            .iter()
+            .any(|v| v.group_id == "cdg_admin" && v.method_code == "app_admin_all"));
+    }
+
+    #[actix_web::test]
+    async fn grant_single() {
+        let ctx = crate::init_app_data().unwrap();
+        let grant = super::Grant {
+            id: None,
+            group_id: "cdg_controller".into(),
+            app_method_id: None,
+            app_code: "portal".into(),
+            method_code: "app_method_list_all".into(),
+            mod_de: None,
+            mod_timp: None,
+        };
+
+        let res = super::db_grant_single_upsert(
+            &grant,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(res.id.is_some());
+
+        let check =
+            super::db_grant_get_by_id(&res.id.unwrap(), &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(res.id, check.id);
+
+        let res = super::db_grant_delete_by_id(
+            &res.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res > 0);
+    }
+}
@@ -0,0 +1,486 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+/// work a background job does
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    AppMethodImport,
+    AppMethodExport,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AppMethodImport => "app_method_import",
+            Self::AppMethodExport => "app_method_export",
+        }
+    }
+
+    pub fn parse(v: &str) -> Option<Self> {
+        [Self::AppMethodImport, Self::AppMethodExport]
+            .into_iter()
+            .find(|k| k.as_str() == v)
+    }
+}
+
+/// background job; `status` is one of "queued", "running", "done", "failed" or "cancelled",
+/// `progress` goes from 0 to 100. The files are only named here, their content is a `JobFile`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Job {
+    pub id: uuid::Uuid,
+    pub kind: String,
+    pub params: serde_json::Value,
+    #[serde(default, skip_serializing)]
+    pub input_file: Option<String>,
+    pub status: String,
+    pub stage: Option<String>,
+    pub progress: i32,
+    pub cancel_requested: bool,
+    pub attempts: i32,
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing)]
+    pub result_file: Option<String>,
+    pub error: Option<String>,
+    pub heartbeat_timp: Option<chrono::NaiveDateTime>,
+    pub started_timp: Option<chrono::NaiveDateTime>,
+    pub finished_timp: Option<chrono::NaiveDateTime>,
+    pub mod_de: String,
+    pub created_timp: chrono::NaiveDateTime,
+    pub mod_timp: chrono::NaiveDateTime,
+}
+
+impl TryFrom<tokio_postgres::Row> for Job {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            kind: row.try_get("kind")?,
+            params: row.try_get("params")?,
+            input_file: row.try_get("input_file")?,
+            status: row.try_get("status")?,
+            stage: row.try_get("stage")?,
+            progress: row.try_get("progress")?,
+            cancel_requested: row.try_get("cancel_requested")?,
+            attempts: row.try_get("attempts")?,
+            result: row.try_get("result")?,
+            result_file: row.try_get("result_file")?,
+            error: row.try_get("error")?,
+            heartbeat_timp: row.try_get("heartbeat_timp")?,
+            started_timp: row.try_get("started_timp")?,
+            finished_timp: row.try_get("finished_timp")?,
+            mod_de: row.try_get("mod_de")?,
+            created_timp: row.try_get("created_timp")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// file of a job, kept in the db so any server can run the job or serve its result
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum JobFileKind {
+    Input,
+    Result,
+}
+
+impl JobFileKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Result => "result",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct JobFile {
+    pub job_id: uuid::Uuid,
+    pub kind: String,
+    pub content: Vec<u8>,
+    pub created_timp: chrono::NaiveDateTime,
+}
+
+impl TryFrom<tokio_postgres::Row> for JobFile {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            job_id: row.try_get("job_id")?,
+            kind: row.try_get("kind")?,
+            content: row.try_get("content")?,
+            created_timp: row.try_get("created_timp")?,
+        })
+    }
+}
+
+/// queues a job of `mod_de` with its input file, name and content, in one statement,
+/// so a worker never claims a job without its input
+pub async fn db_job_insert(
+    id: &uuid::Uuid,
+    kind: JobKind,
+    params: &serde_json::Value,
+    input_file: Option<(&str, &[u8])>,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_insert.sql")?;
+    let kind = kind.as_str();
+    let file_name = input_file.map(|v| v.0);
+    let content = input_file.map(|v| v.1);
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::JSONB,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::BYTEA,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&id, &kind, &params, &file_name, &mod_de, &content];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// takes the oldest queued job, or a running one without heartbeat for `stale_secs`
+/// (its server stopped), if it wasn't tried `max_attempts` times yet
+pub async fn db_job_claim(
+    stale_secs: i32,
+    max_attempts: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_claim.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::INT4, postgres_types::Type::INT4];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&stale_secs, &max_attempts];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// ends the running jobs without heartbeat for `stale_secs` that won't be claimed again:
+/// the cancelled ones and the ones tried `max_attempts` times
+pub async fn db_job_fail_stale(
+    stale_secs: i32,
+    max_attempts: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_fail_stale.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::INT4, postgres_types::Type::INT4];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&stale_secs, &max_attempts];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// progress of a running job, only for the run of claim `attempts`; `None` when the job is
+/// not running anymore or was claimed again meanwhile (this run lost its heartbeat)
+pub async fn db_job_heartbeat(
+    id: &uuid::Uuid,
+    attempts: i32,
+    stage: &str,
+    progress: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_heartbeat.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::INT4,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::INT4,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &attempts, &stage, &progress];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// ends the run of the claimed `job` (by its `attempts`), saving its result file, name and
+/// content, and dropping the input one; `None` when the job was claimed again meanwhile,
+/// the newer run owns it
+pub async fn db_job_finish(
+    job: &Job,
+    status: &str,
+    result: Option<&serde_json::Value>,
+    result_file: Option<(&str, &[u8])>,
+    error: Option<&str>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_finish.sql")?;
+    let file_name = result_file.map(|v| v.0);
+    let content = result_file.map(|v| v.1);
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::JSONB,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::BYTEA,
+        postgres_types::Type::INT4,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &job.id,
+        &status,
+        &result,
+        &file_name,
+        &error,
+        &content,
+        &job.attempts,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// a queued job is cancelled at once, a running one when it reaches its next stage;
+/// `None` when the job is not of `mod_de` or already finished
+pub async fn db_job_cancel(
+    id: &uuid::Uuid,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_cancel.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &mod_de];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// last 100 jobs of `mod_de`, newest first
+pub async fn db_job_get_for_user(
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_get_for_user.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&mod_de];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_job_get_by_id(
+    id: &uuid::Uuid,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_single_get_by_id.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &mod_de];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// removes the jobs finished more than `days` ago, their files going with them
+pub async fn db_job_delete_finished(
+    days: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Job>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_delete_finished.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::INT4];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&days];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Job> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_job_file_get(
+    job_id: &uuid::Uuid,
+    kind: JobFileKind,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<JobFile>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_job_file_get.sql")?;
+    let kind = kind.as_str();
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&job_id, &kind];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<JobFile> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.into_iter().next())
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn queued_job_cancels_at_once() {
+        let ctx = crate::init_app_data().unwrap();
+        let id = uuid::Uuid::new_v4();
+        let res = super::db_job_insert(
+            &id,
+            super::JobKind::AppMethodExport,
+            &serde_json::json!({ "format": "csv" }),
+            None,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!("queued", res.status);
+
+        let res = super::db_job_cancel(
+            &id,
+            "someone_else",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(None, res);
+
+        let res = super::db_job_cancel(&id, "catalin", &ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!("cancelled", res.status);
+        assert!(res.finished_timp.is_some());
+    }
+
+    #[actix_web::test]
+    async fn job_input_file_is_kept_with_the_job() {
+        let ctx = crate::init_app_data().unwrap();
+        let id = uuid::Uuid::new_v4();
+        let file_name = format!("{}{}.csv", crate::jobs::FILE_PREFIX, id);
+        let res = super::db_job_insert(
+            &id,
+            super::JobKind::AppMethodImport,
+            &serde_json::json!({}),
+            Some((file_name.as_str(), b"app_code;method_code".as_slice())),
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(Some(file_name), res.input_file);
+
+        let res = super::db_job_file_get(
+            &id,
+            super::JobFileKind::Input,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(b"app_code;method_code".to_vec(), res.content);
+
+        let res = super::db_job_cancel(&id, "catalin", &ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!("cancelled", res.status);
+    }
+}
@@ -0,0 +1,363 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+/// what the queued mail is about, used for the delivery audit of the login related mails
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MailKind {
+    Login,
+    StepUp,
+    NewDevice,
+    Announcement,
+}
+
+impl MailKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::StepUp => "step_up",
+            Self::NewDevice => "new_device",
+            Self::Announcement => "announcement",
+        }
+    }
+
+    pub fn parse(v: &str) -> Option<Self> {
+        [
+            Self::Login,
+            Self::StepUp,
+            Self::NewDevice,
+            Self::Announcement,
+        ]
+        .into_iter()
+        .find(|k| k.as_str() == v)
+    }
+}
+
+/// outbound mail queue entry; `status` is one of "pending", "sending", "sent" or "dead"
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct OutboxMail {
+    pub id: uuid::Uuid,
+    pub kind: String,
+    pub user_id: Option<String>,
+    pub to_addrs: Vec<String>,
+    pub subject: String,
+    pub html: String,
+    pub text_body: Option<String>,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt: chrono::NaiveDateTime,
+    pub last_error: Option<String>,
+    pub sent_timp: Option<chrono::NaiveDateTime>,
+    pub created_timp: chrono::NaiveDateTime,
+    pub mod_timp: chrono::NaiveDateTime,
+    pub link_token_id: Option<uuid::Uuid>, // sign-in link mails, the token is signed when sent
+    pub link_exp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::Row> for OutboxMail {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            kind: row.try_get("kind")?,
+            user_id: row.try_get("user_id")?,
+            to_addrs: row.try_get("to_addrs")?,
+            subject: row.try_get("subject")?,
+            html: row.try_get("html")?,
+            text_body: row.try_get("text_body")?,
+            status: row.try_get("status")?,
+            attempts: row.try_get("attempts")?,
+            next_attempt: row.try_get("next_attempt")?,
+            last_error: row.try_get("last_error")?,
+            sent_timp: row.try_get("sent_timp")?,
+            created_timp: row.try_get("created_timp")?,
+            mod_timp: row.try_get("mod_timp")?,
+            link_token_id: row.try_get("link_token_id")?,
+            link_exp: row.try_get("link_exp")?,
+        })
+    }
+}
+
+/// queue entry as listed by the admins, without the message: the mails may carry links
+/// that act on the user's behalf (ex.: the revoke link of the new sign-in mail)
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct OutboxMailInfo {
+    pub id: uuid::Uuid,
+    pub kind: String,
+    pub user_id: Option<String>,
+    pub to_addrs: Vec<String>,
+    pub subject: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt: chrono::NaiveDateTime,
+    pub last_error: Option<String>,
+    pub sent_timp: Option<chrono::NaiveDateTime>,
+    pub created_timp: chrono::NaiveDateTime,
+    pub mod_timp: chrono::NaiveDateTime,
+}
+
+impl TryFrom<tokio_postgres::Row> for OutboxMailInfo {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            kind: row.try_get("kind")?,
+            user_id: row.try_get("user_id")?,
+            to_addrs: row.try_get("to_addrs")?,
+            subject: row.try_get("subject")?,
+            status: row.try_get("status")?,
+            attempts: row.try_get("attempts")?,
+            next_attempt: row.try_get("next_attempt")?,
+            last_error: row.try_get("last_error")?,
+            sent_timp: row.try_get("sent_timp")?,
+            created_timp: row.try_get("created_timp")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// `link_token` is the id and expiry of the sign-in token for the login/ step-up mails,
+/// whose message has `crate::mail::LINK_TOKEN_MARK` in place of the token
+pub async fn db_outbox_enqueue(
+    kind: MailKind,
+    user_id: Option<&str>,
+    to_addrs: &[lettre::message::Mailbox],
+    mail: &crate::mail::MailContent,
+    link_token: Option<(uuid::Uuid, chrono::NaiveDateTime)>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<OutboxMail, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_mail_outbox_enqueue.sql")?;
+    let kind = kind.as_str();
+    let to_addrs: Vec<String> = to_addrs.iter().map(ToString::to_string).collect();
+    let link_token_id = link_token.map(|v| v.0);
+    let link_exp = link_token.map(|v| v.1);
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::UUID,
+        postgres_types::Type::TIMESTAMP,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &kind,
+        &user_id,
+        &to_addrs,
+        &mail.subject,
+        &mail.html,
+        &mail.text,
+        &link_token_id,
+        &link_exp,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let rows: Vec<OutboxMail> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    let Some(res) = rows.first().map(ToOwned::to_owned) else {
+        return Err(actix_web::error::ErrorExpectationFailed("could not queue mail"));
+    };
+    Ok(res)
+}
+
+/// marks up to `limit` due mails as "sending" and returns them; mails stuck in "sending"
+/// (worker stopped while sending) are picked up again after 10 minutes
+pub async fn db_outbox_claim(
+    limit: i64,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<OutboxMail>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_mail_outbox_claim.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::INT8];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&limit];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<OutboxMail> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_outbox_mark_sent(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_mail_outbox_mark_sent.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// schedules the next attempt after `backoff_secs * 2^attempts` seconds (max 6 hours),
+/// or moves the mail to "dead" once it reaches `max_attempts`
+pub async fn db_outbox_mark_failed(
+    id: &uuid::Uuid,
+    error: &str,
+    max_attempts: i32,
+    backoff_secs: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<OutboxMail>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_mail_outbox_mark_failed.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::INT4,
+        postgres_types::Type::INT4,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&id, &error, &max_attempts, &backoff_secs];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<OutboxMail> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// last 1000 queued mails, optionally filtered by status, without their message
+pub async fn db_outbox_get_filtered(
+    status: Option<&str>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<OutboxMailInfo>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_mail_outbox_get_filtered.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&status];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<OutboxMailInfo> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// puts a "dead" mail back in the queue; returns `None` for other statuses and for the
+/// sign-in link mails, whose links are useless once expired
+pub async fn db_outbox_resend(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<OutboxMailInfo>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_mail_outbox_resend.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<OutboxMailInfo> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn outbox_retry_and_dead() {
+        let ctx = crate::init_app_data().unwrap();
+        let to_addrs = vec!["Catalin <mail@example.com>".parse().unwrap()];
+        let mail = super::db_outbox_enqueue(
+            super::MailKind::NewDevice,
+            Some("catalin"),
+            &to_addrs,
+            &crate::mail::MailContent {
+                subject: "test".into(),
+                html: "<span>test</span>".into(),
+                text: None,
+            },
+            None,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!("pending", mail.status);
+
+        let res = super::db_outbox_mark_failed(
+            &mail.id,
+            "smtp down",
+            2,
+            30,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(("pending", 1), (res.status.as_str(), res.attempts));
+        assert!(res.next_attempt > mail.next_attempt);
+
+        let res = super::db_outbox_mark_failed(
+            &mail.id,
+            "smtp down",
+            2,
+            30,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!("dead", res.status);
+
+        let res = super::db_outbox_resend(&mail.id, &ctx, std::time::Duration::from_secs(10))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(("pending", 0), (res.status.as_str(), res.attempts));
+    }
+}
@@ -0,0 +1,200 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+/// mail message editable by admins, overrides the `<template_code>_<lang>.html` file template;
+/// `html` goes inside the mail layout, `text_body` is the optional plain text alternative
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct MailTemplate {
+    pub id: Option<uuid::Uuid>,
+    pub template_code: String,
+    pub lang: String,
+    pub subject: String,
+    pub html: String,
+    pub text_body: Option<String>,
+    pub mod_de: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::Row> for MailTemplate {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            template_code: row.try_get("template_code")?,
+            lang: row.try_get("lang")?,
+            subject: row.try_get("subject")?,
+            html: row.try_get("html")?,
+            text_body: row.try_get("text_body")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// all templates, optionally only the ones for `template_code`
+pub async fn db_mail_template_get_all(
+    template_code: Option<&str>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<MailTemplate>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_mail_template_get_all.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&template_code];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<MailTemplate> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_mail_template_get_single(
+    template_code: &str,
+    lang: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<MailTemplate>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_mail_template_single_get.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::TEXT, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&template_code, &lang];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<MailTemplate> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// insert or update by `template_code` and `lang`
+pub async fn db_mail_template_single_upsert(
+    template: &MailTemplate,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<MailTemplate, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_mail_template_single_upsert.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &template.template_code,
+        &template.lang,
+        &template.subject,
+        &template.html,
+        &template.text_body,
+        &mod_de,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let rows: Vec<MailTemplate> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    let Some(res) = rows.first().map(ToOwned::to_owned) else {
+        return Err(actix_web::error::ErrorExpectationFailed("could not save mail template"));
+    };
+    Ok(res)
+}
+
+pub async fn db_mail_template_delete_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_mail_template_single_delete_by_id.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn mail_template_single() {
+        let ctx = crate::init_app_data().unwrap();
+        let template = super::MailTemplate {
+            id: None,
+            template_code: "login".into(),
+            lang: "en".into(),
+            subject: "Portal CDG - sign in".into(),
+            html: "<span>Hello {{name}}, <a href=\"{{link}}\">sign in</a></span>".into(),
+            text_body: Some("Hello {{name}}, sign in: {{link}}".into()),
+            mod_de: None,
+            mod_timp: None,
+        };
+
+        let res = super::db_mail_template_single_upsert(
+            &template,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res.id.is_some());
+
+        let check = super::db_mail_template_get_single(
+            "login",
+            "en",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!((res.id, res.text_body), (check.id, check.text_body));
+
+        let res = super::db_mail_template_delete_by_id(
+            &res.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(res > 0);
+    }
+}
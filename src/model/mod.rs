@@ -1,2 +1,11 @@
-pub mod app_method;
-pub mod users;
+pub mod announcement;
+pub mod app_method;
+pub mod app_owner;
+pub mod audit;
+pub mod grant;
+pub mod job;
+pub mod mail_outbox;
+pub mod mail_template;
+pub mod notification;
+pub mod users;
+pub mod webhook;
@@ -0,0 +1,214 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+/// what the notification is about
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    UploadFinished,
+    AccessGranted,
+    Announcement,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UploadFinished => "upload_finished",
+            Self::AccessGranted => "access_granted",
+            Self::Announcement => "announcement",
+        }
+    }
+}
+
+/// in-app notification of one user
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Notification {
+    pub id: uuid::Uuid,
+    pub user_id: String,
+    pub kind: String,
+    pub title: String,
+    pub message: String,
+    pub read_timp: Option<chrono::NaiveDateTime>, // `None` while unread
+    pub mod_timp: chrono::NaiveDateTime,
+}
+
+impl TryFrom<tokio_postgres::Row> for Notification {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            kind: row.try_get("kind")?,
+            title: row.try_get("title")?,
+            message: row.try_get("message")?,
+            read_timp: row.try_get("read_timp")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// one notification for each of `user_ids` and for each user of `group_ids`
+pub async fn db_notification_insert(
+    kind: NotificationKind,
+    user_ids: &[String],
+    group_ids: &[String],
+    title: &str,
+    message: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Notification>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_notification_insert.sql")?;
+    let kind = kind.as_str();
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&user_ids, &group_ids, &kind, &title, &message];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Notification> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// last 1000 notifications of `user_id`, newest first
+pub async fn db_notification_get_for_user(
+    user_id: &str,
+    unread_only: bool,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Notification>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_notification_get_for_user.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::TEXT, postgres_types::Type::BOOL];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&user_id, &unread_only];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Notification> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// notifications announced by the db to the servers, see `crate::notify::run_listener`
+pub async fn db_notification_get_by_ids(
+    ids: &[uuid::Uuid],
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Notification>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_notification_get_by_ids.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID_ARRAY];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&ids];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Notification> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// returns 0 if the notification doesn't exist or belongs to another user
+pub async fn db_notification_mark_read(
+    id: &uuid::Uuid,
+    user_id: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_notification_mark_read.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &user_id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn notification_read_state() {
+        let ctx = crate::init_app_data().unwrap();
+        let res = super::db_notification_insert(
+            super::NotificationKind::AccessGranted,
+            &["catalin".to_string()],
+            &["cdg_admin".to_string()],
+            "test",
+            "test message",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        // user and group target overlap, one notification each user
+        assert_eq!(1, res.iter().filter(|v| v.user_id == "catalin").count());
+        let id = res.iter().find(|v| v.user_id == "catalin").unwrap().id;
+
+        let by_ids =
+            super::db_notification_get_by_ids(&[id], &ctx, std::time::Duration::from_secs(10))
+                .await
+                .unwrap();
+        assert_eq!(vec![id], by_ids.iter().map(|v| v.id).collect::<Vec<_>>());
+
+        let res = super::db_notification_mark_read(
+            &id,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, res);
+
+        let unread = super::db_notification_get_for_user(
+            "catalin",
+            true,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert!(!unread.iter().any(|v| v.id == id));
+    }
+}
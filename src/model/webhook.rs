@@ -0,0 +1,410 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::AppContext;
+
+/// change a webhook can subscribe to
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum WebhookEvent {
+    #[serde(rename = "app_method.upserted")]
+    AppMethodUpserted,
+    #[serde(rename = "app_method.deleted")]
+    AppMethodDeleted,
+    #[serde(rename = "app_method.uploaded")]
+    AppMethodUploaded,
+    #[serde(rename = "user.upserted")]
+    UserUpserted,
+    #[serde(rename = "grant.upserted")]
+    GrantUpserted,
+    #[serde(rename = "grant.deleted")]
+    GrantDeleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [Self; 6] = [
+        Self::AppMethodUpserted,
+        Self::AppMethodDeleted,
+        Self::AppMethodUploaded,
+        Self::UserUpserted,
+        Self::GrantUpserted,
+        Self::GrantDeleted,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AppMethodUpserted => "app_method.upserted",
+            Self::AppMethodDeleted => "app_method.deleted",
+            Self::AppMethodUploaded => "app_method.uploaded",
+            Self::UserUpserted => "user.upserted",
+            Self::GrantUpserted => "grant.upserted",
+            Self::GrantDeleted => "grant.deleted",
+        }
+    }
+
+    pub fn parse(v: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == v)
+    }
+}
+
+/// subscription of an external url to some of the `WebhookEvent`s;
+/// the secret is write only, left out on update it keeps the stored one
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Webhook {
+    pub id: Option<uuid::Uuid>,
+    pub url: String,
+    pub event_types: Vec<String>,
+    #[serde(default, skip_serializing)]
+    pub secret: Option<String>,
+    pub active: bool,
+    pub mod_de: Option<String>,
+    pub mod_timp: Option<chrono::NaiveDateTime>,
+}
+
+impl TryFrom<tokio_postgres::Row> for Webhook {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            url: row.try_get("url")?,
+            event_types: row.try_get("event_types")?,
+            secret: row.try_get("secret")?,
+            active: row.try_get("active")?,
+            mod_de: row.try_get("mod_de")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// delivery log entry; `status` is one of "pending", "sending", "delivered" or "dead"
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct WebhookDelivery {
+    pub id: uuid::Uuid,
+    pub webhook_id: uuid::Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt: chrono::NaiveDateTime,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_timp: Option<chrono::NaiveDateTime>,
+    pub created_timp: chrono::NaiveDateTime,
+    pub mod_timp: chrono::NaiveDateTime,
+}
+
+impl TryFrom<tokio_postgres::Row> for WebhookDelivery {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            webhook_id: row.try_get("webhook_id")?,
+            event_type: row.try_get("event_type")?,
+            payload: row.try_get("payload")?,
+            status: row.try_get("status")?,
+            attempts: row.try_get("attempts")?,
+            next_attempt: row.try_get("next_attempt")?,
+            response_status: row.try_get("response_status")?,
+            last_error: row.try_get("last_error")?,
+            delivered_timp: row.try_get("delivered_timp")?,
+            created_timp: row.try_get("created_timp")?,
+            mod_timp: row.try_get("mod_timp")?,
+        })
+    }
+}
+
+/// claimed delivery, with the target url and signing secret of its webhook
+#[derive(Debug, Clone)]
+pub struct ClaimedDelivery {
+    pub delivery: WebhookDelivery,
+    pub url: String,
+    pub secret: String,
+}
+
+impl TryFrom<tokio_postgres::Row> for ClaimedDelivery {
+    type Error = dbpool::error::ErrorReport;
+
+    fn try_from(row: tokio_postgres::Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            url: row.try_get("url")?,
+            secret: row.try_get("secret")?,
+            delivery: WebhookDelivery::try_from(row)?,
+        })
+    }
+}
+
+pub async fn db_webhook_get_all(
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<Webhook>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_webhook_get_all.sql")?;
+
+    let callable =
+        |conn| async move { dbpool::pgsql::connection_get(&conn, sql.as_str(), None, None).await };
+
+    let res: Vec<Webhook> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_webhook_single_upsert(
+    webhook: &Webhook,
+    mod_de: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<Webhook>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx.general.get_sql("pgsql_api_webhook_single_upsert.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::TEXT_ARRAY,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::BOOL,
+        postgres_types::Type::TEXT,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[
+        &webhook.id,
+        &webhook.url,
+        &webhook.event_types,
+        &webhook.secret,
+        &webhook.active,
+        &mod_de,
+    ];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<Webhook> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// the delivery log of the webhook is deleted with it
+pub async fn db_webhook_delete_by_id(
+    id: &uuid::Uuid,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_webhook_single_delete_by_id.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::UUID];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// queues one delivery for each active webhook subscribed to `event`;
+/// returns the number of queued deliveries
+pub async fn db_delivery_enqueue(
+    event: WebhookEvent,
+    payload: &serde_json::Value,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_webhook_delivery_enqueue.sql")?;
+    let event = event.as_str();
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::TEXT, postgres_types::Type::JSONB];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&event, &payload];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// marks up to `limit` due deliveries as "sending" and returns them; deliveries stuck in
+/// "sending" (worker stopped while posting) are picked up again after 10 minutes, the ones of
+/// inactive webhooks wait until the webhook is active again
+pub async fn db_delivery_claim(
+    limit: i64,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<ClaimedDelivery>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_webhook_delivery_claim.sql")?;
+    let param_types: &[postgres_types::Type] = &[postgres_types::Type::INT8];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&limit];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<ClaimedDelivery> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+pub async fn db_delivery_mark_delivered(
+    id: &uuid::Uuid,
+    response_status: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<usize, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_webhook_delivery_mark_delivered.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::INT4];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&id, &response_status];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_run(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res = db
+        .conn_run(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+/// schedules the next attempt after `backoff_secs * 2^attempts` seconds (max 6 hours),
+/// or moves the delivery to "dead" once it reaches `max_attempts`
+pub async fn db_delivery_mark_failed(
+    id: &uuid::Uuid,
+    response_status: Option<i32>,
+    error: &str,
+    max_attempts: i32,
+    backoff_secs: i32,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Option<WebhookDelivery>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_webhook_delivery_mark_failed.sql")?;
+    let param_types: &[postgres_types::Type] = &[
+        postgres_types::Type::UUID,
+        postgres_types::Type::INT4,
+        postgres_types::Type::TEXT,
+        postgres_types::Type::INT4,
+        postgres_types::Type::INT4,
+    ];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] =
+        &[&id, &response_status, &error, &max_attempts, &backoff_secs];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<WebhookDelivery> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res.first().map(ToOwned::to_owned))
+}
+
+/// last 1000 deliveries, optionally filtered by webhook and status
+pub async fn db_delivery_get_filtered(
+    webhook_id: Option<&uuid::Uuid>,
+    status: Option<&str>,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+) -> Result<Vec<WebhookDelivery>, actix_web::Error> {
+    let db = &ctx.pgsql_pool;
+    let sql = ctx
+        .general
+        .get_sql("pgsql_api_webhook_delivery_get_filtered.sql")?;
+    let param_types: &[postgres_types::Type] =
+        &[postgres_types::Type::UUID, postgres_types::Type::TEXT];
+    let param_values: &[&(dyn postgres_types::ToSql + Sync)] = &[&webhook_id, &status];
+
+    let callable = |conn| async move {
+        dbpool::pgsql::connection_get(&conn, sql.as_str(), Some(param_types), Some(param_values))
+            .await
+    };
+
+    let res: Vec<WebhookDelivery> = db
+        .conn_get(callable, timeout)
+        .await
+        .map_err(actix_web::error::ErrorExpectationFailed)?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    #[actix_web::test]
+    async fn webhook_keeps_secret() {
+        let ctx = crate::init_app_data().unwrap();
+        let mut webhook = super::Webhook {
+            id: None,
+            url: "http://127.0.0.1:9/hook".into(),
+            event_types: vec![super::WebhookEvent::UserUpserted.as_str().into()],
+            secret: Some("test_secret".into()),
+            active: false,
+            mod_de: None,
+            mod_timp: None,
+        };
+        let res = super::db_webhook_single_upsert(
+            &webhook,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(Some("test_secret".to_string()), res.secret);
+
+        webhook.id = res.id;
+        webhook.secret = None;
+        let res = super::db_webhook_single_upsert(
+            &webhook,
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(Some("test_secret".to_string()), res.secret);
+
+        let res = super::db_webhook_delete_by_id(
+            &res.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, res);
+    }
+}
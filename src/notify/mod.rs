@@ -0,0 +1,273 @@
+use actix_web::web;
+use futures::StreamExt;
+use futures_util::future::Either;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::{
+    model::notification::{Notification, NotificationKind},
+    AppContext,
+};
+
+/// comment line sent on idle SSE streams, so proxies keep them open and closed clients are noticed
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// notifications kept for slow SSE streams, older ones are dropped for them
+pub const CHANNEL_CAPACITY: usize = 256;
+/// postgres channel the db announces the ids of the new notifications on
+pub const DB_CHANNEL: &str = "portal_notification";
+/// wait before listening again on a lost db connection
+pub const LISTEN_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// live fan-out of new notifications to the SSE streams of this server instance, fed by
+/// `run_listener`; the notifications are stored in the db first, so a missed push is still
+/// in the inbox
+pub struct Notifier {
+    sender: broadcast::Sender<Notification>,
+    conn_string: String,
+}
+
+impl Notifier {
+    /// `conn_string` of the db connection listening for new notifications
+    pub fn new(conn_string: String) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+            conn_string,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.sender.subscribe()
+    }
+
+    /// no open streams is not an error
+    pub fn publish(&self, notification: Notification) {
+        let _ = self.sender.send(notification);
+    }
+}
+
+/// listens on `DB_CHANNEL` and pushes the notifications stored by any server to the streams
+/// of this one; a lost connection is opened again, the notifications meanwhile are only
+/// in the inbox
+pub async fn run_listener(ctx: web::Data<AppContext>) {
+    loop {
+        if let Err(err) = listen(&ctx).await {
+            log::error!("Notification listener -> {}", err);
+        }
+        tokio::time::sleep(LISTEN_RETRY_INTERVAL).await;
+    }
+}
+
+/// runs until the listening connection is lost
+async fn listen(
+    ctx: &web::Data<AppContext>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (client, mut connection) =
+        tokio_postgres::connect(&ctx.notifier.conn_string, tokio_postgres::NoTls).await?;
+    let (sender, receiver) = futures::channel::mpsc::unbounded();
+    // the connection has to be polled for the client to work, its messages are the announcements
+    actix_web::rt::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(tokio_postgres::AsyncMessage::Notification(v)) => {
+                    if sender.unbounded_send(v.payload().to_string()).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    log::error!("Notification listener connection -> {}", err);
+                    break;
+                }
+            }
+        }
+    });
+    client
+        .batch_execute(&format!("listen {}", DB_CHANNEL))
+        .await?;
+
+    // the announcements that came meanwhile are loaded together
+    let mut batches = receiver.ready_chunks(CHANNEL_CAPACITY);
+    while let Some(batch) = batches.next().await {
+        let ids = batch
+            .iter()
+            .filter_map(|v| v.parse().ok())
+            .collect::<Vec<uuid::Uuid>>();
+        match crate::model::notification::db_notification_get_by_ids(
+            &ids,
+            ctx,
+            Duration::from_secs(10),
+        )
+        .await
+        {
+            Ok(res) => res.into_iter().for_each(|v| ctx.notifier.publish(v)),
+            Err(err) => log::error!("Notification listener -> {}", err),
+        }
+    }
+    Err("connection closed".into())
+}
+
+/// stores a notification for `user_ids` and the users of `group_ids`; the db announces it,
+/// so it's pushed live by `run_listener` on every server.
+/// Errors are only logged, a notification never fails the action it reports
+pub async fn notify(
+    kind: NotificationKind,
+    user_ids: &[String],
+    group_ids: &[String],
+    title: &str,
+    message: &str,
+    ctx: &web::Data<AppContext>,
+) {
+    match crate::model::notification::db_notification_insert(
+        kind,
+        user_ids,
+        group_ids,
+        title,
+        message,
+        ctx,
+        Duration::from_secs(10),
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(err) => log::error!("Notification '{}' -> {}", kind.as_str(), err),
+    }
+}
+
+/// true while `token_id` is the last token of `user_id`, i.e. the session wasn't ended
+/// (logout, revoke, new sign-in); a failed check counts as ended, the client reconnects
+pub async fn is_session_active(
+    user_id: String,
+    token_id: uuid::Uuid,
+    ctx: web::Data<AppContext>,
+) -> bool {
+    match crate::model::users::db_get_last_token_id(&user_id, &ctx, Duration::from_secs(10)).await {
+        Ok(v) => v.is_some_and(|v| v.token_id == token_id),
+        Err(err) => {
+            log::error!(
+                "Notification stream session check for {} -> {}",
+                user_id,
+                err
+            );
+            false
+        }
+    }
+}
+
+/// `text/event-stream` body with the live notifications of `user_id`, as "notification" events;
+/// every `keep_alive` (`KEEP_ALIVE_INTERVAL` outside tests) `is_active` is checked and the
+/// stream ends once the session did. The ticks don't wait for the channel to be idle, so the
+/// notifications of other users can't delay them
+pub fn sse_stream<F, Fut>(
+    user_id: String,
+    receiver: broadcast::Receiver<Notification>,
+    is_active: F,
+    keep_alive: Duration,
+) -> impl futures::Stream<Item = Result<web::Bytes, actix_web::Error>>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let ticks = tokio::time::interval_at(tokio::time::Instant::now() + keep_alive, keep_alive);
+    futures::stream::unfold(
+        (user_id, receiver, is_active, ticks),
+        |(user_id, mut receiver, is_active, mut ticks)| async move {
+            loop {
+                let received = match futures_util::future::select(
+                    Box::pin(receiver.recv()),
+                    Box::pin(ticks.tick()),
+                )
+                .await
+                {
+                    Either::Left((v, _)) => Some(v),
+                    Either::Right(_) => None,
+                };
+                let event = match received {
+                    None if !is_active().await => return None,
+                    None => ": keep-alive\n\n".to_string(),
+                    Some(Ok(v)) if v.user_id == user_id => match serde_json::to_string(&v) {
+                        Ok(data) => {
+                            format!("event: notification\nid: {}\ndata: {}\n\n", v.id, data)
+                        }
+                        Err(err) => {
+                            log::error!("Notification {} -> {}", v.id, err);
+                            continue;
+                        }
+                    },
+                    Some(Ok(_)) | Some(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                    Some(Err(broadcast::error::RecvError::Closed)) => return None,
+                };
+                return Some((
+                    Ok(web::Bytes::from(event)),
+                    (user_id, receiver, is_active, ticks),
+                ));
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    fn notification(user_id: &str) -> crate::model::notification::Notification {
+        crate::model::notification::Notification {
+            id: uuid::Uuid::new_v4(),
+            user_id: user_id.into(),
+            kind: "announcement".into(),
+            title: "test".into(),
+            message: "test message".into(),
+            read_timp: None,
+            mod_timp: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn sse_stream() {
+        let notifier = super::Notifier::new(String::new());
+        let stream = super::sse_stream(
+            "catalin".into(),
+            notifier.subscribe(),
+            || async { true },
+            super::KEEP_ALIVE_INTERVAL,
+        );
+        futures::pin_mut!(stream);
+
+        notifier.publish(notification("other_user"));
+        notifier.publish(notification("catalin"));
+
+        let event = stream.next().await.unwrap().unwrap();
+        let event = String::from_utf8(event.to_vec()).unwrap();
+        assert!(event.starts_with("event: notification\n"));
+        assert!(event.contains("\"user_id\":\"catalin\""));
+    }
+
+    #[actix_web::test]
+    async fn sse_stream_ends_with_session_on_busy_channel() {
+        let notifier = std::sync::Arc::new(super::Notifier::new(String::new()));
+        let stream = super::sse_stream(
+            "catalin".into(),
+            notifier.subscribe(),
+            || async { false },
+            std::time::Duration::from_millis(50),
+        );
+        futures::pin_mut!(stream);
+
+        // notifications of other users keep coming faster than the keep-alive ticks
+        let publisher = {
+            let notifier = notifier.clone();
+            actix_web::rt::spawn(async move {
+                loop {
+                    notifier.publish(notification("other_user"));
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                }
+            })
+        };
+        let res = tokio::time::timeout(std::time::Duration::from_secs(5), stream.next())
+            .await
+            .unwrap();
+        publisher.abort();
+        assert!(res.is_none());
+    }
+}
@@ -0,0 +1,1071 @@
+pub mod template;
+
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    model::app_method::{AppMethod, MissingMethod},
+    AppContext,
+};
+
+/// columns of an app method upload file
+pub const COLUMN_APP_CODE: &str = "app_code";
+pub const COLUMN_METHOD_CODE: &str = "method_code";
+pub const COLUMN_DESCR: &str = "descr";
+/// columns filled by the db; present in the downloaded files, so they are ignored on upload
+pub const IGNORED_COLUMNS: [&str; 3] = ["id", "mod_de", "mod_timp"];
+/// extra column of the error file, after the columns of the uploaded file;
+/// ignored on upload, so a corrected error file can be uploaded again
+pub const COLUMN_ERROR: &str = "error";
+/// error files are named "e-{user id}-{uuid}.{xlsx|csv}", so each user gets only his own
+pub const ERROR_FILE_PREFIX: &str = "e-";
+/// error files older than this are removed when a new one is written
+pub const ERROR_FILE_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+/// encoding of the text files found to be neither UTF-8 nor marked by a BOM,
+/// the one Excel uses for Romanian text on Windows
+pub const FALLBACK_ENCODING: &encoding_rs::Encoding = encoding_rs::WINDOWS_1250;
+
+/// column of an uploadable table, as expected in the upload files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadColumn {
+    pub name: &'static str,
+    /// what the column holds, shown as the header note of the template
+    pub note: &'static str,
+    /// other headers read as this column, in Romanian and English
+    pub aliases: &'static [&'static str],
+}
+
+/// table filled from upload files; `columns` are in the order of the downloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadTable {
+    /// start of the names of the files made for this table
+    pub file_name: &'static str,
+    pub columns: &'static [UploadColumn],
+}
+
+pub const APP_METHOD_TABLE: UploadTable = UploadTable {
+    file_name: "app_transactions",
+    columns: &[
+        UploadColumn {
+            name: COLUMN_APP_CODE,
+            note: "application code, one of the list; \
+                not read when the upload is made for a single app code",
+            aliases: &[
+                "cod aplicatie",
+                "cod aplicație",
+                "aplicatie",
+                "aplicație",
+                "app code",
+                "application code",
+                "application",
+            ],
+        },
+        UploadColumn {
+            name: COLUMN_METHOD_CODE,
+            note: "transaction code, unique within the application; mandatory",
+            aliases: &[
+                "cod tranzactie",
+                "cod tranzacție",
+                "tranzactie",
+                "tranzacție",
+                "cod metoda",
+                "cod metodă",
+                "transaction code",
+                "transaction",
+                "method code",
+            ],
+        },
+        UploadColumn {
+            name: COLUMN_DESCR,
+            note: "transaction description; mandatory",
+            aliases: &["descriere", "denumire", "description", "name"],
+        },
+    ],
+};
+
+/// how the uploaded file is read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadFormat {
+    /// first sheet when no name is given
+    Xlsx { sheet_name: Option<String> },
+    /// first line holds the column names; no quote char means no quoting;
+    /// no encoding means auto-detection, see `decode_text`
+    Text {
+        delimiter: u8,
+        quote: Option<u8>,
+        quote_escape: Option<u8>,
+        #[serde(default)]
+        encoding: Option<String>,
+    },
+}
+
+impl UploadFormat {
+    /// extension of the error file written for this format
+    pub fn error_file_extension(&self) -> &'static str {
+        match self {
+            Self::Xlsx { .. } => "xlsx",
+            Self::Text { .. } => "csv",
+        }
+    }
+}
+
+/// what happens to the valid rows when some rows are bad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorMode {
+    /// nothing is written
+    #[default]
+    AllOrNothing,
+    /// the valid rows are written
+    SkipBadRows,
+}
+
+impl ErrorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AllOrNothing => "all_or_nothing",
+            Self::SkipBadRows => "skip_bad_rows",
+        }
+    }
+
+    pub fn parse(v: &str) -> Option<Self> {
+        [Self::AllOrNothing, Self::SkipBadRows]
+            .into_iter()
+            .find(|k| k.as_str() == v)
+    }
+}
+
+/// how an upload is saved
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// upload scope, replacing the app codes of the file
+    pub app_code: Option<String>,
+    /// only checks the file
+    pub dry_run: bool,
+    pub error_mode: ErrorMode,
+    /// needs `app_code`, whose methods missing from the file get deleted unless still granted;
+    /// bad rows stop a sync whatever the `error_mode`
+    pub sync: bool,
+    /// file header -> table column, for headers that are neither column names nor aliases
+    #[serde(default)]
+    pub column_map: HashMap<String, String>,
+}
+
+/// valid row of the file; `row` is the sheet row or file line, the header being 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadRow {
+    pub row: usize,
+    pub method: AppMethod,
+}
+
+/// row left out of the upload and why; `cells` are the row as found in the file
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+    #[serde(skip)]
+    pub cells: Vec<String>,
+}
+
+impl RowError {
+    fn new(row: usize, column: &str, message: impl Into<String>) -> Self {
+        Self {
+            row,
+            column: Some(column.into()),
+            message: message.into(),
+            cells: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedUpload {
+    /// column names as found in the file
+    pub header: Vec<String>,
+    pub rows: Vec<UploadRow>,
+    pub rejected: Vec<RowError>,
+}
+
+/// outcome of an upload, or what it would be for a dry run
+#[derive(Debug, Serialize, Default, Clone, PartialEq, Eq)]
+pub struct UploadReport {
+    pub dry_run: bool,
+    /// false for dry runs and for uploads stopped by bad rows
+    pub saved: bool,
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub rejected: Vec<RowError>,
+    /// name of the file with the rejected rows, for `/app_methods/upload_errors/{name}`
+    pub error_file: Option<String>,
+    /// sync uploads: methods of the app code missing from the file, deleted
+    pub deleted: usize,
+    /// sync uploads: methods missing from the file, kept because groups are still granted them
+    pub in_use: Vec<MissingMethod>,
+}
+
+impl UploadReport {
+    /// compares the valid rows with the methods already in the db (same app and method code)
+    pub fn from_existing(parsed: &ParsedUpload, existing: &[AppMethod], dry_run: bool) -> Self {
+        let existing: HashMap<(&str, &str), &str> = existing
+            .iter()
+            .map(|v| {
+                (
+                    (v.app_code.as_str(), v.method_code.as_str()),
+                    v.descr.as_str(),
+                )
+            })
+            .collect();
+        let mut res = Self {
+            dry_run,
+            rejected: parsed.rejected.clone(),
+            ..Default::default()
+        };
+        for v in parsed.rows.iter().map(|v| &v.method) {
+            match existing.get(&(v.app_code.as_str(), v.method_code.as_str())) {
+                None => res.inserted += 1,
+                Some(descr) if *descr == v.descr => res.unchanged += 1,
+                Some(_) => res.updated += 1,
+            }
+        }
+        res
+    }
+
+    /// adds the outcome of a sync upload for the methods missing from the file
+    pub fn with_missing(mut self, missing: Vec<MissingMethod>) -> Self {
+        let (in_use, deleted): (Vec<MissingMethod>, Vec<MissingMethod>) =
+            missing.into_iter().partition(|v| v.grants > 0);
+        self.deleted = deleted.len();
+        self.in_use = in_use;
+        self
+    }
+}
+
+/// checks the file against the methods in the db, then saves the valid rows unless it's a dry run
+/// or bad rows stop it (`saved` stays false); the bad rows go to a downloadable error file.
+/// A job passes its `progress`, which also stops the import when the job gets cancelled
+pub async fn import_file(
+    file_path: &Path,
+    format: &UploadFormat,
+    options: &ImportOptions,
+    user_id: &str,
+    ctx: &web::Data<AppContext>,
+    timeout: Duration,
+    progress: Option<&crate::jobs::Progress<'_>>,
+) -> Result<UploadReport, actix_web::Error> {
+    if let Some(progress) = progress {
+        progress.set("parsing", 10).await?;
+    }
+    let parse_path = file_path.to_path_buf();
+    let parse_format = format.clone();
+    let parse_app_code = options.app_code.clone();
+    let parse_column_map = options.column_map.clone();
+    let parsed = web::block(move || {
+        parse_file(
+            &parse_path,
+            &parse_format,
+            parse_app_code.as_deref(),
+            &parse_column_map,
+        )
+    })
+    .await?
+    .map_err(actix_web::error::ErrorBadRequest)?;
+
+    if let Some(progress) = progress {
+        progress.set("checking", 40).await?;
+    }
+    let methods: Vec<AppMethod> = parsed.rows.iter().map(|v| v.method.clone()).collect();
+    let existing =
+        crate::model::app_method::db_methods_get_by_codes(&methods, ctx, timeout).await?;
+    let mut report = UploadReport::from_existing(&parsed, &existing, options.dry_run);
+    if !parsed.rejected.is_empty() {
+        let dir = ctx.general.temp_dir.clone();
+        let name = error_file_name(user_id, format);
+        let format = format.clone();
+        report.error_file = Some(name.clone());
+        web::block(move || write_error_file(&dir, &name, &format, &parsed))
+            .await?
+            .map_err(actix_web::error::ErrorExpectationFailed)?;
+    }
+
+    let sync_app_code = options.app_code.as_deref().filter(|_| options.sync);
+    if options.dry_run {
+        if let Some(app_code) = sync_app_code {
+            let missing = crate::model::app_method::db_methods_sync_get_missing(
+                &methods, app_code, ctx, timeout,
+            )
+            .await?;
+            report = report.with_missing(missing);
+        }
+        return Ok(report);
+    }
+    // a skipped bad row would be missing from a sync, deleting its method
+    if !report.rejected.is_empty()
+        && (options.error_mode == ErrorMode::AllOrNothing || options.sync)
+    {
+        return Ok(report);
+    }
+
+    if let Some(progress) = progress {
+        progress.set("saving", 70).await?;
+    }
+    let res = match sync_app_code {
+        Some(app_code) => {
+            let missing = crate::model::app_method::db_methods_sync_rows(
+                &methods, app_code, user_id, ctx, timeout,
+            )
+            .await?;
+            report = report.with_missing(missing);
+            methods.len()
+        }
+        None => {
+            crate::model::app_method::db_methods_upsert_rows(
+                &methods,
+                options.app_code.as_deref(),
+                user_id,
+                ctx,
+                timeout,
+            )
+            .await?
+        }
+    };
+    report.saved = true;
+    notify_upload_finished(user_id, res, ctx).await;
+    Ok(report)
+}
+
+pub async fn notify_upload_finished(user_id: &str, rows: usize, ctx: &web::Data<AppContext>) {
+    crate::notify::notify(
+        crate::model::notification::NotificationKind::UploadFinished,
+        &[user_id.to_string()],
+        &[],
+        "Upload finished",
+        &format!("{} app methods uploaded", rows),
+        ctx,
+    )
+    .await;
+}
+
+/// reads and validates the file; `app_code` scopes the upload, replacing the file's app codes.
+/// Errors are for the whole file (unreadable, missing columns), bad rows end up in `rejected`
+pub fn parse_file(
+    file_path: &Path,
+    format: &UploadFormat,
+    app_code: Option<&str>,
+    column_map: &HashMap<String, String>,
+) -> Result<ParsedUpload, String> {
+    let records = match format {
+        UploadFormat::Xlsx { sheet_name } => read_xlsx(file_path, sheet_name.as_deref())?,
+        UploadFormat::Text {
+            delimiter,
+            quote,
+            quote_escape,
+            encoding,
+        } => read_text(
+            file_path,
+            *delimiter,
+            *quote,
+            *quote_escape,
+            encoding.as_deref(),
+        )?,
+    };
+    validate(records, app_code, column_map)
+}
+
+/// checks a JSON upload like a file; the row of an error is the position in the array, from 1.
+/// `id`, `mod_de` and `mod_timp` of the methods are ignored, the db fills them
+pub fn from_methods(methods: Vec<AppMethod>) -> Result<ParsedUpload, String> {
+    let header = [COLUMN_APP_CODE, COLUMN_METHOD_CODE, COLUMN_DESCR]
+        .into_iter()
+        .map(|v| Ok(v.to_string()))
+        .collect();
+    let records = std::iter::once((0, header))
+        .chain(
+            methods
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| (i + 1, vec![Ok(v.app_code), Ok(v.method_code), Ok(v.descr)])),
+        )
+        .collect();
+    validate(records, None, &HashMap::new())
+}
+
+/// file row with its cells, a cell being `Err` when it can't be read as text
+type RawRecord = (usize, Vec<Result<String, String>>);
+
+fn read_xlsx(file_path: &Path, sheet_name: Option<&str>) -> Result<Vec<RawRecord>, String> {
+    use calamine::Reader;
+
+    let mut workbook = calamine::open_workbook_auto(file_path).map_err(|e| e.to_string())?;
+    let sheet_name = match sheet_name {
+        Some(v) => v.to_string(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or("workbook has no sheets")?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .map_err(|e| format!("sheet '{}': {}", sheet_name, e))?;
+    let first_row = range.start().map(|v| v.0 as usize).unwrap_or_default();
+
+    Ok(range
+        .rows()
+        .enumerate()
+        .map(|(i, cells)| {
+            let cells = cells
+                .iter()
+                .map(|v| match v {
+                    calamine::Data::Error(e) => Err(format!("cell error {}", e)),
+                    v => Ok(v.to_string()),
+                })
+                .collect();
+            (first_row + i + 1, cells)
+        })
+        .collect())
+}
+
+fn read_text(
+    file_path: &Path,
+    delimiter: u8,
+    quote: Option<u8>,
+    quote_escape: Option<u8>,
+    encoding: Option<&str>,
+) -> Result<Vec<RawRecord>, String> {
+    let bytes = std::fs::read(file_path).map_err(|e| e.to_string())?;
+    let text = decode_text(&bytes, text_encoding(encoding)?)?;
+
+    let mut builder = csv::ReaderBuilder::new();
+    builder
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter);
+    match quote {
+        Some(q) => {
+            builder.quote(q);
+            if let Some(e) = quote_escape.filter(|e| *e != q) {
+                builder.escape(Some(e)).double_quote(false);
+            }
+        }
+        None => {
+            builder.quoting(false);
+        }
+    }
+    let mut reader = builder.from_reader(text.as_bytes());
+
+    let mut res = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let line = record
+            .position()
+            .map(|v| v.line() as usize)
+            .unwrap_or_default();
+        let cells = record.iter().map(|v| Ok(v.to_string())).collect();
+        res.push((line, cells));
+    }
+    Ok(res)
+}
+
+/// encoding named by `label` ("utf-8", "windows-1250", "latin2" ...); none for "auto"
+pub fn text_encoding(
+    label: Option<&str>,
+) -> Result<Option<&'static encoding_rs::Encoding>, String> {
+    match label.map(str::trim) {
+        None => Ok(None),
+        Some(v) if v.eq_ignore_ascii_case("auto") => Ok(None),
+        Some(v) => encoding_rs::Encoding::for_label(v.as_bytes())
+            .map(Some)
+            .ok_or_else(|| format!("unknown encoding '{}'", v)),
+    }
+}
+
+/// text of a file as UTF-8, without BOM. A BOM wins over `encoding`; with neither,
+/// the text is UTF-8 when valid, else `FALLBACK_ENCODING`
+pub fn decode_text(
+    bytes: &[u8],
+    encoding: Option<&'static encoding_rs::Encoding>,
+) -> Result<String, String> {
+    let encoding = match encoding {
+        Some(v) => v,
+        None if std::str::from_utf8(bytes).is_ok() => encoding_rs::UTF_8,
+        None => FALLBACK_ENCODING,
+    };
+    let (text, used, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(format!(
+            "file is not valid {} text, check the 'encoding' field",
+            used.name()
+        ));
+    }
+    Ok(text.into_owned())
+}
+
+/// header compared without case, spaces and underscores: "App_Code" is "app code";
+/// the cedilla "ş"/ "ţ" are the comma below "ș"/ "ț", the only ones Windows-1250 has
+fn header_key(v: &str) -> String {
+    v.split(|c: char| c.is_whitespace() || c == '_')
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .replace('ş', "ș")
+        .replace('ţ', "ț")
+}
+
+/// finds the table column of each file header: first in `column_map`, then by column name
+/// or alias. Every header problem is reported, not only the first one
+fn map_columns(
+    header: &[String],
+    column_map: &HashMap<String, String>,
+    app_code: Option<&str>,
+) -> Result<HashMap<&'static str, usize>, String> {
+    let known: Vec<&'static str> = APP_METHOD_TABLE
+        .columns
+        .iter()
+        .map(|v| v.name)
+        .chain(IGNORED_COLUMNS)
+        .chain([COLUMN_ERROR])
+        .collect();
+    let mut errors: Vec<String> = Vec::new();
+
+    let mut mapped: HashMap<String, &'static str> = HashMap::new();
+    for (from, to) in column_map.iter() {
+        match known.iter().find(|c| header_key(c) == header_key(to)) {
+            Some(to) => {
+                mapped.insert(header_key(from), *to);
+            }
+            None => errors.push(format!(
+                "column_map: '{}' is not a column, expected one of {}",
+                to,
+                known.join(", ")
+            )),
+        }
+        if !header.iter().any(|v| header_key(v) == header_key(from)) {
+            errors.push(format!(
+                "column_map: column '{}' not found in the file",
+                from
+            ));
+        }
+    }
+
+    let mut columns: HashMap<&'static str, usize> = HashMap::new();
+    for (i, name) in header.iter().enumerate() {
+        if name.is_empty() {
+            continue;
+        }
+        let key = header_key(name);
+        let column = mapped.get(&key).copied().or_else(|| {
+            APP_METHOD_TABLE
+                .columns
+                .iter()
+                .find(|c| {
+                    header_key(c.name) == key || c.aliases.iter().any(|a| header_key(a) == key)
+                })
+                .map(|c| c.name)
+                .or_else(|| known.iter().find(|c| header_key(c) == key).copied())
+        });
+        let Some(column) = column else {
+            errors.push(format!(
+                "unknown column '{}', rename it or map it with the field 'column_map'",
+                name
+            ));
+            continue;
+        };
+        if let Some(first) = columns.insert(column, i) {
+            errors.push(format!(
+                "columns '{}' and '{}' are both read as '{}'",
+                header[first], name, column
+            ));
+        }
+    }
+    for name in [COLUMN_METHOD_CODE, COLUMN_DESCR]
+        .into_iter()
+        .chain(app_code.is_none().then_some(COLUMN_APP_CODE))
+    {
+        if !columns.contains_key(name) {
+            errors.push(format!("missing column '{}'", name));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    Ok(columns)
+}
+
+fn validate(
+    records: Vec<RawRecord>,
+    app_code: Option<&str>,
+    column_map: &HashMap<String, String>,
+) -> Result<ParsedUpload, String> {
+    let mut records = records.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err("file is empty".into());
+    };
+    let header: Vec<String> = header
+        .into_iter()
+        .map(|v| v.unwrap_or_default().trim().to_string())
+        .collect();
+    let columns = map_columns(&header, column_map, app_code)?;
+
+    let mut res = ParsedUpload {
+        header,
+        ..Default::default()
+    };
+    let mut keys: HashMap<(String, String), usize> = HashMap::new();
+    for (row, cells) in records {
+        if cells.iter().all(|v| {
+            v.as_deref()
+                .map(|v| v.trim().is_empty())
+                .unwrap_or_default()
+        }) {
+            continue;
+        }
+        let cell = |name: &str| -> Result<String, RowError> {
+            let value = columns
+                .get(name)
+                .and_then(|i| cells.get(*i))
+                .cloned()
+                .unwrap_or_else(|| Ok(String::new()))
+                .map_err(|message| RowError::new(row, name, message))?;
+            let value = value.trim().to_string();
+            if value.is_empty() {
+                return Err(RowError::new(row, name, "value is mandatory"));
+            }
+            Ok(value)
+        };
+        let row_cells = || {
+            cells
+                .iter()
+                .map(|v| v.clone().unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+        let app_code = match app_code {
+            Some(v) => Ok(v.to_string()),
+            None => cell(COLUMN_APP_CODE),
+        };
+        let method = match (app_code, cell(COLUMN_METHOD_CODE), cell(COLUMN_DESCR)) {
+            (Ok(app_code), Ok(method_code), Ok(descr)) => AppMethod {
+                id: None,
+                app_code,
+                method_code,
+                descr,
+                mod_de: None,
+                mod_timp: None,
+            },
+            (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+                res.rejected.push(RowError {
+                    cells: row_cells(),
+                    ..err
+                });
+                continue;
+            }
+        };
+        let key = (method.app_code.clone(), method.method_code.clone());
+        if let Some(first) = keys.get(&key) {
+            res.rejected.push(RowError {
+                cells: row_cells(),
+                ..RowError::new(
+                    row,
+                    COLUMN_METHOD_CODE,
+                    format!("duplicate of row {}", first),
+                )
+            });
+        } else {
+            keys.insert(key, row);
+            res.rows.push(UploadRow { row, method });
+        }
+    }
+    Ok(res)
+}
+
+/// new error file name for `user_id`
+pub fn error_file_name(user_id: &str, format: &UploadFormat) -> String {
+    format!(
+        "{}{}-{}.{}",
+        ERROR_FILE_PREFIX,
+        user_id,
+        uuid::Uuid::new_v4(),
+        format.error_file_extension()
+    )
+}
+
+/// path of an error file of `user_id`; `None` for names of other users or outside `dir`
+///
+/// the whole rest of the name after the user id must be the uuid and extension, as user ids
+/// may contain "-" (ex.: "bob" must not get the files of "bob-x")
+pub fn error_file_path(dir: &Path, user_id: &str, name: &str) -> Option<PathBuf> {
+    let owned = name
+        .strip_prefix(ERROR_FILE_PREFIX)
+        .and_then(|v| v.strip_prefix(user_id))
+        .and_then(|v| v.strip_prefix('-'))
+        .and_then(|v| v.strip_suffix(".xlsx").or_else(|| v.strip_suffix(".csv")))
+        .is_some_and(|v| uuid::Uuid::try_parse(v).is_ok())
+        && !name.contains(['/', '\\']);
+    let path = dir.join(name);
+    (owned && path.is_file()).then_some(path)
+}
+
+/// writes the rejected rows, as they were in the file, with the reason in an extra "error" column;
+/// error files older than `ERROR_FILE_MAX_AGE` are removed first
+pub fn write_error_file(
+    dir: &Path,
+    name: &str,
+    format: &UploadFormat,
+    parsed: &ParsedUpload,
+) -> Result<PathBuf, String> {
+    purge_error_files(dir);
+    let path = dir.join(name);
+    let header: Vec<&str> = parsed
+        .header
+        .iter()
+        .map(String::as_str)
+        .chain([COLUMN_ERROR])
+        .collect();
+    let rows = parsed.rejected.iter().map(|v| {
+        let message = match &v.column {
+            Some(column) => format!("row {}, {}: {}", v.row, column, v.message),
+            None => format!("row {}: {}", v.row, v.message),
+        };
+        // short rows are padded, so the error is always in the last column
+        let mut cells: Vec<String> = v.cells.clone();
+        cells.resize(parsed.header.len().max(cells.len()), String::new());
+        cells.push(message);
+        cells
+    });
+
+    match format {
+        UploadFormat::Xlsx { .. } => {
+            let mut workbook = rust_xlsxwriter::Workbook::new();
+            let sheet = workbook.add_worksheet();
+            for (c, v) in header.iter().enumerate() {
+                sheet
+                    .write_string(0, c as u16, *v)
+                    .map_err(|e| e.to_string())?;
+            }
+            for (r, cells) in rows.enumerate() {
+                for (c, v) in cells.iter().enumerate() {
+                    sheet
+                        .write_string(r as u32 + 1, c as u16, v)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            workbook.save(&path).map_err(|e| e.to_string())?;
+        }
+        UploadFormat::Text {
+            delimiter, quote, ..
+        } => {
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(*delimiter)
+                .quote(quote.unwrap_or(b'"'))
+                .flexible(true)
+                .from_path(&path)
+                .map_err(|e| e.to_string())?;
+            writer.write_record(&header).map_err(|e| e.to_string())?;
+            for cells in rows {
+                writer.write_record(&cells).map_err(|e| e.to_string())?;
+            }
+            writer.flush().map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(path)
+}
+
+fn purge_error_files(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let is_old = entry
+            .metadata()
+            .and_then(|v| v.modified())
+            .ok()
+            .and_then(|v| v.elapsed().ok())
+            .map(|v| v > ERROR_FILE_MAX_AGE)
+            .unwrap_or_default();
+        if is_old
+            && entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(ERROR_FILE_PREFIX)
+        {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    fn text_file(content: &str) -> crate::helper::TempFile {
+        let file = crate::helper::TempFile {
+            path: std::env::temp_dir().join(format!("upload-test-{}.csv", uuid::Uuid::new_v4())),
+        };
+        std::fs::write(&file.path, content).unwrap();
+        file
+    }
+
+    #[test]
+    fn parse_text_rejects_bad_rows() {
+        let file = text_file(
+            "App_Code;method_code;descr;mod_de\n\
+            portal;m1;\"first; method\";catalin\n\
+            portal;;no code;catalin\n\
+            ;;;\n\
+            portal;m1;duplicate;catalin\n",
+        );
+        let format = super::UploadFormat::Text {
+            delimiter: b';',
+            quote: Some(b'"'),
+            quote_escape: None,
+            encoding: None,
+        };
+        let res = super::parse_file(&file.path, &format, None, &HashMap::new()).unwrap();
+
+        assert_eq!(1, res.rows.len());
+        assert_eq!(
+            (2, "first; method"),
+            (res.rows[0].row, res.rows[0].method.descr.as_str())
+        );
+        assert_eq!(
+            vec![
+                (3, Some("method_code".to_string())),
+                (5, Some("method_code".to_string()))
+            ],
+            res.rejected
+                .iter()
+                .map(|v| (v.row, v.column.clone()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parse_text_decodes_encodings() {
+        let format = |encoding: Option<&str>| super::UploadFormat::Text {
+            delimiter: b',',
+            quote: Some(b'"'),
+            quote_escape: None,
+            encoding: encoding.map(String::from),
+        };
+        let file = text_file("");
+
+        // Windows-1250 without BOM, found by the auto-detection
+        let mut bytes = b"app_code,method_code,descr\nportal,m1,".to_vec();
+        bytes.extend_from_slice(&[0xE3, 0xBA, 0xFE]);
+        std::fs::write(&file.path, &bytes).unwrap();
+        let res = super::parse_file(&file.path, &format(None), None, &HashMap::new()).unwrap();
+        assert_eq!("ăşţ", res.rows[0].method.descr);
+        assert!(
+            super::parse_file(&file.path, &format(Some("utf-8")), None, &HashMap::new()).is_err()
+        );
+
+        // the BOM is not part of the first header
+        std::fs::write(
+            &file.path,
+            "\u{feff}app_code,method_code,descr\nportal,m1,ăşț\n",
+        )
+        .unwrap();
+        let res = super::parse_file(&file.path, &format(None), None, &HashMap::new()).unwrap();
+        assert_eq!("ăşț", res.rows[0].method.descr);
+
+        assert!(super::text_encoding(Some("klingon")).is_err());
+        assert_eq!(
+            Some(encoding_rs::WINDOWS_1250),
+            super::text_encoding(Some("cp1250")).unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_text_maps_cedilla_headers() {
+        let format = super::UploadFormat::Text {
+            delimiter: b',',
+            quote: Some(b'"'),
+            quote_escape: None,
+            encoding: Some("windows-1250".into()),
+        };
+        let file = text_file("");
+
+        // "Cod aplicaţie,Cod tranzacţie" as saved by Excel on Windows, cedilla "ţ" only
+        let mut bytes = b"Cod aplica".to_vec();
+        bytes.push(0xFE);
+        bytes.extend_from_slice(b"ie,Cod tranzac");
+        bytes.push(0xFE);
+        bytes.extend_from_slice(b"ie,Descriere\nportal,m1,first\n");
+        std::fs::write(&file.path, &bytes).unwrap();
+        let res = super::parse_file(&file.path, &format, None, &HashMap::new()).unwrap();
+        assert_eq!("portal", res.rows[0].method.app_code);
+        assert_eq!("m1", res.rows[0].method.method_code);
+    }
+
+    #[test]
+    fn parse_text_checks_columns() {
+        let format = super::UploadFormat::Text {
+            delimiter: b',',
+            quote: None,
+            quote_escape: None,
+            encoding: None,
+        };
+        let file = text_file("method_code,descr\nm1,first\n");
+        assert!(super::parse_file(&file.path, &format, None, &HashMap::new()).is_err());
+        let res = super::parse_file(&file.path, &format, Some("portal"), &HashMap::new()).unwrap();
+        assert_eq!("portal", res.rows[0].method.app_code);
+
+        let file = text_file("method_code,descr,other\nm1,first,x\n");
+        assert!(super::parse_file(&file.path, &format, Some("portal"), &HashMap::new()).is_err());
+
+        // aliases, and the column map for the other headers
+        let file = text_file("Cod aplicatie,Cod tranzacție,Text,other\nportal,m1,first,x\n");
+        let err = super::parse_file(&file.path, &format, None, &HashMap::new()).unwrap_err();
+        assert_eq!(
+            "unknown column 'Text', rename it or map it with the field 'column_map'; \
+            unknown column 'other', rename it or map it with the field 'column_map'; \
+            missing column 'descr'",
+            err
+        );
+        let column_map = HashMap::from([
+            ("text".to_string(), "descr".to_string()),
+            ("other".to_string(), "mod_de".to_string()),
+        ]);
+        let res = super::parse_file(&file.path, &format, None, &column_map).unwrap();
+        assert_eq!(
+            ("portal", "m1", "first"),
+            (
+                res.rows[0].method.app_code.as_str(),
+                res.rows[0].method.method_code.as_str(),
+                res.rows[0].method.descr.as_str()
+            )
+        );
+
+        let file = text_file("app_code,method_code,Description,descr\nportal,m1,first,again\n");
+        let err = super::parse_file(&file.path, &format, None, &HashMap::new()).unwrap_err();
+        assert_eq!(
+            "columns 'Description' and 'descr' are both read as 'descr'",
+            err
+        );
+    }
+
+    #[test]
+    fn from_methods_checks_items() {
+        let method = |code: &str| crate::model::app_method::AppMethod {
+            id: Some(uuid::Uuid::new_v4()),
+            app_code: "portal".into(),
+            method_code: code.into(),
+            descr: "descr".into(),
+            mod_de: Some("someone".into()),
+            mod_timp: None,
+        };
+        let res = super::from_methods(vec![method("m1"), method(" "), method("m1")]).unwrap();
+        assert_eq!(1, res.rows.len());
+        assert_eq!(None, res.rows[0].method.id);
+        assert_eq!(None, res.rows[0].method.mod_de);
+        let errors: Vec<_> = res
+            .rejected
+            .iter()
+            .map(|v| (v.row, v.message.as_str()))
+            .collect();
+        assert_eq!(
+            vec![(2, "value is mandatory"), (3, "duplicate of row 1")],
+            errors
+        );
+    }
+
+    #[test]
+    fn report_from_existing() {
+        let method = |code: &str, descr: &str| crate::model::app_method::AppMethod {
+            id: None,
+            app_code: "portal".into(),
+            method_code: code.into(),
+            descr: descr.into(),
+            mod_de: None,
+            mod_timp: None,
+        };
+        let parsed = super::ParsedUpload {
+            rows: ["m1", "m2", "m3"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| super::UploadRow {
+                    row: i + 2,
+                    method: method(v, "same"),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let res = super::UploadReport::from_existing(
+            &parsed,
+            &[method("m1", "same"), method("m2", "other")],
+            true,
+        );
+        assert_eq!((1, 1, 1), (res.inserted, res.updated, res.unchanged));
+
+        let missing = |code: &str, grants: i64| crate::model::app_method::MissingMethod {
+            id: uuid::Uuid::new_v4(),
+            app_code: "portal".into(),
+            method_code: code.into(),
+            grants,
+        };
+        let res = res.with_missing(vec![missing("m4", 0), missing("m5", 2), missing("m6", 0)]);
+        assert_eq!(2, res.deleted);
+        assert_eq!(
+            vec!["m5"],
+            res.in_use
+                .iter()
+                .map(|v| v.method_code.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn error_file_keeps_rejected_rows() {
+        let dir = std::env::temp_dir();
+        let file = text_file("app_code,method_code,descr\nportal,,no code\nportal,m1,first\n");
+        let format = super::UploadFormat::Text {
+            delimiter: b',',
+            quote: Some(b'"'),
+            quote_escape: None,
+            encoding: None,
+        };
+        let parsed = super::parse_file(&file.path, &format, None, &HashMap::new()).unwrap();
+
+        let name = super::error_file_name("catalin", &format);
+        let error_file = crate::helper::TempFile {
+            path: super::write_error_file(&dir, &name, &format, &parsed).unwrap(),
+        };
+        assert_eq!(
+            Some(error_file.path.clone()),
+            super::error_file_path(&dir, "catalin", &name)
+        );
+        assert!(super::error_file_path(&dir, "other", &name).is_none());
+        assert!(super::error_file_path(&dir, "cata", &name).is_none());
+        let name_with_dash = name.replacen("catalin", "cata-lin", 1);
+        let other_file = crate::helper::TempFile {
+            path: dir.join(&name_with_dash),
+        };
+        std::fs::copy(&error_file.path, &other_file.path).unwrap();
+        assert!(super::error_file_path(&dir, "cata", &name_with_dash).is_none());
+        assert!(super::error_file_path(&dir, "cata-lin", &name_with_dash).is_some());
+        assert_eq!(
+            "app_code,method_code,descr,error\n\
+            portal,,no code,\"row 2, method_code: value is mandatory\"\n",
+            std::fs::read_to_string(&error_file.path).unwrap()
+        );
+
+        // the xlsx error file reads back like the uploaded file
+        let format = super::UploadFormat::Xlsx { sheet_name: None };
+        let name = super::error_file_name("catalin", &format);
+        let error_file = crate::helper::TempFile {
+            path: super::write_error_file(&dir, &name, &format, &parsed).unwrap(),
+        };
+        let res = super::parse_file(&error_file.path, &format, None, &HashMap::new()).unwrap();
+        assert_eq!((0, 1), (res.rows.len(), res.rejected.len()));
+        assert_eq!(Some("method_code".to_string()), res.rejected[0].column);
+    }
+}
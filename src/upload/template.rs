@@ -0,0 +1,127 @@
+use rust_xlsxwriter::{DataValidation, Format, Formula, Note, Workbook};
+use std::path::{Path, PathBuf};
+
+use super::UploadTable;
+
+/// sheet with the header row, to be filled by the user
+pub const DATA_SHEET: &str = "DATA";
+/// hidden sheet with the dropdown values, a column for each list
+pub const LISTS_SHEET: &str = "LISTS";
+/// template files are named "t-{user id}-{uuid}.xlsx"; removed once sent
+pub const FILE_PREFIX: &str = "t-";
+/// last sheet row (0 based) with a dropdown
+const LAST_ROW: u32 = 1_048_575;
+
+/// path of a new template file for `user_id`
+pub fn file_path(dir: &Path, user_id: &str) -> PathBuf {
+    dir.join(format!(
+        "{}{}-{}.xlsx",
+        FILE_PREFIX,
+        user_id,
+        uuid::Uuid::new_v4()
+    ))
+}
+
+/// name the template of `table` is downloaded with
+pub fn download_name(table: &UploadTable) -> String {
+    format!("{}_template.xlsx", table.file_name)
+}
+
+/// writes an empty upload workbook for `table`: the header row, with the column description
+/// as a note on each header, and a dropdown on the columns found in `lists`, whose values
+/// are kept in a hidden sheet (the inline lists of Excel are limited to 255 characters)
+pub fn write_template(
+    path: &Path,
+    table: &UploadTable,
+    lists: &[(&str, Vec<String>)],
+) -> Result<(), String> {
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(DATA_SHEET).map_err(|e| e.to_string())?;
+    for (c, column) in table.columns.iter().enumerate() {
+        let c = c as u16;
+        sheet
+            .write_string_with_format(0, c, column.name, &header_format)
+            .map_err(|e| e.to_string())?;
+        sheet.set_column_width(c, 30).map_err(|e| e.to_string())?;
+        sheet
+            .insert_note(0, c, &Note::new(column.note).set_author("portal"))
+            .map_err(|e| e.to_string())?;
+
+        let Some(list_column) = lists.iter().position(|(name, _)| *name == column.name) else {
+            continue;
+        };
+        let values = &lists[list_column].1;
+        if values.is_empty() {
+            continue;
+        }
+        let range = format!(
+            "={}!${}$1:${}${}",
+            LISTS_SHEET,
+            column_letter(list_column),
+            column_letter(list_column),
+            values.len()
+        );
+        let validation = DataValidation::new()
+            .allow_list_formula(Formula::new(range))
+            .set_error_title(column.name)
+            .and_then(|v| v.set_error_message("pick a value from the list"))
+            .map_err(|e| e.to_string())?;
+        sheet
+            .add_data_validation(1, c, LAST_ROW, c, &validation)
+            .map_err(|e| e.to_string())?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(|e| e.to_string())?;
+
+    let lists_sheet = workbook.add_worksheet();
+    lists_sheet
+        .set_name(LISTS_SHEET)
+        .map_err(|e| e.to_string())?;
+    lists_sheet.set_hidden(true);
+    for (c, (_, values)) in lists.iter().enumerate() {
+        for (r, v) in values.iter().enumerate() {
+            lists_sheet
+                .write_string(r as u32, c as u16, v)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    workbook.save(path).map_err(|e| e.to_string())
+}
+
+/// sheet column letter of a 0 based index, enough for the few lists of a template
+fn column_letter(idx: usize) -> char {
+    (b'A' + (idx % 26) as u8) as char
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn template_reads_back_as_empty_upload() {
+        use calamine::Reader;
+
+        let file = crate::helper::TempFile {
+            path: super::file_path(&std::env::temp_dir(), "catalin"),
+        };
+        let app_codes = vec!["portal".to_string(), "sap".to_string()];
+        super::write_template(
+            &file.path,
+            &crate::upload::APP_METHOD_TABLE,
+            &[(crate::upload::COLUMN_APP_CODE, app_codes.clone())],
+        )
+        .unwrap();
+
+        let format = crate::upload::UploadFormat::Xlsx { sheet_name: None };
+        let res =
+            crate::upload::parse_file(&file.path, &format, None, &Default::default()).unwrap();
+        assert_eq!(vec!["app_code", "method_code", "descr"], res.header);
+        assert!(res.rows.is_empty() && res.rejected.is_empty());
+
+        let mut workbook = calamine::open_workbook_auto(&file.path).unwrap();
+        let lists = workbook.worksheet_range(super::LISTS_SHEET).unwrap();
+        let values: Vec<String> = lists.rows().map(|v| v[0].to_string()).collect();
+        assert_eq!(app_codes, values);
+    }
+}
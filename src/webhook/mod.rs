@@ -0,0 +1,319 @@
+use actix_web::web;
+use std::time::Duration;
+
+use crate::{
+    model::webhook::{ClaimedDelivery, WebhookEvent},
+    AppContext,
+};
+
+/// how often the worker looks for due deliveries
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// deliveries posted on each poll
+pub const BATCH_SIZE: i64 = 20;
+/// failed attempts before a delivery goes "dead"
+pub const MAX_ATTEMPTS: i32 = 8;
+/// first retry delay, doubled on each failed attempt
+pub const BACKOFF_BASE_SECS: i32 = 30;
+/// receivers answering slower than this count as failed
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// name of the event header
+pub const HEADER_EVENT: &str = "X-Portal-Event";
+/// name of the delivery id header, the same on each retry so receivers can drop duplicates
+pub const HEADER_DELIVERY: &str = "X-Portal-Delivery";
+/// name of the signature header, "sha256=" followed by the hex HMAC-SHA256 of the body
+pub const HEADER_SIGNATURE: &str = "X-Portal-Signature";
+
+/// queues `data` for the webhooks subscribed to `event`;
+/// errors are only logged, a webhook never fails the change it reports
+pub async fn publish(event: WebhookEvent, data: serde_json::Value, ctx: &web::Data<AppContext>) {
+    if let Err(err) =
+        crate::model::webhook::db_delivery_enqueue(event, &data, ctx, Duration::from_secs(10)).await
+    {
+        log::error!("Webhook event '{}' -> {}", event.as_str(), err);
+    }
+}
+
+/// value of the signature header for `body`
+pub fn sign(secret: &str, body: &[u8]) -> Result<String, openssl::error::ErrorStack> {
+    let key = openssl::pkey::PKey::hmac(secret.as_bytes())?;
+    let mut signer = openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &key)?;
+    signer.update(body)?;
+    let hex: String = signer
+        .sign_to_vec()?
+        .iter()
+        .map(|v| format!("{:02x}", v))
+        .collect();
+    Ok(format!("sha256={}", hex))
+}
+
+/// background worker posting the queued deliveries, runs for the whole life of the server
+pub async fn run_worker(ctx: web::Data<AppContext>) {
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(v) => v,
+        Err(err) => {
+            log::error!("Webhook worker http client -> {}", err);
+            return;
+        }
+    };
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = process_batch(&client, &ctx).await {
+            log::error!("Webhook worker -> {}", err);
+        }
+    }
+}
+
+/// posts one batch of due deliveries; returns the number of delivered ones
+///
+/// a failed status update is logged and the batch goes on, the delivery stays "sending"
+/// and is picked up again by a later claim
+pub async fn process_batch(
+    client: &reqwest::Client,
+    ctx: &web::Data<AppContext>,
+) -> Result<usize, actix_web::Error> {
+    let deliveries =
+        crate::model::webhook::db_delivery_claim(BATCH_SIZE, ctx, Duration::from_secs(10)).await?;
+
+    let mut delivered = 0;
+    for claimed in deliveries {
+        let id = claimed.delivery.id;
+        match post(client, &claimed).await {
+            Ok(status) => {
+                if let Err(err) = crate::model::webhook::db_delivery_mark_delivered(
+                    &id,
+                    status,
+                    ctx,
+                    Duration::from_secs(10),
+                )
+                .await
+                {
+                    log::error!("Webhook delivery {} mark delivered -> {}", id, err);
+                }
+                delivered += 1;
+            }
+            Err((status, err)) => {
+                log::error!(
+                    "Webhook delivery {} attempt {} -> {}",
+                    id,
+                    claimed.delivery.attempts + 1,
+                    err
+                );
+                if let Err(db_err) = crate::model::webhook::db_delivery_mark_failed(
+                    &id,
+                    status,
+                    &err,
+                    MAX_ATTEMPTS,
+                    BACKOFF_BASE_SECS,
+                    ctx,
+                    Duration::from_secs(10),
+                )
+                .await
+                {
+                    log::error!("Webhook delivery {} mark failed -> {}", id, db_err);
+                }
+            }
+        }
+    }
+    Ok(delivered)
+}
+
+/// signed POST of the delivery; any 2xx answer is a success, the response status is kept either way
+async fn post(
+    client: &reqwest::Client,
+    claimed: &ClaimedDelivery,
+) -> Result<i32, (Option<i32>, String)> {
+    let delivery = &claimed.delivery;
+    let body = serde_json::to_vec(&serde_json::json!({
+        "id": delivery.id,
+        "event": delivery.event_type,
+        "created": delivery.created_timp,
+        "data": delivery.payload,
+    }))
+    .map_err(|e| (None, e.to_string()))?;
+    let signature = sign(&claimed.secret, &body).map_err(|e| (None, e.to_string()))?;
+
+    let res = client
+        .post(&claimed.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(HEADER_EVENT, &delivery.event_type)
+        .header(HEADER_DELIVERY, delivery.id.to_string())
+        .header(HEADER_SIGNATURE, signature)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| (None, e.to_string()))?;
+
+    let status = res.status();
+    if status.is_success() {
+        Ok(status.as_u16() as i32)
+    } else {
+        Err((
+            Some(status.as_u16() as i32),
+            format!("receiver answered {}", status),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    /// signature header and body of each request the test receiver got
+    type Received = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+    #[test]
+    fn sign() {
+        // RFC 4231 test case 2
+        let res = super::sign("Jefe", b"what do ya want for nothing?").unwrap();
+        assert_eq!(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            res
+        );
+    }
+
+    #[actix_web::test]
+    async fn deliver_to_local_receiver() {
+        let received: Received = Arc::default();
+        let received_srv = received.clone();
+        let server = actix_web::HttpServer::new(move || {
+            let received = received_srv.clone();
+            actix_web::App::new().route(
+                "/hook",
+                actix_web::web::post().to(
+                    move |req: actix_web::HttpRequest, body: actix_web::web::Bytes| {
+                        let received = received.clone();
+                        async move {
+                            let signature = req
+                                .headers()
+                                .get(super::HEADER_SIGNATURE)
+                                .and_then(|v| v.to_str().ok())
+                                .unwrap_or_default()
+                                .to_string();
+                            received.lock().unwrap().push((signature, body.to_vec()));
+                            actix_web::HttpResponse::Ok().finish()
+                        }
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let port = server.addrs()[0].port();
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        let ctx = crate::init_app_data().unwrap();
+        let webhook = crate::model::webhook::db_webhook_single_upsert(
+            &crate::model::webhook::Webhook {
+                id: None,
+                url: format!("http://127.0.0.1:{}/hook", port),
+                event_types: vec![
+                    super::WebhookEvent::AppMethodUpserted.as_str().into(),
+                    super::WebhookEvent::GrantUpserted.as_str().into(),
+                    super::WebhookEvent::GrantDeleted.as_str().into(),
+                ],
+                secret: Some("test_secret".into()),
+                active: true,
+                mod_de: None,
+                mod_timp: None,
+            },
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let method = crate::model::app_method::AppMethod {
+            id: None,
+            app_code: "portal".into(),
+            method_code: "webhook_test".into(),
+            descr: "Webhook test".into(),
+            mod_de: None,
+            mod_timp: None,
+        };
+        crate::model::app_method::db_method_single_upsert(
+            &method,
+            "catalin",
+            None,
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        let grant = crate::model::grant::db_grant_single_upsert(
+            &crate::model::grant::Grant {
+                id: None,
+                group_id: "cdg_controller".into(),
+                app_method_id: None,
+                app_code: "portal".into(),
+                method_code: "webhook_test".into(),
+                mod_de: None,
+                mod_timp: None,
+            },
+            "catalin",
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        crate::model::grant::db_grant_delete_by_id(
+            &grant.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+
+        let client = reqwest::Client::new();
+        let res = super::process_batch(&client, &ctx).await.unwrap();
+        assert!(res > 0);
+
+        let deliveries = crate::model::webhook::db_delivery_get_filtered(
+            webhook.id.as_ref(),
+            Some("delivered"),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        assert_eq!(3, deliveries.len());
+
+        let received = received.lock().unwrap().clone();
+        let got = |event: super::WebhookEvent, part: &str| {
+            received.iter().any(|(signature, body)| {
+                let body_str = String::from_utf8_lossy(body);
+                *signature == super::sign("test_secret", body).unwrap()
+                    && body_str.contains(&format!("\"event\":\"{}\"", event.as_str()))
+                    && body_str.contains(part)
+            })
+        };
+        assert!(got(
+            super::WebhookEvent::AppMethodUpserted,
+            "\"method_code\":\"webhook_test\""
+        ));
+        assert!(got(
+            super::WebhookEvent::GrantUpserted,
+            "\"group_id\":\"cdg_controller\""
+        ));
+        assert!(got(
+            super::WebhookEvent::GrantDeleted,
+            &grant.id.unwrap().to_string()
+        ));
+
+        crate::model::webhook::db_webhook_delete_by_id(
+            &webhook.id.unwrap(),
+            &ctx,
+            std::time::Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+        handle.stop(true).await;
+    }
+}
@@ -1,24 +1,53 @@
-#[actix_web::test]
-async fn test_login_request() {
-    let payload = cdg_portal::model::users::LoginData {
-        user_id: "catalin".into(),
-    };
-
-    let app_data = cdg_portal::init_app_data().unwrap();
-    let app = actix_web::test::init_service(actix_web::App::new().app_data(app_data).route(
-        "/",
-        actix_web::web::post().to(cdg_portal::handlers::auth::user_login),
-    ))
-    .await;
-    let req = actix_web::test::TestRequest::post()
-        .set_json(payload)
-        .to_request();
-    let resp = actix_web::test::call_service(&app, req).await;
-    let status = resp.status();
-    let bytes = actix_web::body::to_bytes(resp.into_body())
-        .await
-        .unwrap()
-        .to_vec();
-    let body = String::from_utf8(bytes).unwrap();
-    assert!(status.is_success(), "{}", body);
-}
+#[actix_web::test]
+async fn test_login_request() {
+    let payload = cdg_portal::model::users::LoginData {
+        user_id: "catalin".into(),
+    };
+
+    // no smtp server needed: mails are kept in memory
+    let mailer = cdg_portal::mail::transport::RecordingMailer::default();
+    let app_data = cdg_portal::init_app_data_with_mailer(Some(Box::new(mailer.clone()))).unwrap();
+    let user = cdg_portal::model::users::db_get_single(
+        &payload.user_id,
+        &app_data,
+        std::time::Duration::from_secs(10),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let app =
+        actix_web::test::init_service(actix_web::App::new().app_data(app_data.clone()).route(
+            "/",
+            actix_web::web::post().to(cdg_portal::handlers::auth::user_login),
+        ))
+        .await;
+    let req = actix_web::test::TestRequest::post()
+        .set_json(payload)
+        .to_request();
+    let resp = actix_web::test::call_service(&app, req).await;
+    let status = resp.status();
+    let bytes = actix_web::body::to_bytes(resp.into_body())
+        .await
+        .unwrap()
+        .to_vec();
+    let body = String::from_utf8(bytes).unwrap();
+    assert!(status.is_success(), "{}", body);
+
+    // the login mail goes through the outbox
+    cdg_portal::mail::outbox::process_batch(&app_data)
+        .await
+        .unwrap();
+    let link_prefix = format!(
+        "{}{}/auth?{}=",
+        app_data.general.app_domain,
+        app_data.general.app_path,
+        cdg_portal::Consts::AUTH_COOKIE_NAME
+    );
+    let sent = mailer.sent();
+    let Some(mail) = sent.iter().rev().find(|v| v.to_addrs.contains(&user.email)) else {
+        panic!("no login mail sent to {}", user.email);
+    };
+    assert!(!mail.subject.is_empty());
+    assert!(mail.html.contains(&link_prefix), "{}", mail.html);
+}